@@ -0,0 +1,306 @@
+//! Module relating to the blame view, which shows the commit that last changed each line of a file.
+
+use std::{collections::HashMap, fmt};
+
+use anyhow::{Context, Result};
+use crossterm::{
+    event::{KeyCode, KeyEvent},
+    style::{Attribute, SetForegroundColor},
+};
+
+use crate::{
+    config::{Action, CONFIG},
+    git_process,
+    minibuffer::MiniBuffer,
+    render::{self, Renderer, ResetAttributes},
+};
+
+/// The width of the author's name in the left column.
+const AUTHOR_WIDTH: usize = 16;
+/// The width of the left column, which has the abbreviated hash, the author and the date.
+const LEFT_WIDTH: usize = 8 + 1 + AUTHOR_WIDTH + 1 + 10;
+
+/// A single line of the file from `git blame`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct BlameLine {
+    /// The full hash of the commit that last changed the line. Lines that haven't been committed
+    /// yet have a hash of all zeros.
+    pub hash: String,
+    pub author: String,
+    /// When the commit was authored, in seconds since the Unix epoch.
+    pub author_time: i64,
+    pub content: String,
+}
+
+impl BlameLine {
+    /// Whether the line has been changed since the last commit.
+    fn is_uncommitted(&self) -> bool {
+        self.hash.bytes().all(|b| b == b'0')
+    }
+}
+
+/// Parse the output of `git blame --porcelain`. The details of a commit are only given for the
+/// first line that it's blamed for, so they're remembered for the lines after that.
+fn parse_blame(porcelain: &str) -> Result<Vec<BlameLine>> {
+    let mut commits: HashMap<&str, (&str, i64)> = HashMap::new();
+    let mut blame = Vec::new();
+    let mut lines = porcelain.lines();
+    while let Some(header) = lines.next() {
+        let hash = header
+            .split(' ')
+            .next()
+            .filter(|hash| hash.len() == 40 && hash.bytes().all(|b| b.is_ascii_hexdigit()))
+            .with_context(|| format!("unexpected line in `git blame` output: `{header}`"))?;
+        let (mut author, mut author_time) = commits.get(hash).copied().unwrap_or_default();
+        let content = loop {
+            let line = lines
+                .next()
+                .context("unexpected end of `git blame` output")?;
+            if let Some(content) = line.strip_prefix('\t') {
+                break content;
+            } else if let Some(name) = line.strip_prefix("author ") {
+                author = name;
+            } else if let Some(time) = line.strip_prefix("author-time ") {
+                author_time = time
+                    .parse()
+                    .with_context(|| format!("invalid time in `git blame` output: `{time}`"))?;
+            }
+        };
+        commits.insert(hash, (author, author_time));
+        blame.push(BlameLine {
+            hash: hash.to_string(),
+            author: author.to_string(),
+            author_time,
+            content: content.to_string(),
+        });
+    }
+    Ok(blame)
+}
+
+/// The date of `timestamp` as `YYYY-MM-DD`, in UTC.
+fn date(timestamp: i64) -> String {
+    // https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = timestamp.div_euclid(86_400) + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// The commit that last changed each line of a file.
+#[derive(Debug, Default)]
+pub struct BlameView {
+    path: String,
+    lines: Vec<BlameLine>,
+    /// The index of the selected line.
+    cursor: usize,
+}
+
+impl render::Render for BlameView {
+    fn render(&self, f: &mut Renderer) -> fmt::Result {
+        use fmt::Write;
+        let config = CONFIG.get().expect("config wasn't initialised");
+        // Each commit keeps the same colour, so that its lines can be picked out further down too.
+        let tints = [
+            config.colors.hunk_head,
+            config.colors.heading,
+            config.colors.key,
+        ];
+
+        writeln!(f, "\r{}{}{ResetAttributes}", Attribute::Bold, self.path)?;
+        if self.lines.is_empty() {
+            return write!(
+                f,
+                "\r\n{}The file is empty.{}\r\n\nPress Esc to go back.",
+                SetForegroundColor(config.colors.heading),
+                SetForegroundColor(config.colors.foreground),
+            );
+        }
+
+        let number_width = self.lines.len().to_string().len();
+        for (i, line) in self.lines.iter().enumerate() {
+            if i == self.cursor {
+                f.insert_cursor();
+            }
+            let tint = usize::from_str_radix(&line.hash[..2], 16).unwrap_or_default() % tints.len();
+            write!(f, "\r{}", SetForegroundColor(tints[tint]))?;
+            if i == self.cursor {
+                write!(f, "{}", Attribute::Reverse)?;
+            }
+            // Only the first of the lines in a row from the same commit says which commit it is.
+            if i > 0 && self.lines[i - 1].hash == line.hash {
+                write!(f, "{:LEFT_WIDTH$}", "")?;
+            } else if line.is_uncommitted() {
+                write!(f, "{:LEFT_WIDTH$}", "Not committed yet")?;
+            } else {
+                write!(
+                    f,
+                    "{:.8} {:<AUTHOR_WIDTH$.AUTHOR_WIDTH$} {}",
+                    line.hash,
+                    line.author,
+                    date(line.author_time),
+                )?;
+            }
+            writeln!(
+                f,
+                "{ResetAttributes} {}{:>number_width$}{} │ {}",
+                Attribute::Dim,
+                i + 1,
+                Attribute::NormalIntensity,
+                line.content,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl BlameView {
+    /// Show the blame of the file at `path`. Returns `false` if it can't be blamed, e.g. because it
+    /// isn't tracked, in which case the error from git is shown.
+    pub fn open(&mut self, path: &str) -> Result<bool> {
+        let output = git_process(&["blame", "--porcelain", "--", path])?;
+        if !output.status.success() {
+            MiniBuffer::push_command_output(&output);
+            return Ok(false);
+        }
+        self.lines = parse_blame(
+            std::str::from_utf8(&output.stdout).context("malformed stdout from `git blame`")?,
+        )?;
+        path.clone_into(&mut self.path);
+        self.cursor = 0;
+        Ok(true)
+    }
+
+    /// The hash of the commit that last changed the selected line, unless it hasn't been committed.
+    pub fn selected_commit(&self) -> Option<&str> {
+        self.lines
+            .get(self.cursor)
+            .filter(|line| !line.is_uncommitted())
+            .map(|line| line.hash.as_str())
+    }
+
+    /// Handle a key press. Returns `true` when the user wants to leave the blame view.
+    pub fn handle_input(&mut self, key_event: KeyEvent, action: Option<&Action>) -> bool {
+        let last_line = self.lines.len().saturating_sub(1);
+        match action {
+            Some(Action::MoveDown) => self.cursor = (self.cursor + 1).min(last_line),
+            Some(Action::MoveUp) => self.cursor = self.cursor.saturating_sub(1),
+            // Moving by file moves to the next or previous change of commit instead.
+            Some(Action::NextFile) => {
+                let hash = self.lines.get(self.cursor).map(|line| &line.hash);
+                self.cursor = (self.cursor..self.lines.len())
+                    .find(|&i| Some(&self.lines[i].hash) != hash)
+                    .unwrap_or(last_line);
+            }
+            Some(Action::PreviousFile) => {
+                // Go to the first line of the previous commit, or of this one if the cursor isn't
+                // there already.
+                let start = |end: usize| {
+                    (1..=end)
+                        .rev()
+                        .find(|&i| self.lines[i - 1].hash != self.lines[end].hash)
+                        .unwrap_or_default()
+                };
+                if let Some(previous) = self.cursor.checked_sub(1) {
+                    let current = start(self.cursor);
+                    self.cursor = if current == self.cursor {
+                        start(previous)
+                    } else {
+                        current
+                    };
+                }
+            }
+            Some(Action::GotoTop) => self.cursor = 0,
+            Some(Action::GotoBottom) => self.cursor = last_line,
+            _ => {}
+        }
+        matches!(key_event.code, KeyCode::Esc | KeyCode::Char('q'))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::BlameLine;
+
+    #[test]
+    fn parse_blame() {
+        const INPUT: &str = "\
+2bc8724a1f0e6d7c3b9a8e5f4d2c1b0a9e8f7d6c 1 1 2
+author Jane Doe
+author-mail <jane@example.com>
+author-time 1700000000
+author-tz +0100
+committer Jane Doe
+committer-mail <jane@example.com>
+committer-time 1700000000
+committer-tz +0100
+summary Add the parser
+filename src/parse.rs
+\tfn parse() {
+2bc8724a1f0e6d7c3b9a8e5f4d2c1b0a9e8f7d6c 2 2
+\t
+0000000000000000000000000000000000000000 3 3 1
+author Not Committed Yet
+author-mail <not.committed.yet>
+author-time 1700086400
+author-tz +0000
+committer Not Committed Yet
+committer-mail <not.committed.yet>
+committer-time 1700086400
+committer-tz +0000
+summary Version of src/parse.rs from src/parse.rs
+previous 2bc8724a1f0e6d7c3b9a8e5f4d2c1b0a9e8f7d6c src/parse.rs
+filename src/parse.rs
+\t}
+";
+        let line = |hash: &str, author: &str, author_time, content: &str| BlameLine {
+            hash: hash.to_string(),
+            author: author.to_string(),
+            author_time,
+            content: content.to_string(),
+        };
+        assert_eq!(
+            super::parse_blame(INPUT).unwrap(),
+            [
+                line(
+                    "2bc8724a1f0e6d7c3b9a8e5f4d2c1b0a9e8f7d6c",
+                    "Jane Doe",
+                    1_700_000_000,
+                    "fn parse() {"
+                ),
+                line(
+                    "2bc8724a1f0e6d7c3b9a8e5f4d2c1b0a9e8f7d6c",
+                    "Jane Doe",
+                    1_700_000_000,
+                    ""
+                ),
+                line(
+                    "0000000000000000000000000000000000000000",
+                    "Not Committed Yet",
+                    1_700_086_400,
+                    "}"
+                ),
+            ]
+        );
+    }
+
+    #[test_case(0, "1970-01-01" ; "epoch")]
+    #[test_case(951_782_400, "2000-02-29" ; "leap day")]
+    #[test_case(1_700_000_000, "2023-11-14" ; "recent")]
+    #[test_case(-86_400, "1969-12-31" ; "before epoch")]
+    fn date(timestamp: i64, expected: &str) {
+        assert_eq!(super::date(timestamp), expected);
+    }
+}
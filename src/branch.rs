@@ -1,18 +1,117 @@
-use std::{fmt, process::Output};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt,
+    process::{Command, Output},
+    rc::Rc,
+    sync::atomic::Ordering,
+};
 
 use anyhow::{Context, Result};
-use crossterm::style::{Attribute, SetForegroundColor};
+use crossterm::{
+    cursor,
+    event::{KeyCode, KeyEvent},
+    style::{Attribute, SetForegroundColor},
+    terminal,
+};
 
 use crate::{
-    config::CONFIG,
+    command,
+    config::{Action, CONFIG},
+    error::GexError,
     git_process,
     minibuffer::{MessageType, MiniBuffer},
+    parse,
     render::{self, Renderer, ResetAttributes},
+    status, View,
 };
 
+/// The sort key for listing the branches with the most recently committed first.
+const SORT_RECENT: &str = "-committerdate";
+/// The sort key for listing the branches by name.
+const SORT_NAME: &str = "refname";
+
 pub struct BranchList {
+    /// The lines of `git branch`, e.g. `* main` or `  remotes/origin/main`.
     pub branches: Vec<String>,
+    /// The index of the selected branch in [`BranchList::matches`].
     pub cursor: usize,
+    /// Whether remote-tracking branches are listed too.
+    pub show_remotes: bool,
+    /// The key that the branches are sorted by, as passed to `git branch --sort`.
+    sort: String,
+    /// The number of commits each local branch is ahead of and behind its upstream. Branches
+    /// without an upstream, or whose upstream is gone, aren't included.
+    pub ahead_behind: HashMap<String, (usize, usize)>,
+    /// How long ago the tip of each branch was committed and its subject, by the full name of the
+    /// ref, e.g. `refs/heads/main`.
+    tips: HashMap<String, (String, String)>,
+    /// Only branches whose names fuzzy-match this are listed, ignoring case.
+    filter: String,
+    /// Whether the keys typed are being added to [`BranchList::filter`].
+    searching: bool,
+    /// The indices of the branches that match the filter.
+    matches: Vec<usize>,
+    /// The new name of a branch that was just renamed, which the cursor should follow when the list
+    /// is fetched again. It's shared with the minibuffer callback that does the renaming.
+    renamed: Rc<RefCell<Option<String>>>,
+}
+
+/// The remote-tracking branch that a line of `git branch -a` is for, e.g. `origin/main`.
+fn remote_branch(line: &str) -> Option<&str> {
+    line.get(2..)?.strip_prefix("remotes/")
+}
+
+/// The name of the branch that a line of `git branch` is for, as it's displayed.
+fn branch_name(line: &str) -> &str {
+    remote_branch(line).unwrap_or_else(|| line.get(2..).unwrap_or_default())
+}
+
+/// The full name of the ref that a line of `git branch` is for, e.g. `refs/heads/main`. There isn't
+/// one when `HEAD` is detached.
+fn refname(line: &str) -> Option<String> {
+    remote_branch(line).map_or_else(
+        || {
+            line.get(2..)
+                .filter(|name| !name.starts_with('('))
+                .map(|name| format!("refs/heads/{name}"))
+        },
+        |remote| Some(format!("refs/remotes/{remote}")),
+    )
+}
+
+/// `s` cut off with an ellipsis if it's longer than `width` characters.
+fn truncate(s: &str, width: usize) -> String {
+    if s.chars().count() <= width {
+        s.to_string()
+    } else {
+        let mut truncated = s.chars().take(width.saturating_sub(1)).collect::<String>();
+        if width > 0 {
+            truncated.push('…');
+        }
+        truncated
+    }
+}
+
+/// How well `name` matches `filter`, ignoring case, or `None` if it doesn't. Lower is better: an
+/// exact match comes first, then a prefix, then a substring, and then names that only contain the
+/// characters of the filter in order, with fewer characters between them being better.
+fn match_score(name: &str, filter: &str) -> Option<usize> {
+    let name = name.to_lowercase();
+    let filter = filter.to_lowercase();
+    if name == filter {
+        return Some(0);
+    } else if name.starts_with(&filter) {
+        return Some(1);
+    } else if name.contains(&filter) {
+        return Some(2);
+    }
+    let mut gaps = 0;
+    let mut chars = name.chars();
+    for c in filter.chars() {
+        gaps += chars.by_ref().position(|n| n == c)?;
+    }
+    Some(3 + gaps)
 }
 
 impl render::Render for BranchList {
@@ -29,22 +128,86 @@ impl render::Render for BranchList {
             );
         }
 
-        for (i, branch) in self.branches.iter().enumerate() {
-            if branch.starts_with('*') {
-                write!(f, "{}", SetForegroundColor(config.colors.heading))?;
-            }
+        let term_width = terminal::size().map_or(0, |(width, _)| usize::from(width));
+        // The names and ages are lined up in columns. A name that's much longer than the others
+        // would leave no room for the subjects, so the names are cut off after a third of the
+        // width.
+        let branches = self.matches.iter().map(|&i| &self.branches[i]);
+        let name_width = branches
+            .clone()
+            .map(|branch| branch_name(branch).chars().count())
+            .max()
+            .unwrap_or_default()
+            .min(term_width / 3);
+        let tip = |branch: &str| refname(branch).and_then(|refname| self.tips.get(&refname));
+        let age_width = branches
+            .clone()
+            .filter_map(|branch| tip(branch))
+            .map(|(age, _)| age.chars().count())
+            .max()
+            .unwrap_or_default();
+        for (i, branch) in branches.enumerate() {
+            let (marker, _) = branch.split_at(2);
+            let color = if branch.starts_with('*') {
+                config.colors.heading
+            } else if remote_branch(branch).is_some() {
+                config.colors.hunk_head
+            } else {
+                config.colors.foreground
+            };
             if i == self.cursor {
-                let mut branch = branch.to_string();
-                branch.insert_str(2, &format!("{}", Attribute::Reverse));
-                write!(&mut branch, "{ResetAttributes}")?;
                 f.insert_cursor();
-                writeln!(f, "\r{branch}")?;
-            } else {
-                writeln!(f, "\r{branch}")?;
             }
-            if branch.starts_with('*') {
-                write!(f, "{}", SetForegroundColor(config.colors.foreground))?;
+            write!(f, "\r{}{marker}", SetForegroundColor(color))?;
+            if i == self.cursor {
+                write!(f, "{}", Attribute::Reverse)?;
+            }
+            let name = branch_name(branch);
+            let shown_name = truncate(name, name_width);
+            write!(f, "{shown_name}{ResetAttributes}")?;
+            let mut used = marker.chars().count() + shown_name.chars().count();
+            let ahead_behind = self.ahead_behind.get(name).copied();
+            let counts = ahead_behind.map(|ahead_behind| match ahead_behind {
+                (0, 0) => "✓".to_string(),
+                (ahead, 0) => format!("↑{ahead}"),
+                (0, behind) => format!("↓{behind}"),
+                (ahead, behind) => format!("↑{ahead} ↓{behind}"),
+            });
+            if let Some((age, subject)) = tip(branch) {
+                // The marker, the name and the age, with two spaces after each of the last two.
+                let columns = marker.chars().count() + name_width + 2 + age_width + 2;
+                let counts_width = counts.as_ref().map_or(0, |c| c.chars().count() + 2);
+                let subject = truncate(subject, term_width.saturating_sub(columns + counts_width));
+                write!(
+                    f,
+                    "{:padding$}  {}{age:age_width$}{ResetAttributes}  {subject}",
+                    "",
+                    Attribute::Dim,
+                    padding = name_width.saturating_sub(shown_name.chars().count()),
+                )?;
+                used = columns + subject.chars().count();
+            }
+            if let Some(counts) = counts {
+                let padding = term_width.saturating_sub(used + counts.chars().count());
+                write!(f, "{:padding$}", "")?;
+                if ahead_behind == Some((0, 0)) {
+                    write!(f, "{}{counts}{ResetAttributes}", Attribute::Dim)?;
+                } else {
+                    write!(f, "{counts}")?;
+                }
             }
+            writeln!(f)?;
+        }
+        if self.matches.is_empty() {
+            writeln!(f, "\r{}No branches match.{ResetAttributes}", Attribute::Dim)?;
+        }
+        if self.searching || !self.filter.is_empty() {
+            write!(
+                f,
+                "\r\n{}/{ResetAttributes}{}",
+                SetForegroundColor(config.colors.key),
+                self.filter
+            )?;
         }
         Ok(())
     }
@@ -52,51 +215,467 @@ impl render::Render for BranchList {
 
 impl BranchList {
     pub fn new() -> Result<Self> {
+        let config = CONFIG.get().expect("config wasn't initialised");
         let mut branch_list = Self {
             branches: Vec::new(),
             cursor: 0,
+            show_remotes: false,
+            sort: config
+                .options
+                .sort_branches
+                .clone()
+                .unwrap_or_else(|| SORT_RECENT.to_string()),
+            ahead_behind: HashMap::new(),
+            tips: HashMap::new(),
+            filter: String::new(),
+            searching: false,
+            matches: Vec::new(),
+            renamed: Rc::default(),
         };
         branch_list.fetch()?;
         Ok(branch_list)
     }
 
     pub fn fetch(&mut self) -> Result<()> {
-        let config = CONFIG.get().expect("config wasn't initialised");
-
-        let output = match config.options.sort_branches.as_ref() {
-            Some(sort_value) => {
-                let output = git_process(&["branch", &format!("--sort={sort_value}")])?;
-                if output.status.success() {
-                    output
-                } else {
-                    MiniBuffer::push(
-                        &format!(
-                            "`git branch --sort={sort_value}` failed!\n\n{}",
-                            String::from_utf8_lossy(&output.stderr)
-                        ),
-                        MessageType::Error,
-                    );
-                    git_process(&["branch"])?
-                }
-            }
-            None => git_process(&["branch"])?,
+        let args: &[_] = if self.show_remotes {
+            &["branch", "--all"]
+        } else {
+            &["branch"]
+        };
+        let sort = format!("--sort={}", self.sort);
+        let output = git_process(&[args, &[&sort]].concat())?;
+        let output = if output.status.success() {
+            output
+        } else {
+            MiniBuffer::push(
+                &format!(
+                    "`git branch {sort}` failed!\n\n{}",
+                    String::from_utf8_lossy(&output.stderr)
+                ),
+                MessageType::Error,
+            );
+            GexError::run("branch", args)?
         };
 
         self.branches = std::str::from_utf8(&output.stdout)
-            .context("broken stdout from `git branch`")?
+            .map_err(|_| GexError::Utf8Error("branch"))?
             .lines()
+            // Skip the remotes' default branches, e.g. `remotes/origin/HEAD -> origin/main`.
+            .filter(|l| !l.contains(" -> "))
             .map(|l| l.to_string())
             .collect::<Vec<_>>();
+        self.update_matches();
+        let renamed = self.renamed.borrow_mut().take();
+        if let Some(cursor) = renamed.and_then(|renamed| {
+            self.matches.iter().position(|&i| {
+                remote_branch(&self.branches[i]).is_none()
+                    && branch_name(&self.branches[i]) == renamed
+            })
+        }) {
+            self.cursor = cursor;
+        }
+        self.cursor = self.cursor.min(self.matches.len().saturating_sub(1));
+
+        // The subject is last since it's the only field that could have a tab in it.
+        let format = "--format=%(refname)\t%(upstream)\t%(upstream:track)\t\
+                      %(committerdate:relative)\t%(contents:subject)";
+        let refs: &[_] = if self.show_remotes {
+            &["refs/heads/", "refs/remotes/"]
+        } else {
+            &["refs/heads/"]
+        };
+        let output = GexError::run("for-each-ref", &[&["for-each-ref", format], refs].concat())?;
+        self.ahead_behind = HashMap::new();
+        self.tips = HashMap::new();
+        for line in std::str::from_utf8(&output.stdout)
+            .map_err(|_| GexError::Utf8Error("for-each-ref"))?
+            .lines()
+        {
+            let mut fields = line.splitn(5, '\t');
+            let (Some(refname), Some(upstream), Some(track), Some(age), subject) = (
+                fields.next(),
+                fields.next(),
+                fields.next(),
+                fields.next(),
+                fields.next(),
+            ) else {
+                continue;
+            };
+            let subject = subject
+                .unwrap_or_default()
+                .replace(|c: char| c.is_control(), "\u{fffd}");
+            self.tips
+                .insert(refname.to_string(), (age.to_string(), subject));
+            let Some(branch) = refname.strip_prefix("refs/heads/") else {
+                continue;
+            };
+            if upstream.is_empty() {
+                continue;
+            }
+            if let Some(counts) = parse::parse_upstream_track(track)
+                .map_err(|e| GexError::ParseError(format!("{e:#}")))?
+            {
+                self.ahead_behind.insert(branch.to_string(), counts);
+            }
+        }
 
         Ok(())
     }
 
+    /// Check out the selected branch. Checking out a remote-tracking branch creates a local branch
+    /// that tracks it, or checks out the local branch of the same name if there already is one.
     pub fn checkout(&self) -> Result<Output> {
-        git_process(&["checkout", &self.branches[self.cursor][2..]])
+        let branch = self
+            .matches
+            .get(self.cursor)
+            .map(|&i| &self.branches[i])
+            .context("there's no branch to check out")?;
+        let args: &[_] = match remote_branch(branch) {
+            None => &["checkout", &branch[2..]],
+            Some(remote) => {
+                let local_branch = remote.split_once('/').map_or(remote, |(_, name)| name);
+                let local_exists = self
+                    .branches
+                    .iter()
+                    .any(|b| remote_branch(b).is_none() && &b[2..] == local_branch);
+                if local_exists {
+                    &["checkout", local_branch]
+                } else {
+                    &["checkout", "--track", remote]
+                }
+            }
+        };
+        Ok(GexError::run("checkout", args)?)
     }
 
-    pub fn checkout_new(name: &str) -> Result<()> {
-        MiniBuffer::push_command_output(&git_process(&["checkout", "-b", name])?);
+    /// Find the branches that match the filter. They stay in the order of `git branch`.
+    fn update_matches(&mut self) {
+        self.matches = (0..self.branches.len())
+            .filter(|&i| match_score(branch_name(&self.branches[i]), &self.filter).is_some())
+            .collect();
+    }
+
+    /// The index in [`BranchList::matches`] of the branch that matches the filter best.
+    fn best_match(&self) -> usize {
+        self.matches
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, &i)| match_score(branch_name(&self.branches[i]), &self.filter))
+            .map_or(0, |(cursor, _)| cursor)
+    }
+
+    /// Whether the keys typed are being used to search for a branch.
+    pub const fn is_searching(&self) -> bool {
+        self.searching
+    }
+
+    /// Ask whether to delete the selected branch, and delete it with `git branch -d` if the user
+    /// agrees.
+    fn delete(&self, minibuffer: &mut MiniBuffer, view: &mut View) {
+        let Some(branch) = self.matches.get(self.cursor).map(|&i| &self.branches[i]) else {
+            return;
+        };
+        if branch.starts_with('*') {
+            MiniBuffer::push("Can't delete the current branch.", MessageType::Error);
+            return;
+        }
+        if remote_branch(branch).is_some() {
+            MiniBuffer::push(
+                "Remote-tracking branches can't be deleted from here.",
+                MessageType::Error,
+            );
+            return;
+        }
+        let name = branch_name(branch).to_string();
+        let prompt = format!("Delete branch {name}?");
+        minibuffer.confirm_then(
+            Rc::new(move || {
+                // The message isn't translated, since whether it's merged is told from it.
+                let output = Command::new("git")
+                    .args(["branch", "-d", &name])
+                    .env("LC_ALL", "C")
+                    .output()
+                    .context("failed to run `git branch -d`")?;
+                status::REFRESH_FLAG.store(true, Ordering::Release);
+                if String::from_utf8_lossy(&output.stderr).contains("not fully merged") {
+                    let name = name.clone();
+                    return Ok(Some(Box::new(move |minibuffer, view| {
+                        Self::confirm_force_delete(name, minibuffer, view);
+                    })));
+                }
+                MiniBuffer::push_command_output(&output);
+                Ok(None)
+            }),
+            &prompt,
+            view,
+            View::BranchList,
+        );
+    }
+
+    /// Ask for a new name for the selected branch, starting from its current name, and rename it
+    /// with `git branch -m`. With `push_after_rename` set, renaming the current branch renames it
+    /// on `origin` as well, in the background.
+    fn rename(&self, minibuffer: &mut MiniBuffer, view: &mut View) {
+        let Some(branch) = self.matches.get(self.cursor).map(|&i| &self.branches[i]) else {
+            return;
+        };
+        if remote_branch(branch).is_some() {
+            MiniBuffer::push(
+                "Remote-tracking branches can't be renamed from here.",
+                MessageType::Error,
+            );
+            return;
+        }
+        let old = branch_name(branch).to_string();
+        let push =
+            branch.starts_with('*') && CONFIG.get().is_some_and(|c| c.options.push_after_rename);
+        let renamed = Rc::clone(&self.renamed);
+        minibuffer.get_input(
+            Rc::new(move |new| {
+                print!("{}", cursor::Hide);
+                let Some(new) = new
+                    .map(str::trim)
+                    .filter(|new| !new.is_empty() && *new != old)
+                else {
+                    return Ok(());
+                };
+                let output = git_process(&["branch", "-m", &old, new])?;
+                MiniBuffer::push_command_output(&output);
+                if output.status.success() {
+                    *renamed.borrow_mut() = Some(new.to_string());
+                }
+                status::REFRESH_FLAG.store(true, Ordering::Release);
+                if !output.status.success() || !push {
+                    return Ok(());
+                }
+                // Pushing both at once means the old name is only deleted if the new one could be
+                // pushed, and the upstream is moved to the new name, since the old one is gone.
+                command::run_push(&[
+                    "push",
+                    "--atomic",
+                    "--set-upstream",
+                    "origin",
+                    &format!("{new}:{new}"),
+                    &format!(":{old}"),
+                ])
+            }),
+            Some("Rename branch to: "),
+            view,
+            View::BranchList,
+        );
+        minibuffer.prefill(branch_name(branch));
+    }
+
+    /// Ask whether to force delete a branch that [`BranchList::delete`] couldn't delete because it
+    /// isn't fully merged.
+    fn confirm_force_delete(name: String, minibuffer: &mut MiniBuffer, view: &mut View) {
+        minibuffer.confirm(
+            Rc::new(move || {
+                MiniBuffer::push_command_output(&git_process(&["branch", "-D", &name])?);
+                status::REFRESH_FLAG.store(true, Ordering::Release);
+                Ok(())
+            }),
+            "Branch is not fully merged. Force delete?",
+            view,
+            View::BranchList,
+        );
+    }
+
+    /// Handle a key press. Returns `true` when the user wants to leave the branch list.
+    pub fn handle_input(
+        &mut self,
+        key_event: KeyEvent,
+        action: Option<&Action>,
+        minibuffer: &mut MiniBuffer,
+        view: &mut View,
+    ) -> Result<bool> {
+        if self.searching {
+            match key_event.code {
+                KeyCode::Char(c) => self.filter.push(c),
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Esc => {
+                    self.filter.clear();
+                    self.searching = false;
+                }
+                // Check out the selected match straight away, like Enter does without the filter.
+                KeyCode::Enter => self.searching = false,
+                _ => return Ok(false),
+            }
+            if self.searching || key_event.code == KeyCode::Esc {
+                self.update_matches();
+                self.cursor = self.best_match();
+                return Ok(false);
+            }
+        }
+
+        let last_match = self.matches.len().saturating_sub(1);
+        match action {
+            Some(Action::MoveDown) => self.cursor = (self.cursor + 1).min(last_match),
+            Some(Action::MoveUp) => self.cursor = self.cursor.saturating_sub(1),
+            Some(Action::GotoTop) => self.cursor = 0,
+            Some(Action::GotoBottom) => self.cursor = last_match,
+            _ => {}
+        }
+        match key_event.code {
+            KeyCode::Char(' ') | KeyCode::Enter => {
+                MiniBuffer::push_command_output(&self.checkout()?);
+                status::REFRESH_FLAG.store(true, Ordering::Release);
+            }
+            KeyCode::Char('/') => {
+                self.searching = true;
+                return Ok(false);
+            }
+            KeyCode::Char('a') => {
+                self.toggle_remotes()?;
+                return Ok(false);
+            }
+            KeyCode::Char('d') => {
+                self.delete(minibuffer, view);
+                return Ok(false);
+            }
+            KeyCode::Char('R') => {
+                self.rename(minibuffer, view);
+                return Ok(false);
+            }
+            KeyCode::Char('s') => {
+                self.cycle_sort()?;
+                return Ok(false);
+            }
+            KeyCode::Char('m') => {
+                let Some(branch) = self.matches.get(self.cursor).map(|&i| &self.branches[i]) else {
+                    return Ok(false);
+                };
+                command::merge(branch_name(branch), view)?;
+                self.filter.clear();
+                return Ok(false);
+            }
+            KeyCode::Esc => {}
+            _ => return Ok(false),
+        }
+        // The filter shouldn't still be there the next time the list is opened.
+        self.filter.clear();
+        Ok(true)
+    }
+
+    /// Show or hide the remote-tracking branches.
+    pub fn toggle_remotes(&mut self) -> Result<()> {
+        self.show_remotes = !self.show_remotes;
+        self.fetch()
+    }
+
+    /// Switch between listing the most recently committed branches first and listing them by name.
+    /// The cursor stays on the same branch.
+    pub fn cycle_sort(&mut self) -> Result<()> {
+        let selected = self
+            .matches
+            .get(self.cursor)
+            .map(|&i| self.branches[i].clone());
+        let message = if self.sort == SORT_RECENT {
+            SORT_NAME.clone_into(&mut self.sort);
+            "Sorted by name"
+        } else {
+            SORT_RECENT.clone_into(&mut self.sort);
+            "Sorted by most recent commit"
+        };
+        self.fetch()?;
+        if let Some(cursor) = selected.and_then(|selected| {
+            self.matches
+                .iter()
+                .position(|&i| self.branches[i] == selected)
+        }) {
+            self.cursor = cursor;
+        }
+        MiniBuffer::push(message, MessageType::Note);
         Ok(())
     }
+
+    /// Ask for the name of a new branch and check it out with `git checkout -b`.
+    pub fn new_branch(minibuffer: &mut MiniBuffer, view: &mut View) {
+        Self::ask_new_branch("Name for the new branch: ", minibuffer, view);
+    }
+
+    fn ask_new_branch(prompt: &str, minibuffer: &mut MiniBuffer, view: &mut View) {
+        minibuffer.get_input_then(
+            Rc::new(|input| {
+                print!("{}", cursor::Hide);
+                let Some(input) = input else {
+                    return Ok(None);
+                };
+                status::REFRESH_FLAG.store(true, Ordering::Release);
+                let Some((name, reason)) = Self::checkout_new(input)? else {
+                    return Ok(None);
+                };
+                Ok(Some(Box::new(move |minibuffer, view| {
+                    Self::retry_new_branch(&name, &reason, minibuffer, view);
+                })))
+            }),
+            Some(prompt),
+            view,
+            View::Status,
+        );
+    }
+
+    /// Create a branch called `name` and check it out. An empty name does nothing. If the name
+    /// isn't valid or the branch can't be created, e.g. because it already exists, the name is
+    /// returned along with the reason, so that the user can be asked for it again.
+    fn checkout_new(name: &str) -> Result<Option<(String, String)>> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Ok(None);
+        }
+        // This catches names that git would take as an option too, such as `-b`.
+        let output = GexError::run("check-ref-format", &["check-ref-format", "--branch", name])
+            .and_then(|_| GexError::run("checkout", &["checkout", "-b", name]));
+        let stderr = match output {
+            Ok(output) => {
+                MiniBuffer::push_command_output(&output);
+                return Ok(None);
+            }
+            Err(GexError::Failed { stderr, .. }) => stderr,
+            Err(e) => return Err(e.into()),
+        };
+        // The reason goes in the prompt, since a message would replace the input.
+        let mut reason = stderr.trim_start_matches("fatal: ").chars();
+        let reason = reason.next().map_or_else(
+            || "The branch couldn't be created".to_string(),
+            |first| first.to_uppercase().chain(reason).collect(),
+        );
+        Ok(Some((name.to_string(), reason)))
+    }
+
+    /// Ask for the name of the new branch again, starting from the name that was entered, since
+    /// [`BranchList::checkout_new`] couldn't create it.
+    fn retry_new_branch(name: &str, reason: &str, minibuffer: &mut MiniBuffer, view: &mut View) {
+        Self::ask_new_branch(
+            &format!("{reason}. Name for the new branch: "),
+            minibuffer,
+            view,
+        );
+        minibuffer.prefill(name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    #[test_case("main", "main", Some(0) ; "exact")]
+    #[test_case("Feature/login", "feat", Some(1) ; "prefix ignoring case")]
+    #[test_case("origin/main", "main", Some(2) ; "substring")]
+    #[test_case("feature/login", "flog", Some(10) ; "fuzzy")]
+    #[test_case("feature/login", "featre", Some(4) ; "fuzzy with a small gap")]
+    #[test_case("main", "mian", None ; "out of order")]
+    #[test_case("main", "", Some(1) ; "empty filter")]
+    fn match_score(name: &str, filter: &str, expected: Option<usize>) {
+        assert_eq!(super::match_score(name, filter), expected);
+    }
+
+    #[test_case("fix/parser", 10, "fix/parser" ; "fits")]
+    #[test_case("fix/parser", 6, "fix/p…" ; "too long")]
+    #[test_case("fix/parser", 0, "" ; "no room")]
+    fn truncate(s: &str, width: usize, expected: &str) {
+        assert_eq!(super::truncate(s, width), expected);
+    }
 }
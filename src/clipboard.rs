@@ -0,0 +1,74 @@
+//! Module relating to copying text to the system clipboard, which is done by piping it to a
+//! clipboard program of the platform.
+
+use std::{
+    fmt,
+    io::Write,
+    process::{Command, Stdio},
+};
+
+/// The programs that can set the clipboard, and the arguments to pass them, in the order they are
+/// tried.
+#[cfg(target_os = "macos")]
+const BACKENDS: &[(&str, &[&str])] = &[("pbcopy", &[])];
+#[cfg(windows)]
+const BACKENDS: &[(&str, &[&str])] = &[(
+    "powershell",
+    &[
+        "-NoProfile",
+        "-Command",
+        "Set-Clipboard -Value ([Console]::In.ReadToEnd())",
+    ],
+)];
+#[cfg(not(any(target_os = "macos", windows)))]
+const BACKENDS: &[(&str, &[&str])] = &[
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+];
+
+#[derive(Debug)]
+pub enum ClipboardError {
+    /// None of the clipboard programs could be run.
+    Unavailable,
+    /// A clipboard program was run but didn't succeed.
+    Failed(&'static str),
+}
+
+impl fmt::Display for ClipboardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unavailable => write!(f, "Clipboard not available"),
+            Self::Failed(program) => write!(f, "`{program}` failed to copy to the clipboard"),
+        }
+    }
+}
+
+impl std::error::Error for ClipboardError {}
+
+/// Copy `text` to the system clipboard with the first clipboard program that can be run.
+pub fn copy(text: &str) -> Result<(), ClipboardError> {
+    for (program, args) in BACKENDS {
+        // xclip forks into the background to hold on to the clipboard once its input is closed, so
+        // waiting only waits for the fork. Its output is discarded since the fork keeps it open.
+        let Ok(mut child) = Command::new(program)
+            .args(*args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        else {
+            continue;
+        };
+        let written = child
+            .stdin
+            .take()
+            .is_some_and(|mut stdin| stdin.write_all(text.as_bytes()).is_ok());
+        let succeeded = child.wait().is_ok_and(|status| status.success());
+        return if written && succeeded {
+            Ok(())
+        } else {
+            Err(ClipboardError::Failed(program))
+        };
+    }
+    Err(ClipboardError::Unavailable)
+}
@@ -1,15 +1,23 @@
 use std::{
     fmt,
     io::stdout,
-    process::{Command, Stdio},
+    process::{Child, Command, Output, Stdio},
     rc::Rc,
-    sync::atomic::Ordering,
+    sync::{atomic::Ordering, Mutex},
+    thread::{self, JoinHandle},
+    time::Instant,
 };
 
 use anyhow::{Context, Result};
-use crossterm::{cursor, terminal};
+use crossterm::{cursor, style, terminal};
 
-use crate::{branch::BranchList, git_process, minibuffer::MiniBuffer, status, State, View};
+use crate::{
+    branch::BranchList,
+    commit::CommitEditor,
+    git_process, interactive_process,
+    minibuffer::{MessageType, MiniBuffer},
+    remote_git, render, status, State, View,
+};
 
 macro_rules! commands {
     ($($key:literal: $cmd:tt => [$($subkey:literal: $subcmd:tt),+$(,)?]),*$(,)?) => {
@@ -58,11 +66,439 @@ macro_rules! commands {
     }
 }
 
+/// Whether HEAD points at a commit, i.e. the current branch isn't one that has just been created
+/// by `git init` or `git checkout --orphan`.
+pub fn has_commits() -> Result<bool> {
+    Ok(git_process(&["rev-parse", "--verify", "--quiet", "HEAD"])?
+        .status
+        .success())
+}
+
+/// Run `git commit` with `args`, which may open the editor. Returns whether a commit was made,
+/// which isn't the case when e.g. the editor was closed without saving the message.
+pub fn commit(args: &[&str]) -> Result<bool> {
+    let output = interactive_process(Command::new("git").arg("commit").args(args))?;
+    MiniBuffer::push_command_output(&output);
+    Ok(output.status.success())
+}
+
+/// Amend the last commit, opening the editor for the message unless `no_edit` is set. Returns
+/// whether the commit was amended.
+pub fn amend(no_edit: bool) -> Result<bool> {
+    if !has_commits()? {
+        MiniBuffer::push("There's no commit to amend yet.", MessageType::Error);
+        return Ok(false);
+    }
+    commit(if no_edit {
+        &["--amend", "--no-edit"]
+    } else {
+        &["--amend"]
+    })
+}
+
+/// Start an interactive rebase of the last `count` commits, where `count` is what the user entered.
+fn rebase_interactive(count: &str) -> Result<()> {
+    let Some(count) = count
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .filter(|count| *count > 0)
+    else {
+        MiniBuffer::push(
+            &format!("`{}` isn't a number of commits.", count.trim()),
+            MessageType::Error,
+        );
+        return Ok(());
+    };
+    let commits = git_process(&["rev-list", "--count", "HEAD"])?;
+    let commits = std::str::from_utf8(&commits.stdout)
+        .context("invalid utf8 from `git rev-list`")?
+        .trim()
+        .parse::<usize>()
+        .unwrap_or_default();
+    if count > commits {
+        MiniBuffer::push(
+            &format!("There are only {commits} commits to rebase."),
+            MessageType::Error,
+        );
+        return Ok(());
+    }
+    // The first commit has no parent to count back to, so the whole history is rebased instead.
+    let upstream = if count == commits {
+        "--root".to_owned()
+    } else {
+        format!("HEAD~{count}")
+    };
+    MiniBuffer::push_command_output(&interactive_process(
+        Command::new("git").args(["rebase", "-i", &upstream]),
+    )?);
+    status::REFRESH_FLAG.store(true, Ordering::Release);
+    Ok(())
+}
+
+/// Ask for the number of commits to rebase interactively, or for how to go on with the rebase that
+/// is in progress if there is one.
+pub fn rebase(minibuffer: &mut MiniBuffer, view: &mut View) -> Result<()> {
+    if status::rebase_in_progress()?.is_some() {
+        *view = View::Command(GexCommand::Rebase);
+    } else {
+        ask_rebase_count(minibuffer, view);
+    }
+    Ok(())
+}
+
+/// Apply the changes of the commit `hash` onto the current branch, or show how to go on with the
+/// cherry-pick that is in progress if there is one. Any conflicts are shown in the status once it's
+/// refreshed.
+pub fn cherry_pick(hash: &str, view: &mut View) -> Result<()> {
+    if status::cherry_pick_in_progress()? {
+        *view = View::Command(GexCommand::CherryPick);
+        return Ok(());
+    }
+    MiniBuffer::push_command_output(&git_process(&["cherry-pick", hash])?);
+    status::REFRESH_FLAG.store(true, Ordering::Release);
+    *view = View::Status;
+    Ok(())
+}
+
+/// Merge `branch` into the current branch, or show how to go on with the merge that is in progress
+/// if there is one. Any conflicts are shown in the status once it's refreshed.
+pub fn merge(branch: &str, view: &mut View) -> Result<()> {
+    if status::merge_in_progress()? {
+        *view = View::Command(GexCommand::Merge);
+        return Ok(());
+    }
+    // Whether there was anything to merge is told by HEAD moving, since git's messages may be
+    // translated.
+    let head = || git_process(&["rev-parse", "--verify", "--quiet", "HEAD"]).map(|o| o.stdout);
+    let head_before = head()?;
+    // The default message is used for a merge commit, since the editor would be opened otherwise.
+    let output = git_process(&["merge", "--no-edit", branch])?;
+    if status::merge_in_progress()? {
+        MiniBuffer::push(
+            &format!("Merging {branch} stopped with conflicts."),
+            MessageType::Error,
+        );
+    } else if output.status.success() && head()? == head_before {
+        MiniBuffer::push(
+            &format!("Already up to date with {branch}."),
+            MessageType::Note,
+        );
+    } else {
+        // This says whether it was a fast-forward, and how many files changed.
+        MiniBuffer::push_command_output(&output);
+    }
+    status::REFRESH_FLAG.store(true, Ordering::Release);
+    *view = View::Status;
+    Ok(())
+}
+
+/// Ask for the number of commits to rebase interactively.
+fn ask_rebase_count(minibuffer: &mut MiniBuffer, view: &mut View) {
+    minibuffer.get_input(
+        Rc::new(|input| {
+            print!("{}", cursor::Hide);
+            if let Some(count) = input {
+                rebase_interactive(count)?;
+            }
+            Ok(())
+        }),
+        Some("Number of commits to rebase: "),
+        view,
+        View::Status,
+    );
+}
+
+/// Push the current branch. If it doesn't have an upstream yet then the user is asked whether to
+/// push it to `origin` and set that as the upstream instead.
+pub fn push(force: bool, minibuffer: &mut MiniBuffer, view: &mut View) -> Result<()> {
+    if !has_commits()? {
+        MiniBuffer::push("There's nothing to push yet.", MessageType::Error);
+        return Ok(());
+    }
+    let has_upstream = git_process(&["rev-parse", "--abbrev-ref", "--symbolic-full-name", "@{u}"])?
+        .status
+        .success();
+    let branch = git_process(&["symbolic-ref", "--quiet", "--short", "HEAD"])?;
+    let branch = std::str::from_utf8(&branch.stdout)
+        .context("invalid utf8 from `git symbolic-ref`")?
+        .trim()
+        .to_string();
+
+    let mut args = vec!["push"];
+    if force {
+        args.push("--force");
+    }
+    // A detached HEAD doesn't have a branch to set the upstream of, so let git report the error.
+    if has_upstream || branch.is_empty() {
+        return run_push(&args);
+    }
+
+    let prompt = format!("{branch} has no upstream. Push it to origin?");
+    minibuffer.confirm(
+        Rc::new(move || {
+            let mut args = args.clone();
+            args.extend(["--set-upstream", "origin", &branch]);
+            run_push(&args)
+        }),
+        &prompt,
+        view,
+        View::Status,
+    );
+    Ok(())
+}
+
+/// Pull from the upstream. Any conflicts are listed in the output, and the unmerged paths are
+/// shown in the status once it's refreshed.
+pub fn pull() -> Result<()> {
+    run_remote(&["pull"], "Pulling...")
+}
+
+/// Push the tag `name` in the background, to the remote that the current branch is pushed to, or
+/// `origin` if there isn't one.
+pub fn push_tag(name: &str) -> Result<()> {
+    let remote = push_remote()?;
+    MiniBuffer::push(&format!("Pushing {name} to {remote}..."), MessageType::Note);
+    run_push(&["push", &remote, &format!("refs/tags/{name}")])
+}
+
+/// The remote that the current branch is pushed to, which follows `branch.<name>.pushRemote`,
+/// `remote.pushDefault` and then the upstream. A detached HEAD falls back to `origin`.
+fn push_remote() -> Result<String> {
+    let head = git_process(&["symbolic-ref", "--quiet", "HEAD"])?;
+    let head = std::str::from_utf8(&head.stdout).context("invalid utf8 from `git symbolic-ref`")?;
+    if head.trim().is_empty() {
+        return Ok("origin".to_string());
+    }
+    let output = git_process(&["for-each-ref", "--format=%(push:remotename)", head.trim()])?;
+    let remote = std::str::from_utf8(&output.stdout)
+        .context("invalid utf8 from `git for-each-ref`")?
+        .trim();
+    Ok(if remote.is_empty() { "origin" } else { remote }.to_string())
+}
+
+/// The `git push` running in the background, if any, and when it was started.
+static PUSH: Mutex<Option<(Child, Instant)>> = Mutex::new(None);
+
+/// Start running `git push` in the background. Its outcome is reported by [`poll_push`] once it
+/// finishes.
+pub fn run_push(args: &[&str]) -> Result<()> {
+    if push_started().is_some() {
+        MiniBuffer::push("Already pushing.", MessageType::Error);
+        return Ok(());
+    }
+    let child = remote_git(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("failed to run `git push`")?;
+    *PUSH.try_lock().expect("couldn't get mutex lock") = Some((child, Instant::now()));
+    Ok(())
+}
+
+/// When the push running in the background was started, if there is one.
+pub fn push_started() -> Option<Instant> {
+    PUSH.try_lock()
+        .expect("couldn't get mutex lock")
+        .as_ref()
+        .map(|(_, started)| *started)
+}
+
+/// Report the outcome of the push running in the background if it has finished.
+pub fn poll_push() -> Result<()> {
+    let finished = match PUSH.try_lock().expect("couldn't get mutex lock").as_mut() {
+        Some((child, _)) => child
+            .try_wait()
+            .context("failed to wait for `git push`")?
+            .is_some(),
+        None => false,
+    };
+    if !finished {
+        return Ok(());
+    }
+    let Some((child, _)) = PUSH.try_lock().expect("couldn't get mutex lock").take() else {
+        return Ok(());
+    };
+    let output = child
+        .wait_with_output()
+        .context("failed to read the output of `git push`")?;
+    if output.status.success() {
+        MiniBuffer::push("Push successful", MessageType::Note);
+    } else {
+        MiniBuffer::push_command_output(&output);
+    }
+    status::REFRESH_FLAG.store(true, Ordering::Release);
+    Ok(())
+}
+
+/// Stop the push running in the background. Returns `false` if there wasn't one.
+pub fn cancel_push() -> Result<bool> {
+    let Some((mut child, _)) = PUSH.try_lock().expect("couldn't get mutex lock").take() else {
+        return Ok(false);
+    };
+    // It may have already finished, in which case there's nothing to kill.
+    drop(child.kill());
+    child.wait().context("failed to wait for `git push`")?;
+    MiniBuffer::push("Push cancelled", MessageType::Note);
+    status::REFRESH_FLAG.store(true, Ordering::Release);
+    Ok(true)
+}
+
+/// Run a git command that talks to a remote, displaying `indicator` while it runs.
+fn run_remote(args: &[&str], indicator: &str) -> Result<()> {
+    let (_, term_height) = terminal::size().context("failed to query terminal dimensions")?;
+    crossterm::execute!(
+        stdout(),
+        cursor::MoveTo(0, term_height.saturating_sub(1)),
+        terminal::Clear(terminal::ClearType::CurrentLine),
+        style::Print(indicator),
+        cursor::MoveToNextLine(1),
+        cursor::Show,
+    )?;
+    // For now we are just temporarily disabling the raw mode so that if the user is aksed for
+    // credentials then they can provide them that way.
+    terminal::disable_raw_mode().context("failed to disable raw mode")?;
+    let output = git_process(args);
+    crossterm::execute!(stdout(), cursor::Hide)?;
+    terminal::enable_raw_mode().context("failed to enable raw mode")?;
+    // Credentials may have been asked for anywhere on the screen.
+    render::REDRAW_FLAG.store(true, Ordering::Release);
+    let output = output?;
+
+    report_remote_output(&output);
+    status::REFRESH_FLAG.store(true, Ordering::Release);
+    Ok(())
+}
+
+/// Start running `git fetch --all --prune` on another thread so that gex can still be used
+/// meanwhile.
+pub fn fetch_in_background() -> JoinHandle<Result<Output>> {
+    thread::spawn(|| {
+        remote_git(&["fetch", "--all", "--prune"])
+            .output()
+            .context("failed to run `git fetch --all --prune`")
+    })
+}
+
+/// Start running `git pull --rebase` on another thread so that gex can still be used meanwhile. Its
+/// outcome is reported by [`report_pull`] once it finishes.
+pub fn pull_rebase_in_background() -> JoinHandle<Result<Output>> {
+    thread::spawn(|| {
+        // The messages aren't translated, since the outcome is told from them.
+        remote_git(&["pull", "--rebase"])
+            .env("LC_ALL", "C")
+            .output()
+            .context("failed to run `git pull --rebase`")
+    })
+}
+
+/// What came of `git pull --rebase`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PullOutcome {
+    UpToDate,
+    FastForward,
+    /// The local commits were rebased onto the upstream.
+    Rebased,
+    Conflicts,
+    Failed,
+}
+
+impl PullOutcome {
+    /// Tell what happened from whether `git pull --rebase` succeeded and everything it printed, in
+    /// the C locale.
+    fn new(success: bool, output: &str) -> Self {
+        if success {
+            // The second is what the rebase says when there are local commits on top of the
+            // upstream.
+            if output.contains("Already up to date") || output.contains("is up to date.") {
+                Self::UpToDate
+            } else if output.contains("Fast-forward") {
+                Self::FastForward
+            } else {
+                Self::Rebased
+            }
+        } else if output.contains("CONFLICT") {
+            Self::Conflicts
+        } else {
+            Self::Failed
+        }
+    }
+}
+
+/// Display the outcome of `git pull --rebase`. If it stopped because of conflicts, the status is
+/// shown so that the unmerged paths can be resolved, if it or one of the lists is being looked at.
+pub fn report_pull(output: &Output, view: &mut View) -> Result<()> {
+    let printed = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let head = || -> Result<String> {
+        let output = git_process(&["rev-parse", "--short", "HEAD"])?;
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+    match PullOutcome::new(output.status.success(), &printed) {
+        PullOutcome::UpToDate => MiniBuffer::push("Already up to date.", MessageType::Note),
+        PullOutcome::FastForward => {
+            MiniBuffer::push(&format!("Fast-forwarded to {}", head()?), MessageType::Note);
+        }
+        PullOutcome::Rebased => MiniBuffer::push(
+            &format!("Rebased onto the upstream, now at {}", head()?),
+            MessageType::Note,
+        ),
+        PullOutcome::Conflicts => {
+            let message = if status::rebase_in_progress()?.is_some() {
+                "Conflicts paused the rebase: resolve them, then R r continues or R a aborts."
+            } else {
+                "Pulling stopped with conflicts."
+            };
+            MiniBuffer::push(message, MessageType::Error);
+            // Whatever is being typed into the minibuffer or the commit editor isn't interrupted.
+            if matches!(
+                view,
+                View::Status
+                    | View::BranchList
+                    | View::Log
+                    | View::StashList
+                    | View::TagList
+                    | View::RemoteList(_)
+                    | View::WorktreeList
+            ) {
+                *view = View::Status;
+            }
+        }
+        PullOutcome::Failed => MiniBuffer::push_command_output(output),
+    }
+    Ok(())
+}
+
+/// Display the output of a git command that talked to a remote.
+pub fn report_remote_output(output: &Output) {
+    // git reports progress and what was pushed or fetched on stderr, even when it succeeds.
+    if output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        MiniBuffer::push(
+            &stderr
+                .lines()
+                .chain(stdout.lines())
+                .filter(|line| !line.trim().is_empty())
+                .collect::<Vec<_>>()
+                .join("\n"),
+            MessageType::Note,
+        );
+    } else {
+        MiniBuffer::push_command_output(output);
+    }
+}
+
 commands! {
     'b': Branch => ['b': Checkout, 'n': New],
-    'c': Commit => ['c': Commit, 'a': Amend, 'e': Extend],
+    'c': Commit => ['c': Commit, 'a': Amend, 'e': Extend, 'm': Message, 'i': Inline],
     'p': Push => ['p': Remote, 'f': Force],
-    'z': Stash => ['s': Stash, 'p': Pop],
+    'R': Rebase => ['i': Interactive, 'r': Continue, 's': Skip, 'a': Abort],
+    'y': CherryPick => ['y': Continue, 's': Skip, 'a': Abort],
+    'm': Merge => ['m': Continue, 'a': Abort],
 }
 
 impl GexCommand {
@@ -82,103 +518,164 @@ impl GexCommand {
             Branch(subcmd) => {
                 use branch::SubCommand;
                 match subcmd {
-                    SubCommand::New => {
+                    SubCommand::New => BranchList::new_branch(minibuffer, view),
+                    SubCommand::Checkout => {
+                        state.branch_list.fetch()?;
+                        *view = View::BranchList;
+                    }
+                }
+            }
+            Commit(subcmd) => {
+                use commit::SubCommand;
+                let committed = match subcmd {
+                    SubCommand::Commit => commit(&[])?,
+                    SubCommand::Extend => amend(true)?,
+                    SubCommand::Message => {
                         minibuffer.get_input(
                             Rc::new(|input| {
-                                if let Some(input) = input {
-                                    BranchList::checkout_new(input)?;
-                                    status::REFRESH_FLAG.store(true, Ordering::Release);
-                                }
                                 print!("{}", cursor::Hide);
+                                let Some(message) = input else {
+                                    return Ok(());
+                                };
+                                if message.trim().is_empty() {
+                                    MiniBuffer::push(
+                                        "The commit message must not be empty.",
+                                        MessageType::Error,
+                                    );
+                                    return Ok(());
+                                }
+                                MiniBuffer::push_command_output(&git_process(&[
+                                    "commit", "-m", message,
+                                ])?);
+                                status::REFRESH_FLAG.store(true, Ordering::Release);
                                 Ok(())
                             }),
-                            Some("Name for the new branch: "),
+                            Some("Commit message: "),
                             view,
                             View::Status,
                         );
+                        return Ok(());
                     }
-                    SubCommand::Checkout => {
-                        state.branch_list.fetch()?;
-                        *view = View::BranchList;
+                    SubCommand::Inline => {
+                        state.commit_editor = CommitEditor::default();
+                        *view = View::CommitEditor;
+                        return Ok(());
                     }
+                    SubCommand::Amend => amend(false)?,
+                };
+                // The staging area is only changed if the commit went through.
+                if committed {
+                    status::REFRESH_FLAG.store(true, Ordering::Release);
                 }
+                *view = View::Status;
             }
-            Commit(subcmd) => {
-                use commit::SubCommand;
+            Rebase(subcmd) => {
+                use rebase::SubCommand;
                 match subcmd {
-                    SubCommand::Commit => {
-                        crossterm::execute!(stdout(), terminal::LeaveAlternateScreen)
-                            .context("failed to leave alternate screen")?;
-                        MiniBuffer::push_command_output(
-                            &Command::new("git")
-                                .arg("commit")
-                                .stdout(Stdio::inherit())
-                                .stdin(Stdio::inherit())
-                                .output()
-                                .context("failed to run `git commit`")?,
-                        );
-                        status::REFRESH_FLAG.store(true, Ordering::Release);
-                        crossterm::execute!(stdout(), terminal::EnterAlternateScreen, cursor::Hide)
-                            .context("failed to enter alternate screen")?;
+                    SubCommand::Interactive => {
+                        if status::rebase_in_progress()?.is_none() {
+                            ask_rebase_count(minibuffer, view);
+                            return Ok(());
+                        }
+                        MiniBuffer::push("A rebase is already in progress.", MessageType::Error);
                     }
-                    SubCommand::Extend => {
-                        MiniBuffer::push_command_output(
-                            &Command::new("git")
-                                .args(["commit", "--amend", "--no-edit"])
-                                .stdout(Stdio::inherit())
-                                .stdin(Stdio::inherit())
-                                .output()
-                                .context("failed to run `git commit`")?,
-                        );
-                        status::REFRESH_FLAG.store(true, Ordering::Release);
+                    // Continuing may open the editor to change the message of a commit that had
+                    // conflicts.
+                    SubCommand::Continue => MiniBuffer::push_command_output(&interactive_process(
+                        Command::new("git").args(["rebase", "--continue"]),
+                    )?),
+                    SubCommand::Skip => {
+                        MiniBuffer::push_command_output(&git_process(&["rebase", "--skip"])?);
                     }
-                    SubCommand::Amend => {
-                        crossterm::execute!(stdout(), terminal::LeaveAlternateScreen)
-                            .context("failed to leave alternate screen")?;
-                        MiniBuffer::push_command_output(
-                            &Command::new("git")
-                                .args(["commit", "--amend"])
-                                .stdout(Stdio::inherit())
-                                .stdin(Stdio::inherit())
-                                .output()
-                                .context("failed to run `git commit`")?,
-                        );
-                        status::REFRESH_FLAG.store(true, Ordering::Release);
-                        crossterm::execute!(stdout(), terminal::EnterAlternateScreen, cursor::Hide)
-                            .context("failed to enter alternate screen")?;
+                    SubCommand::Abort => {
+                        MiniBuffer::push_command_output(&git_process(&["rebase", "--abort"])?);
                     }
                 }
+                status::REFRESH_FLAG.store(true, Ordering::Release);
                 *view = View::Status;
             }
-            Push(subcmd) => {
-                use push::SubCommand;
-                // For now we are just temporarily disabling the raw mode so that if the user is
-                // aksed for credentials then they can provide them that way.
-                crossterm::execute!(stdout(), cursor::MoveToColumn(0), cursor::Show)?;
-                terminal::disable_raw_mode().context("failed to disable raw mode")?;
+            CherryPick(subcmd) => {
+                use cherrypick::SubCommand;
                 match subcmd {
-                    SubCommand::Remote => MiniBuffer::push_command_output(&git_process(&["push"])?),
-                    SubCommand::Force => {
-                        MiniBuffer::push_command_output(&git_process(&["push", "--force"])?);
+                    // Continuing opens the editor for the message of the commit, like committing.
+                    SubCommand::Continue => MiniBuffer::push_command_output(&interactive_process(
+                        Command::new("git").args(["cherry-pick", "--continue"]),
+                    )?),
+                    SubCommand::Skip => {
+                        MiniBuffer::push_command_output(&git_process(&["cherry-pick", "--skip"])?);
+                    }
+                    SubCommand::Abort => {
+                        MiniBuffer::push_command_output(&git_process(&["cherry-pick", "--abort"])?);
                     }
                 }
-                crossterm::execute!(stdout(), cursor::Hide)?;
-                terminal::enable_raw_mode().context("failed to enable raw mode")?;
+                status::REFRESH_FLAG.store(true, Ordering::Release);
                 *view = View::Status;
             }
-            Stash(subcmd) => {
-                use stash::SubCommand;
+            Merge(subcmd) => {
+                use merge::SubCommand;
                 match subcmd {
-                    SubCommand::Stash => MiniBuffer::push_command_output(&git_process(&["stash"])?),
-                    SubCommand::Pop => {
-                        MiniBuffer::push_command_output(&git_process(&["stash", "pop"])?);
+                    // Continuing opens the editor for the message of the merge commit.
+                    SubCommand::Continue => MiniBuffer::push_command_output(&interactive_process(
+                        Command::new("git").args(["merge", "--continue"]),
+                    )?),
+                    SubCommand::Abort => {
+                        MiniBuffer::push_command_output(&git_process(&["merge", "--abort"])?);
                     }
                 }
                 status::REFRESH_FLAG.store(true, Ordering::Release);
                 *view = View::Status;
             }
+            Push(subcmd) => {
+                use push::SubCommand;
+                push(matches!(subcmd, SubCommand::Force), minibuffer, view)?;
+                if matches!(view, View::Command(_)) {
+                    *view = View::Status;
+                }
+            }
         }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::PullOutcome;
+
+    #[test_case(true, "Already up to date.\n", PullOutcome::UpToDate ; "up to date")]
+    #[test_case(
+        true,
+        "Current branch master is up to date.\n",
+        PullOutcome::UpToDate
+        ; "up to date with local commits"
+    )]
+    #[test_case(
+        true,
+        "Updating b84e9ac..09e648f\nFast-forward\n b | 1 +\n",
+        PullOutcome::FastForward
+        ; "fast-forward"
+    )]
+    #[test_case(
+        true,
+        "Successfully rebased and updated refs/heads/master.\n",
+        PullOutcome::Rebased
+        ; "rebased"
+    )]
+    #[test_case(
+        false,
+        "Auto-merging a\nCONFLICT (content): Merge conflict in a\nerror: could not apply d0c1a8e... 5\n",
+        PullOutcome::Conflicts
+        ; "conflicts"
+    )]
+    #[test_case(
+        false,
+        "fatal: couldn't find remote ref main\n",
+        PullOutcome::Failed
+        ; "failed"
+    )]
+    fn pull_outcome(success: bool, output: &str, expected: PullOutcome) {
+        assert_eq!(PullOutcome::new(success, output), expected);
+    }
+}
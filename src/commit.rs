@@ -0,0 +1,176 @@
+//! Module relating to the inline commit message editor.
+
+use std::{fmt, fs};
+
+use anyhow::{Context, Result};
+use crossterm::{
+    event::{KeyCode, KeyEvent, KeyModifiers},
+    style::{Attribute, SetForegroundColor},
+};
+
+use crate::{
+    config, git_process,
+    minibuffer::MiniBuffer,
+    render::{self, Renderer, ResetAttributes},
+};
+
+/// A simple multi-line text editor for writing a commit message without leaving gex. The first
+/// line is the subject, and the body follows after a blank line.
+#[derive(Debug)]
+pub struct CommitEditor {
+    lines: Vec<String>,
+    /// The line the cursor is on.
+    row: usize,
+    /// The character (not byte) index of the cursor within its line.
+    col: usize,
+    error: Option<&'static str>,
+}
+
+impl Default for CommitEditor {
+    fn default() -> Self {
+        Self {
+            lines: vec![String::new()],
+            row: 0,
+            col: 0,
+            error: None,
+        }
+    }
+}
+
+impl render::Render for CommitEditor {
+    fn render(&self, f: &mut Renderer) -> fmt::Result {
+        use fmt::Write;
+        let config = config!();
+        writeln!(
+            f,
+            "\r{}Commit message{} {}(Ctrl-S to commit, Esc to cancel){ResetAttributes}",
+            SetForegroundColor(config.colors.heading),
+            ResetAttributes,
+            Attribute::Dim,
+        )?;
+        if let Some(error) = self.error {
+            writeln!(
+                f,
+                "\r{}{error}{ResetAttributes}",
+                SetForegroundColor(config.colors.error)
+            )?;
+        }
+        writeln!(f, "\r")?;
+
+        for (i, line) in self.lines.iter().enumerate() {
+            if i == self.row {
+                f.insert_cursor();
+                let (before, after) = line.split_at(self.byte_index(line));
+                let mut after = after.chars();
+                let under_cursor = after.next().unwrap_or(' ');
+                writeln!(
+                    f,
+                    "\r{before}{}{under_cursor}{ResetAttributes}{}",
+                    Attribute::Reverse,
+                    after.as_str()
+                )?;
+            } else {
+                writeln!(f, "\r{line}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CommitEditor {
+    /// The byte index into `line` of the cursor.
+    fn byte_index(&self, line: &str) -> usize {
+        line.char_indices()
+            .nth(self.col)
+            .map_or(line.len(), |(i, _)| i)
+    }
+
+    fn line_len(&self) -> usize {
+        self.lines[self.row].chars().count()
+    }
+
+    /// Handle a key press. Returns `true` when the editor is finished with, either because the
+    /// commit was made or because it was cancelled.
+    pub fn handle_input(&mut self, key_event: KeyEvent) -> Result<bool> {
+        self.error = None;
+        match (key_event.code, key_event.modifiers) {
+            (KeyCode::Esc, _) => return Ok(true),
+            (KeyCode::Char('s'), KeyModifiers::CONTROL) => return self.commit(),
+            (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
+                let idx = self.byte_index(&self.lines[self.row]);
+                self.lines[self.row].insert(idx, c);
+                self.col += 1;
+            }
+            (KeyCode::Enter, _) => {
+                let idx = self.byte_index(&self.lines[self.row]);
+                let rest = self.lines[self.row].split_off(idx);
+                self.row += 1;
+                self.lines.insert(self.row, rest);
+                self.col = 0;
+            }
+            (KeyCode::Backspace, _) => {
+                if self.col > 0 {
+                    self.col -= 1;
+                    let idx = self.byte_index(&self.lines[self.row]);
+                    self.lines[self.row].remove(idx);
+                } else if self.row > 0 {
+                    let line = self.lines.remove(self.row);
+                    self.row -= 1;
+                    self.col = self.line_len();
+                    self.lines[self.row].push_str(&line);
+                }
+            }
+            (KeyCode::Delete, _) => {
+                if self.col < self.line_len() {
+                    let idx = self.byte_index(&self.lines[self.row]);
+                    self.lines[self.row].remove(idx);
+                } else if self.row + 1 < self.lines.len() {
+                    let line = self.lines.remove(self.row + 1);
+                    self.lines[self.row].push_str(&line);
+                }
+            }
+            (KeyCode::Left, _) => self.col = self.col.saturating_sub(1),
+            (KeyCode::Right, _) => self.col = (self.col + 1).min(self.line_len()),
+            (KeyCode::Up, _) => {
+                self.row = self.row.saturating_sub(1);
+                self.col = self.col.min(self.line_len());
+            }
+            (KeyCode::Down, _) => {
+                self.row = (self.row + 1).min(self.lines.len() - 1);
+                self.col = self.col.min(self.line_len());
+            }
+            (KeyCode::Home, _) => self.col = 0,
+            (KeyCode::End, _) => self.col = self.line_len(),
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    /// Write the message to a temporary file and commit with it. If the commit fails then the
+    /// editor stays open so that the message isn't lost.
+    fn commit(&mut self) -> Result<bool> {
+        if self.lines[0].trim().is_empty() {
+            self.error = Some("The commit subject must not be empty.");
+            return Ok(false);
+        }
+
+        // The message is written inside the git directory rather than a shared temporary directory,
+        // where another user could have put a symlink at a predictable path.
+        let output = git_process(&["rev-parse", "--git-path", "GEX_COMMIT_EDITMSG"])?;
+        if !output.status.success() {
+            MiniBuffer::push_command_output(&output);
+            return Ok(false);
+        }
+        let path = std::str::from_utf8(&output.stdout)
+            .context("invalid utf8 from `git rev-parse`")?
+            .trim()
+            .to_string();
+        fs::write(&path, self.lines.join("\n")).context("failed to write commit message")?;
+        let output = git_process(&["commit", "-F", &path]);
+        drop(fs::remove_file(&path));
+        let output = output?;
+
+        MiniBuffer::push_command_output(&output);
+        Ok(output.status.success())
+    }
+}
@@ -1,9 +1,9 @@
 //! Gex configuration.
 #![allow(clippy::derivable_impls)]
-use std::{collections::HashMap, fs, path::PathBuf, str::FromStr, sync::OnceLock};
+use std::{collections::HashMap, fmt, fs, path::PathBuf, str::FromStr, sync::OnceLock};
 
 use anyhow::{Context, Result};
-use clap::{command, Parser};
+use clap::Parser;
 use crossterm::{event::KeyCode, style::Color};
 use serde::{
     de::{self, Visitor},
@@ -22,7 +22,7 @@ macro_rules! config {
 
 /// Command line args.
 #[derive(Parser)]
-#[command(version = env!("GEX_VERSION"), about)]
+#[command(version = env!("GEX_VERSION"), about, after_help = crate::help::summary())]
 pub struct Clargs {
     /// The path to the repository.
     #[clap(default_value = ".")]
@@ -31,6 +31,16 @@ pub struct Clargs {
     /// Path to a config file to use.
     #[clap(short, long, name = "PATH")]
     pub config_file: Option<String>,
+
+    /// Don't use any colours or text attributes. This is also the case when `NO_COLOR` is set,
+    /// unless colours are set in the config file.
+    #[clap(long)]
+    pub no_color: bool,
+
+    /// Print the status and exit instead of starting the TUI. Colours are only used when printing
+    /// to a terminal.
+    #[clap(long)]
+    pub print: bool,
 }
 
 /// The top-level of the config parsed from the config file.
@@ -42,14 +52,28 @@ pub struct Config {
     pub keymap: Keymaps,
 }
 
+// These are all independent options in the config file.
+#[allow(clippy::struct_excessive_bools)]
 #[derive(Deserialize, Debug, PartialEq, Eq)]
 #[serde(default)]
 pub struct Options {
     pub auto_expand_files: bool,
     pub auto_expand_hunks: bool,
+    /// Refresh the status when the repository changes outside of gex, by watching the working
+    /// tree.
+    pub auto_refresh: bool,
     pub editor: String,
     pub lookahead_lines: usize,
+    /// Handle mouse clicks and scrolling. Turning this off leaves the mouse to the terminal, e.g.
+    /// for selecting text.
+    pub mouse: bool,
+    /// Rename the branch on `origin` too when renaming the current branch.
+    pub push_after_rename: bool,
+    /// The key to sort the branch list by, as passed to `git branch --sort`. The most recently
+    /// committed branches come first if it isn't set.
     pub sort_branches: Option<String>,
+    /// Highlight keywords, strings, numbers and comments in diffs of files in known languages.
+    pub syntax_highlighting: bool,
     pub truncate_lines: bool,
     pub ws_error_highlight: WsErrorHighlight,
 }
@@ -59,7 +83,9 @@ impl Options {
         git2::Config::open_default()
             .and_then(|mut config| config.snapshot())
             .and_then(|config| config.get_str("core.editor").map(|ed| ed.to_owned()))
-            .unwrap_or_else(|_| std::env::var("EDITOR").unwrap_or_else(|_| "vi".to_string()))
+            .or_else(|_| std::env::var("VISUAL"))
+            .or_else(|_| std::env::var("EDITOR"))
+            .unwrap_or_else(|_| "vi".to_string())
     }
 }
 
@@ -68,9 +94,13 @@ impl Default for Options {
         Self {
             auto_expand_files: false,
             auto_expand_hunks: true,
+            auto_refresh: true,
             editor: Self::default_editor(),
             lookahead_lines: 5,
+            mouse: true,
+            push_after_rename: false,
             sort_branches: None,
+            syntax_highlighting: true,
             truncate_lines: true,
             ws_error_highlight: WsErrorHighlight::default(),
         }
@@ -86,7 +116,7 @@ pub struct WsErrorHighlight {
 }
 
 #[derive(Deserialize, Debug, PartialEq, Eq)]
-#[serde(default)]
+#[serde(from = "ColorsConfig")]
 pub struct Colors {
     pub foreground: Color,
     pub background: Color,
@@ -96,6 +126,86 @@ pub struct Colors {
     pub deletion: Color,
     pub key: Color,
     pub error: Color,
+    /// The background of marked items.
+    pub selection: Color,
+    /// The background of the part of a path that matches the search.
+    pub search: Color,
+}
+
+/// A built-in set of colours, which the colours from the config file are applied on top of.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Theme {
+    /// Colours for terminals with a dark background. This is the default.
+    Dark,
+    /// Colours for terminals with a light background, where yellow and dark green are hard to read.
+    Light,
+}
+
+impl Theme {
+    const fn colors(self) -> Colors {
+        match self {
+            Self::Dark => Colors {
+                foreground: Color::Reset,
+                background: Color::Reset,
+                heading: Color::Yellow,
+                hunk_head: Color::Blue,
+                addition: Color::DarkGreen,
+                deletion: Color::DarkRed,
+                key: Color::Green,
+                error: Color::Red,
+                selection: Color::DarkGrey,
+                search: Color::DarkYellow,
+            },
+            Self::Light => Colors {
+                foreground: Color::Reset,
+                background: Color::Reset,
+                heading: Color::DarkMagenta,
+                hunk_head: Color::DarkBlue,
+                addition: Color::AnsiValue(28),
+                deletion: Color::AnsiValue(124),
+                key: Color::DarkCyan,
+                error: Color::DarkRed,
+                selection: Color::Grey,
+                search: Color::Yellow,
+            },
+        }
+    }
+}
+
+/// The `[colors]` section of the config file, where every colour is optional.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct ColorsConfig {
+    theme: Option<Theme>,
+    foreground: Option<Color>,
+    background: Option<Color>,
+    heading: Option<Color>,
+    hunk_head: Option<Color>,
+    addition: Option<Color>,
+    deletion: Option<Color>,
+    key: Option<Color>,
+    error: Option<Color>,
+    selection: Option<Color>,
+    search: Option<Color>,
+}
+
+impl From<ColorsConfig> for Colors {
+    fn from(config: ColorsConfig) -> Self {
+        let theme = config.theme.map_or_else(Self::default, Theme::colors);
+        Self {
+            foreground: config.foreground.unwrap_or(theme.foreground),
+            background: config.background.unwrap_or(theme.background),
+            heading: config.heading.unwrap_or(theme.heading),
+            hunk_head: config.hunk_head.unwrap_or(theme.hunk_head),
+            addition: config.addition.unwrap_or(theme.addition),
+            deletion: config.deletion.unwrap_or(theme.deletion),
+            key: config.key.unwrap_or(theme.key),
+            error: config.error.unwrap_or(theme.error),
+            selection: config.selection.unwrap_or(theme.selection),
+            search: config.search.unwrap_or(theme.search),
+        }
+    }
 }
 
 impl Default for Colors {
@@ -103,9 +213,10 @@ impl Default for Colors {
         // We have to force colour output here regardless of NO_COLOR setting, because then we can
         // handle it ourselves. The NO_COLOR standard specifies that colour output should be
         // enabled when the user has explicitly set it, which can be achieved here by detecting the
-        // env variable and then enabling color granularly based on the user config.
+        // env variable and then enabling color granularly based on the user config. Choosing a
+        // theme counts as setting the colours explicitly.
         crossterm::style::force_color_output(true);
-        if std::env::var("NO_COLOR").map_or(false, |v| !v.is_empty()) {
+        if std::env::var("NO_COLOR").is_ok_and(|v| !v.is_empty()) {
             Self {
                 foreground: Color::Reset,
                 background: Color::Reset,
@@ -115,18 +226,11 @@ impl Default for Colors {
                 deletion: Color::Reset,
                 key: Color::Reset,
                 error: Color::Reset,
+                selection: Color::Reset,
+                search: Color::Reset,
             }
         } else {
-            Self {
-                foreground: Color::Reset,
-                background: Color::Reset,
-                heading: Color::Yellow,
-                hunk_head: Color::Blue,
-                addition: Color::DarkGreen,
-                deletion: Color::DarkRed,
-                key: Color::Green,
-                error: Color::Red,
-            }
+            Theme::Dark.colors()
         }
     }
 }
@@ -134,75 +238,135 @@ impl Default for Colors {
 #[derive(Debug, PartialEq, Eq)]
 pub struct Keymaps {
     pub navigation: HashMap<KeyCode, Action>,
+    pub actions: HashMap<KeyCode, GexAction>,
+}
+
+/// Parse a key from the config, which is either a single character or the name of a [`KeyCode`]
+/// variant.
+fn parse_key<E: de::Error>(key: String) -> std::result::Result<KeyCode, E> {
+    // cross-term can't, with Serde,  directly deserialize '<CHARACTER_VALUE>' into a KeyCode
+    let mut chars = key.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(KeyCode::Char(c));
+    }
+    Deserialize::deserialize(de::value::StringDeserializer::new(key))
+}
+
+/// Bind the `keys` for each action in `section_values` in `keymap`, replacing the default keys for
+/// those actions. Binding a key that is still used by another action is an error, since only one of
+/// them could ever be run.
+fn bind_keys<T, E>(
+    keymap: &mut HashMap<KeyCode, T>,
+    section_values: HashMap<String, Vec<String>>,
+) -> std::result::Result<(), E>
+where
+    T: for<'de> Deserialize<'de> + Clone + PartialEq + fmt::Debug,
+    E: de::Error,
+{
+    let bindings = section_values
+        .into_iter()
+        .map(|(name, keys)| {
+            let action: T = Deserialize::deserialize(de::value::StringDeserializer::new(name))?;
+            Ok((action, keys))
+        })
+        .collect::<std::result::Result<Vec<_>, E>>()?;
+
+    // over-write default key-map to action
+    keymap.retain(|_, value| bindings.iter().all(|(action, _)| action != value));
+
+    for (action, keys) in bindings {
+        for key in keys {
+            let code = parse_key(key.clone())?;
+            if let Some(bound) = keymap.get(&code).filter(|bound| **bound != action) {
+                return Err(de::Error::custom(format!(
+                    "`{key}` can't be bound to both {} and {}",
+                    snake_case(bound),
+                    snake_case(&action)
+                )));
+            }
+            keymap.insert(code, action.clone());
+        }
+    }
+    Ok(())
+}
+
+/// The name of `action` as it's written in the config.
+fn snake_case(action: &impl fmt::Debug) -> String {
+    format!("{action:?}")
+        .chars()
+        .enumerate()
+        .flat_map(|(i, c)| {
+            let separator = (i > 0 && c.is_ascii_uppercase()).then_some('_');
+            separator.into_iter().chain(c.to_lowercase())
+        })
+        .collect()
 }
 
 struct KeymapsVisitor;
 
-impl<'de> Deserialize<'de> for Keymaps {
-    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+impl<'de> Visitor<'de> for KeymapsVisitor {
+    type Value = Keymaps;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str(
+            "
+                [keymap.SECTION]
+                action_under_section = ['<CHARACTER_VALUE>', \"<KeyCode enum value name>\"],
+                ...
+            ",
+        )
+    }
+
+    fn visit_map<A>(self, mut map: A) -> std::result::Result<Self::Value, A::Error>
     where
-        D: serde::Deserializer<'de>,
+        A: serde::de::MapAccess<'de>,
     {
-        impl<'de> Visitor<'de> for KeymapsVisitor {
-            type Value = Keymaps;
-
-            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-                formatter.write_str(
-                    "
-                        [keymap.SECTION]
-                        action_under_section = ['<CHARACTER_VALUE>', \"<KeyCode enum value name>\"],
-                        ...
-                    ",
-                )
-            }
-
-            fn visit_map<A>(self, mut map: A) -> std::result::Result<Self::Value, A::Error>
-            where
-                A: serde::de::MapAccess<'de>,
-            {
-                let mut navigation = Self::Value::default().navigation;
-
-                while let Some((section, section_values)) =
-                    map.next_entry::<String, HashMap<String, Vec<String>>>()?
-                {
-                    if section == "navigation" {
-                        for (action, keys) in section_values {
-                            let ac: Action = Deserialize::deserialize(
-                                de::value::StringDeserializer::new(action),
-                            )?;
-
-                            // over-write default key-map to action
-                            navigation.retain(|_, value| value != &ac);
-
-                            for key in keys {
-                                // cross-term can't, with Serde,  directly deserialize '<CHARACTER_VALUE>' into a KeyCode
-                                if key.len() == 1 {
-                                    if let Some(c) = key.chars().next() {
-                                        let key = KeyCode::Char(c);
-                                        navigation.insert(key, ac.clone());
-                                        continue;
-                                    }
-                                }
-
-                                let key: KeyCode = Deserialize::deserialize(
-                                    de::value::StringDeserializer::new(key),
-                                )?;
-
-                                navigation.insert(key, ac.clone());
-                            }
-                        }
-                    }
+        let Keymaps {
+            mut navigation,
+            mut actions,
+        } = Self::Value::default();
+
+        while let Some((section, section_values)) =
+            map.next_entry::<String, HashMap<String, Vec<String>>>()?
+        {
+            match section.as_str() {
+                "navigation" => bind_keys(&mut navigation, section_values)?,
+                "actions" => bind_keys(&mut actions, section_values)?,
+                _ => {
+                    return Err(de::Error::unknown_field(
+                        &section,
+                        &["navigation", "actions"],
+                    ))
                 }
-
-                Ok(Keymaps { navigation })
             }
         }
 
+        // Both keymaps are checked for every key, so a key in both would do two things at once.
+        if let Some((key, action)) = navigation.iter().find(|(key, _)| actions.contains_key(key)) {
+            return Err(de::Error::custom(format!(
+                "{key:?} can't be bound to both {} and {}",
+                snake_case(action),
+                snake_case(&actions[key])
+            )));
+        }
+
+        Ok(Keymaps {
+            navigation,
+            actions,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Keymaps {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
         deserializer.deserialize_map(KeymapsVisitor)
     }
 }
 
-#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(rename_all(deserialize = "snake_case"))]
 #[cfg_attr(test, derive(strum::EnumIter))]
 pub enum Action {
@@ -213,11 +377,143 @@ pub enum Action {
     ToggleExpand,
     GotoTop,
     GotoBottom,
+    PageDown,
+    PageUp,
+}
+
+/// The actions available from the status view.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all(deserialize = "snake_case"))]
+#[cfg_attr(test, derive(strum::EnumIter))]
+pub enum GexAction {
+    Stage,
+    StageAll,
+    ForceAdd,
+    Unstage,
+    UnstageAll,
+    Discard,
+    Mark,
+    MarkRange,
+    WordDiff,
+    Edit,
+    CopyPath,
+    Blame,
+    Amend,
+    Log,
+    StashList,
+    Stash,
+    RemoteList,
+    WorktreeList,
+    TagList,
+    Search,
+    SearchNext,
+    SearchPrevious,
+    Push,
+    Fetch,
+    Pull,
+    PullRebase,
+    Refresh,
+    GitCommand,
+    ShellCommand,
+    Help,
+    Open,
+    Quit,
+}
+
+impl Action {
+    /// A description of the action for the help page.
+    pub const fn description(&self) -> &'static str {
+        match self {
+            Self::MoveDown => "move down",
+            Self::MoveUp => "move up",
+            Self::NextFile => "jump to next file",
+            Self::PreviousFile => "jump to previous file",
+            Self::ToggleExpand => "toggle expand",
+            Self::GotoTop => "go to top",
+            Self::GotoBottom => "go to bottom",
+            Self::PageDown => "page down",
+            Self::PageUp => "page up",
+        }
+    }
+}
+
+impl GexAction {
+    /// A description of the action for the help page.
+    pub const fn description(&self) -> &'static str {
+        match self {
+            Self::Stage => "stage item",
+            Self::StageAll => "stage all items",
+            Self::ForceAdd => "force add ignored item",
+            Self::Unstage => "unstage item",
+            Self::UnstageAll => "unstage all items",
+            Self::Discard => "discard item",
+            Self::Mark => "mark item",
+            Self::MarkRange => "mark range of items",
+            Self::WordDiff => "toggle word diff",
+            Self::Edit => "edit file/hunk",
+            Self::CopyPath => "copy path",
+            Self::Blame => "blame file",
+            Self::Amend => "amend last commit",
+            Self::Log => "show log",
+            Self::StashList => "show stashes",
+            Self::Stash => "stash changes",
+            Self::RemoteList => "show remotes",
+            Self::WorktreeList => "show worktrees",
+            Self::TagList => "show tags",
+            Self::Search => "search paths",
+            Self::SearchNext => "jump to next match",
+            Self::SearchPrevious => "jump to previous match",
+            Self::Push => "push to upstream",
+            Self::Fetch => "fetch all remotes",
+            Self::Pull => "pull from remote",
+            Self::PullRebase => "pull with rebase",
+            Self::Refresh => "refresh",
+            Self::GitCommand => "execute git command",
+            Self::ShellCommand => "execute subprocess",
+            Self::Help => "show this help",
+            Self::Open => "open hunks or submodule",
+            Self::Quit => "quit gex",
+        }
+    }
 }
 
 impl Default for Keymaps {
     fn default() -> Self {
         Self {
+            actions: HashMap::from([
+                (KeyCode::Char('s'), GexAction::Stage),
+                (KeyCode::Char('S'), GexAction::StageAll),
+                (KeyCode::Char('I'), GexAction::ForceAdd),
+                (KeyCode::Char('u'), GexAction::Unstage),
+                (KeyCode::Char('U'), GexAction::UnstageAll),
+                (KeyCode::Char('d'), GexAction::Discard),
+                (KeyCode::Char('v'), GexAction::Mark),
+                (KeyCode::Char('V'), GexAction::MarkRange),
+                (KeyCode::Char('w'), GexAction::WordDiff),
+                (KeyCode::Char('e'), GexAction::Edit),
+                (KeyCode::Char('y'), GexAction::CopyPath),
+                (KeyCode::Char('B'), GexAction::Blame),
+                (KeyCode::Char('C'), GexAction::Amend),
+                (KeyCode::Char('l'), GexAction::Log),
+                (KeyCode::Char('z'), GexAction::StashList),
+                (KeyCode::Char('Z'), GexAction::Stash),
+                (KeyCode::Char('M'), GexAction::RemoteList),
+                (KeyCode::Char('W'), GexAction::WorktreeList),
+                (KeyCode::Char('T'), GexAction::TagList),
+                (KeyCode::Char('/'), GexAction::Search),
+                (KeyCode::Char('n'), GexAction::SearchNext),
+                (KeyCode::Char('N'), GexAction::SearchPrevious),
+                (KeyCode::Char('P'), GexAction::Push),
+                (KeyCode::Char('f'), GexAction::Fetch),
+                (KeyCode::Char('F'), GexAction::Pull),
+                (KeyCode::Char('L'), GexAction::PullRebase),
+                (KeyCode::Char('r'), GexAction::Refresh),
+                (KeyCode::Char(':'), GexAction::GitCommand),
+                (KeyCode::Char('!'), GexAction::ShellCommand),
+                (KeyCode::Char('?'), GexAction::Help),
+                (KeyCode::Enter, GexAction::Open),
+                (KeyCode::Char('q'), GexAction::Quit),
+            ]),
             navigation: HashMap::from([
                 (KeyCode::Char('j'), Action::MoveDown),
                 (KeyCode::Down, Action::MoveDown),
@@ -229,6 +525,8 @@ impl Default for Keymaps {
                 (KeyCode::Tab, Action::ToggleExpand),
                 (KeyCode::Char('g'), Action::GotoTop),
                 (KeyCode::Char('G'), Action::GotoBottom),
+                (KeyCode::PageDown, Action::PageDown),
+                (KeyCode::PageUp, Action::PageUp),
             ]),
         }
     }
@@ -239,7 +537,7 @@ impl Config {
     /// returns it along with a Vec of unrecognised keys.
     /// If there is no config file, it will return `Ok(None)`.
     /// If there is a config file but it is unable to parse it, it will return `Err(_)`.
-    pub fn read_from_file(path: &Option<String>) -> Result<Option<(Self, Vec<String>)>> {
+    pub fn read_from_file(path: Option<&str>) -> Result<Option<(Self, Vec<String>)>> {
         let mut config_path;
         if let Some(path) = path {
             config_path = PathBuf::from(path);
@@ -343,6 +641,7 @@ mod tests {
     use super::*;
     use crossterm::style::Color;
     use strum::IntoEnumIterator;
+    use test_case::test_case;
 
     #[test]
     fn every_action_has_a_default_key() {
@@ -355,6 +654,16 @@ mod tests {
             action_list.is_empty(),
             "The following Actions do not have a default keybinding: {action_list:?}"
         );
+
+        let mut action_list: Vec<GexAction> = GexAction::iter().collect();
+        for (_, action) in Keymaps::default().actions {
+            action_list.retain(|x| x != &action);
+        }
+
+        assert!(
+            action_list.is_empty(),
+            "The following GexActions do not have a default keybinding: {action_list:?}"
+        );
     }
 
     // Should be up to date with the example config in the README.
@@ -364,9 +673,13 @@ mod tests {
 [options]
 auto_expand_files = false
 auto_expand_hunks = true
+auto_refresh = true # refresh when files change outside of gex
 editor = \"nvim\"
 lookahead_lines = 5
+mouse = true # `false` leaves text selection to the terminal
+push_after_rename = false # rename the current branch on origin too
 sort_branches = \"-committerdate\" # key to pass to `git branch --sort`. https://git-scm.com/docs/git-for-each-ref#_field_names 
+syntax_highlighting = true # highlight keywords, strings and comments in diffs
 truncate_lines = true # `false` is not recommended - see #37
 ws_error_highlight = \"new\" # override git's diff.wsErrorHighlight
 
@@ -375,6 +688,7 @@ ws_error_highlight = \"new\" # override git's diff.wsErrorHighlight
 #
 # This example uses a Gruvbox colour theme.
 [colors]
+theme = \"dark\" # `\"light\"` for terminals with a light background
 foreground = \"#ebdbb2\"
 background = \"#282828\"
 heading = \"#fabd2f\"
@@ -383,6 +697,8 @@ addition = \"#b8bb26\"
 deletion = \"#fb4934\"
 key = \"#d79921\"
 error = \"#cc241d\"
+selection = \"#504945\"
+search = \"#665c54\"
 
 [keymap.navigation]
 move_down     = [\'j\', \"Down\"]
@@ -392,6 +708,13 @@ previous_file = [\'K\']
 toggle_expand = [\" \", \"Tab\"]
 goto_top      = [\'g\']
 goto_bottom   = [\'G\']
+page_down     = [\"PageDown\"]
+page_up       = [\"PageUp\"]
+
+[keymap.actions]
+stage = [\'s\']
+open  = [\"Enter\", \'o\']
+quit  = [\'q\']
 ";
         assert_eq!(
             toml::from_str(INPUT),
@@ -399,10 +722,14 @@ goto_bottom   = [\'G\']
                 options: Options {
                     auto_expand_files: false,
                     auto_expand_hunks: true,
+                    auto_refresh: true,
                     editor: "nvim".to_string(),
                     lookahead_lines: 5,
+                    mouse: true,
+                    push_after_rename: false,
                     truncate_lines: true,
                     sort_branches: Some("-committerdate".to_string()),
+                    syntax_highlighting: true,
                     ws_error_highlight: WsErrorHighlight {
                         old: false,
                         new: true,
@@ -417,7 +744,9 @@ goto_bottom   = [\'G\']
                     addition: Color::from((184, 187, 38)),
                     deletion: Color::from((251, 73, 52)),
                     key: Color::from((215, 153, 33)),
-                    error: Color::from((204, 36, 29))
+                    error: Color::from((204, 36, 29)),
+                    selection: Color::from((80, 73, 69)),
+                    search: Color::from((102, 92, 84))
                 },
                 keymap: Keymaps {
                     navigation: HashMap::from([
@@ -431,9 +760,56 @@ goto_bottom   = [\'G\']
                         (KeyCode::Tab, Action::ToggleExpand),
                         (KeyCode::Char('g'), Action::GotoTop),
                         (KeyCode::Char('G'), Action::GotoBottom),
+                        (KeyCode::PageDown, Action::PageDown),
+                        (KeyCode::PageUp, Action::PageUp),
                     ]),
+                    actions: {
+                        let mut actions = Keymaps::default().actions;
+                        actions.insert(KeyCode::Char('o'), GexAction::Open);
+                        actions
+                    },
                 }
             })
         );
     }
+
+    #[test_case("[keymap.actions]\nstage = ['u']", "`u` can't be bound to both unstage and stage" ; "default binding")]
+    #[test_case(
+        "[keymap.actions]\nstage = ['x']\nunstage = ['x']",
+        "`x` can't be bound to both" ;
+        "two new bindings"
+    )]
+    #[test_case("[keymap.actions]\nstage = ['j']", "Char('j') can't be bound to both move_down and stage" ; "across keymaps")]
+    #[test_case("[keymap.actions]\nstage_everything = ['s']", "unknown variant `stage_everything`" ; "unknown action")]
+    fn parse_bad_keymap(input: &str, expected: &str) {
+        let error = toml::from_str::<Config>(input).unwrap_err().to_string();
+        assert!(error.contains(expected), "unexpected error: {error}");
+    }
+
+    #[test]
+    fn parse_swapped_keys() {
+        let config: Config =
+            toml::from_str("[keymap.actions]\nstage = ['u']\nunstage = ['s']").unwrap();
+        assert_eq!(config.keymap.actions[&KeyCode::Char('u')], GexAction::Stage);
+        assert_eq!(
+            config.keymap.actions[&KeyCode::Char('s')],
+            GexAction::Unstage
+        );
+    }
+
+    #[test]
+    fn parse_theme_with_overrides() {
+        let config: Config = toml::from_str(
+            "[colors]\ntheme = \"light\"\nheading = \"#89b4fa\"\nkey = \"ansi_(33)\"",
+        )
+        .unwrap();
+        assert_eq!(
+            config.colors,
+            Colors {
+                heading: Color::from((137, 180, 250)),
+                key: Color::AnsiValue(33),
+                ..Theme::Light.colors()
+            }
+        );
+    }
 }
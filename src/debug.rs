@@ -29,7 +29,7 @@ macro_rules! debug {
 
 #[macro_export]
 macro_rules! debug_draw {
-    () => {
+    ($renderer:expr) => {
         #[cfg(debug_assertions)]
         {
             if let Ok(mut buf) = $crate::debug::DBG_BUFFER.lock() {
@@ -50,6 +50,7 @@ macro_rules! debug_draw {
                         $crate::render::Clear(::crossterm::terminal::ClearType::UntilNewLine),
                     )
                 });
+                $renderer.invalidate_rows(0..prev_dimensions.1 as u16 + 1);
 
                 if !buf.is_empty() {
                     let max_width = buf.lines().map(|l| l.len()).max().expect("!buf.is_empty");
@@ -72,6 +73,7 @@ macro_rules! debug_draw {
                     buf.clear();
 
                     *prev_dimensions = (max_width + 3, count_lines + 1);
+                    $renderer.invalidate_rows(0..count_lines as u16 + 1);
                 }
             }
         }
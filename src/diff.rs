@@ -0,0 +1,287 @@
+//! The hunks of the unified diffs output by `git diff`, which are parsed by
+//! [`parse_diff`](crate::parse::parse_diff), and highlighting the words that changed in them.
+
+use std::{
+    fmt,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use anyhow::{anyhow, Result};
+use crossterm::style::Attribute;
+
+/// Whether the words that changed within modified lines are highlighted.
+pub static WORD_DIFF: AtomicBool = AtomicBool::new(false);
+
+/// A hunk of the changes to a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    pub old_start: usize,
+    pub old_count: usize,
+    pub new_start: usize,
+    pub new_count: usize,
+    /// The text after the `@@ ... @@` header, which git fills in with the enclosing function or
+    /// similar.
+    pub heading: String,
+    pub lines: Vec<DiffLine>,
+}
+
+/// A line of a [`Hunk`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine {
+    Context(String),
+    Added(String),
+    Deleted(String),
+    /// `\ No newline at end of file`, which applies to the line before it.
+    NoNewline,
+}
+
+impl DiffLine {
+    /// Parse a line of a hunk, which starts with ` `, `+`, `-` or `\`.
+    pub fn parse(line: &str) -> Result<Self> {
+        let content = line.get(1..).unwrap_or_default().to_string();
+        Ok(match line.chars().next() {
+            Some('+') => Self::Added(content),
+            Some('-') => Self::Deleted(content),
+            Some('\\') => Self::NoNewline,
+            // Some tools strip the trailing space of empty context lines.
+            Some(' ') | None => Self::Context(content),
+            Some(_) => return Err(anyhow!("unexpected line in hunk: `{line}`")),
+        })
+    }
+}
+
+/// Displays the hunk as it appears in the diff, e.g. for passing to `git apply`.
+impl fmt::Display for Hunk {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // git leaves the count out when it's 1.
+        let range = |start: usize, count: usize| {
+            if count == 1 {
+                start.to_string()
+            } else {
+                format!("{start},{count}")
+            }
+        };
+        write!(
+            f,
+            "@@ -{} +{} @@{}",
+            range(self.old_start, self.old_count),
+            range(self.new_start, self.new_count),
+            self.heading
+        )?;
+        for line in &self.lines {
+            match line {
+                DiffLine::Context(line) => write!(f, "\n {line}")?,
+                DiffLine::Added(line) => write!(f, "\n+{line}")?,
+                DiffLine::Deleted(line) => write!(f, "\n-{line}")?,
+                DiffLine::NoNewline => write!(f, "\n\\ No newline at end of file")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A piece of a line in the word diff of two lines.
+#[derive(Debug, PartialEq, Eq)]
+pub enum WordChunk {
+    Equal(String),
+    Deleted(String),
+    Added(String),
+}
+
+/// Split `line` into words and the whitespace between them, so that the tokens can be joined back
+/// into the line.
+fn tokens(line: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut previous = None;
+    for (i, c) in line.char_indices() {
+        let whitespace = c.is_whitespace();
+        if previous.is_some_and(|previous| previous != whitespace) {
+            tokens.push(&line[start..i]);
+            start = i;
+        }
+        previous = Some(whitespace);
+    }
+    if start < line.len() {
+        tokens.push(&line[start..]);
+    }
+    tokens
+}
+
+/// The words that were deleted from `old` and added in `new`, found with the Myers diff algorithm.
+/// Adjacent chunks of the same kind are merged.
+pub fn diff_words(old: &str, new: &str) -> Vec<WordChunk> {
+    let (old_tokens, new_tokens) = (tokens(old), tokens(new));
+    let (old_len, new_len) = (old_tokens.len(), new_tokens.len());
+    // Diagonal k contains the points where x - y = k - offset, where x and y are the number of
+    // old and new tokens that have been diffed.
+    let offset = old_len + new_len;
+    // The furthest x reached on each diagonal so far, and its value before each number of edits.
+    let mut furthest = vec![0; 2 * offset + 2];
+    let mut trace = Vec::new();
+    'search: for edits in 0..=offset {
+        trace.push(furthest.clone());
+        for k in (offset - edits..=offset + edits).step_by(2) {
+            let mut x = if k == offset - edits
+                || (k != offset + edits && furthest[k - 1] < furthest[k + 1])
+            {
+                furthest[k + 1]
+            } else {
+                furthest[k - 1] + 1
+            };
+            let mut y = x + offset - k;
+            while old_tokens
+                .get(x)
+                .is_some_and(|token| new_tokens.get(y) == Some(token))
+            {
+                x += 1;
+                y += 1;
+            }
+            furthest[k] = x;
+            if x >= old_len && y >= new_len {
+                break 'search;
+            }
+        }
+    }
+
+    // Walk back through the edits to find the path that was taken.
+    let mut chunks = Vec::new();
+    let (mut x, mut y) = (old_len, new_len);
+    for (edits, furthest) in trace.iter().enumerate().rev() {
+        let k = x + offset - y;
+        let previous_k =
+            if k == offset - edits || (k != offset + edits && furthest[k - 1] < furthest[k + 1]) {
+                k + 1
+            } else {
+                k - 1
+            };
+        let previous_x = furthest[previous_k];
+        let previous_y = (previous_x + offset).saturating_sub(previous_k);
+        while x > previous_x && y > previous_y {
+            x -= 1;
+            y -= 1;
+            chunks.push(WordChunk::Equal(old_tokens[x].to_string()));
+        }
+        if edits > 0 {
+            if x == previous_x {
+                y -= 1;
+                chunks.push(WordChunk::Added(new_tokens[y].to_string()));
+            } else {
+                x -= 1;
+                chunks.push(WordChunk::Deleted(old_tokens[x].to_string()));
+            }
+        }
+    }
+
+    let mut merged: Vec<WordChunk> = Vec::new();
+    for chunk in chunks.into_iter().rev() {
+        match (merged.last_mut(), chunk) {
+            (Some(WordChunk::Equal(last)), WordChunk::Equal(next))
+            | (Some(WordChunk::Deleted(last)), WordChunk::Deleted(next))
+            | (Some(WordChunk::Added(last)), WordChunk::Added(next)) => last.push_str(&next),
+            (_, chunk) => merged.push(chunk),
+        }
+    }
+    merged
+}
+
+/// The lines of a hunk with the words that changed highlighted, if [`WORD_DIFF`] is enabled. Only
+/// modified lines are highlighted, i.e. a run of deleted lines followed by a run of added lines,
+/// which are paired up in order.
+pub fn highlight_words(lines: &[DiffLine]) -> Vec<Option<String>> {
+    use fmt::Write;
+    let mut highlighted = vec![None; lines.len()];
+    if !WORD_DIFF.load(Ordering::Acquire) {
+        return highlighted;
+    }
+
+    let mut i = 0;
+    while i < lines.len() {
+        let mut deleted = Vec::new();
+        while let Some(DiffLine::Deleted(_) | DiffLine::NoNewline) = lines.get(i) {
+            deleted.push(i);
+            i += 1;
+        }
+        let mut added = Vec::new();
+        while let Some(DiffLine::Added(_) | DiffLine::NoNewline) = lines.get(i) {
+            added.push(i);
+            i += 1;
+        }
+        if deleted.is_empty() && added.is_empty() {
+            i += 1;
+        }
+        let deleted = deleted.into_iter().filter_map(|i| match &lines[i] {
+            DiffLine::Deleted(line) => Some((i, line)),
+            _ => None,
+        });
+        let added = added.into_iter().filter_map(|i| match &lines[i] {
+            DiffLine::Added(line) => Some((i, line)),
+            _ => None,
+        });
+        for ((i, old), (j, new)) in deleted.zip(added) {
+            let (mut old_words, mut new_words) = (String::new(), String::new());
+            for chunk in diff_words(old, new) {
+                match chunk {
+                    WordChunk::Equal(word) => {
+                        old_words.push_str(&word);
+                        new_words.push_str(&word);
+                    }
+                    WordChunk::Deleted(word) => {
+                        let _ = write!(
+                            old_words,
+                            "{}{word}{}",
+                            Attribute::Reverse,
+                            Attribute::NoReverse
+                        );
+                    }
+                    WordChunk::Added(word) => {
+                        let _ = write!(
+                            new_words,
+                            "{}{word}{}",
+                            Attribute::Reverse,
+                            Attribute::NoReverse
+                        );
+                    }
+                }
+            }
+            highlighted[i] = Some(old_words);
+            highlighted[j] = Some(new_words);
+        }
+    }
+    highlighted
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::WordChunk;
+
+    #[test_case("same line", "same line", &[WordChunk::Equal("same line".to_string())] ; "equal")]
+    #[test_case(
+        "let x = 1;",
+        "let y = 1;",
+        &[
+            WordChunk::Equal("let ".to_string()),
+            WordChunk::Deleted("x".to_string()),
+            WordChunk::Added("y".to_string()),
+            WordChunk::Equal(" = 1;".to_string()),
+        ] ;
+        "changed word"
+    )]
+    #[test_case(
+        "a c",
+        "a b c",
+        &[
+            WordChunk::Equal("a ".to_string()),
+            WordChunk::Added("b ".to_string()),
+            WordChunk::Equal("c".to_string()),
+        ] ;
+        "added word"
+    )]
+    #[test_case("", "new", &[WordChunk::Added("new".to_string())] ; "empty old")]
+    #[test_case("old", "", &[WordChunk::Deleted("old".to_string())] ; "empty new")]
+    fn diff_words(old: &str, new: &str, expected: &[WordChunk]) {
+        assert_eq!(super::diff_words(old, new), expected);
+    }
+}
@@ -0,0 +1,154 @@
+//! The errors that gex recovers from by showing them in the minibuffer, rather than exiting.
+
+use std::{
+    fmt, io,
+    process::{Command, Output, Stdio},
+};
+
+/// The ways that running git can fail.
+#[derive(Debug)]
+pub enum GexError {
+    /// There's no `git` to run.
+    GitNotFound,
+    /// The working directory isn't in a repository. This is checked on startup, but the repository
+    /// can still be removed while gex is running.
+    NotAGitRepository,
+    /// A git command printed something that isn't valid UTF-8.
+    Utf8Error(&'static str),
+    /// The output of a git command couldn't be parsed.
+    ParseError(String),
+    /// A git command couldn't be run.
+    IoError(io::Error),
+    /// The thread running a git command in the background panicked.
+    ThreadPanicked(&'static str),
+    /// A git command ran but failed, with what it printed to stderr.
+    Failed {
+        command: &'static str,
+        stderr: String,
+    },
+}
+
+impl fmt::Display for GexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::GitNotFound => write!(f, "`git` wasn't found, is it installed?"),
+            Self::NotAGitRepository => write!(f, "Not a git repository (or any parent directory)"),
+            Self::Utf8Error(command) => write!(f, "invalid utf8 from `git {command}`"),
+            Self::ParseError(e) => write!(f, "{e}"),
+            Self::IoError(e) => write!(f, "failed to run `git`: {e}"),
+            Self::ThreadPanicked(command) => write!(f, "`git {command}` panicked"),
+            Self::Failed { command, stderr } => write!(f, "`git {command}` failed: {stderr}"),
+        }
+    }
+}
+
+impl std::error::Error for GexError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::IoError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl GexError {
+    /// Run `git` with `args`, which is `git <command>` with any options, and check its outcome.
+    pub fn run(command: &'static str, args: &[&str]) -> Result<Output, Self> {
+        Self::check(command, Command::new("git").args(args).output())
+    }
+
+    /// Check the outcome of running `git <command>`, giving back its output if it succeeded.
+    pub fn check(command: &'static str, output: io::Result<Output>) -> Result<Output, Self> {
+        Self::check_in(command, output, || {
+            Command::new("git")
+                .args(["rev-parse", "--git-dir"])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .is_ok_and(|status| status.success())
+        })
+    }
+
+    /// [`GexError::check`], asking `in_repository` whether the working directory is still in a
+    /// repository if the command failed. That's checked with git, since its messages can be
+    /// translated.
+    fn check_in(
+        command: &'static str,
+        output: io::Result<Output>,
+        in_repository: impl FnOnce() -> bool,
+    ) -> Result<Output, Self> {
+        let output = output.map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => Self::GitNotFound,
+            _ => Self::IoError(e),
+        })?;
+        if output.status.success() {
+            return Ok(output);
+        }
+        if in_repository() {
+            Err(Self::Failed {
+                command,
+                stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            })
+        } else {
+            Err(Self::NotAGitRepository)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io,
+        process::{ExitStatus, Output},
+    };
+
+    use test_case::test_case;
+
+    use super::GexError;
+
+    /// The status that a process exiting with `code` would have.
+    fn exit_status(code: u8) -> ExitStatus {
+        #[cfg(unix)]
+        return std::os::unix::process::ExitStatusExt::from_raw(i32::from(code) << 8);
+        #[cfg(windows)]
+        return std::os::windows::process::ExitStatusExt::from_raw(u32::from(code));
+    }
+
+    fn output(code: u8, stderr: &str) -> Output {
+        Output {
+            status: exit_status(code),
+            stdout: b"# branch.head main\0".to_vec(),
+            stderr: stderr.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn check_success() {
+        let output = GexError::check_in("status", Ok(output(0, "")), || true).unwrap();
+        assert_eq!(output.stdout, b"# branch.head main\0");
+    }
+
+    #[test_case(
+        Err(io::ErrorKind::NotFound.into()), true
+        => matches GexError::GitNotFound
+        ; "git not found"
+    )]
+    #[test_case(
+        Err(io::ErrorKind::PermissionDenied.into()), true
+        => matches GexError::IoError(e) if e.kind() == io::ErrorKind::PermissionDenied
+        ; "io error"
+    )]
+    #[test_case(
+        Ok(output(128, "fatal: ni un repositorio git\n")), false
+        => matches GexError::NotAGitRepository
+        ; "not a repository"
+    )]
+    #[test_case(
+        Ok(output(128, "fatal: bad object HEAD\n")), true
+        => matches GexError::Failed { stderr, .. } if stderr == "fatal: bad object HEAD"
+        ; "failed"
+    )]
+    fn check_failure(output: io::Result<Output>, in_repository: bool) -> GexError {
+        GexError::check_in("status", output, || in_repository).unwrap_err()
+    }
+}
@@ -0,0 +1,201 @@
+//! Module relating to the help overlay, which lists the key bindings.
+
+use std::{collections::BTreeMap, ops::Range};
+
+use crossterm::{
+    cursor::MoveTo,
+    event::KeyCode,
+    style::{Attribute, SetForegroundColor},
+};
+
+use crate::{
+    command::GexCommand,
+    config,
+    config::{Action, Keymaps, CONFIG},
+    render::{self, ResetAttributes},
+};
+
+/// The width of the key column.
+const KEY_WIDTH: usize = 12;
+
+/// An overlay listing the key bindings. It's generated from the same keymaps and commands that
+/// handle the input so that it can't go out of date.
+#[derive(Debug, Default)]
+pub struct Help {
+    /// The first row of bindings that is displayed, for when they don't all fit on the screen.
+    scroll: usize,
+}
+
+/// A row of the overlay.
+enum Row {
+    Heading(&'static str),
+    Binding(String, String),
+}
+
+/// A human readable name for `key`.
+pub fn key_name(key: KeyCode) -> String {
+    match key {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::F(n) => format!("F{n}"),
+        key => format!("{key:?}"),
+    }
+}
+
+/// The bindings of one keymap, with the keys bound to the same action grouped together.
+fn bindings<'a, A: Ord + 'a>(
+    keymap: impl IntoIterator<Item = (&'a KeyCode, &'a A)>,
+    description: impl Fn(&A) -> &'static str,
+) -> Vec<Row> {
+    let mut actions = BTreeMap::<_, Vec<_>>::new();
+    for (key, action) in keymap {
+        actions.entry(action).or_default().push(key_name(*key));
+    }
+    actions
+        .into_iter()
+        .map(|(action, mut keys)| {
+            keys.sort();
+            Row::Binding(keys.join(" / "), description(action).to_string())
+        })
+        .collect()
+}
+
+fn rows(keymap: &Keymaps) -> Vec<Row> {
+    let commands = GexCommand::commands().iter().map(|(key, cmd)| {
+        let subcommands = cmd
+            .subcommands()
+            .iter()
+            .map(|(key, subcmd)| format!("{key} {subcmd}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        Row::Binding(
+            key.to_string(),
+            format!("{} ({subcommands})", format!("{cmd:?}").to_lowercase()),
+        )
+    });
+
+    let mut rows = vec![Row::Heading("Navigation")];
+    rows.extend(bindings(&keymap.navigation, Action::description));
+    rows.push(Row::Binding(
+        "Ctrl-d / Ctrl-u".to_string(),
+        "half page down / up".to_string(),
+    ));
+    rows.push(Row::Heading("Actions"));
+    rows.extend(bindings(&keymap.actions, |action| action.description()));
+    rows.push(Row::Heading("Commands"));
+    rows.extend(commands);
+    rows.push(Row::Binding(
+        "Esc".to_string(),
+        "cancel current".to_string(),
+    ));
+    rows
+}
+
+/// The default key bindings as plain text, for `--help`.
+pub fn summary() -> String {
+    use std::fmt::Write;
+    let mut summary = String::from("Default key bindings:");
+    for row in rows(&Keymaps::default()) {
+        let _ = match row {
+            Row::Heading(heading) => write!(summary, "\n\n{heading}:"),
+            Row::Binding(keys, description) => {
+                write!(summary, "\n  {keys:<KEY_WIDTH$} {description}")
+            }
+        };
+    }
+    summary
+}
+
+impl Help {
+    /// Draw the overlay on top of whatever has already been rendered. Returns the rows it was drawn
+    /// on.
+    pub fn draw(&self, term_width: u16, term_height: u16) -> Range<u16> {
+        let config = CONFIG.get().expect("config wasn't initialised");
+        let rows = rows(&config.keymap);
+
+        let content_width = rows
+            .iter()
+            .map(|row| match row {
+                Row::Heading(heading) => heading.chars().count(),
+                Row::Binding(_, description) => KEY_WIDTH + 1 + description.chars().count(),
+            })
+            .max()
+            .unwrap_or_default();
+        // Two columns on each side for the border and padding.
+        let width = (content_width + 4).min(term_width.into());
+        let inner_width = width.saturating_sub(4);
+        let visible_rows = usize::from(term_height).saturating_sub(2).min(rows.len());
+        let scroll = self.scroll.min(rows.len() - visible_rows);
+        let left = usize::from(term_width).saturating_sub(width) / 2;
+        let top = usize::from(term_height).saturating_sub(visible_rows + 2) / 2;
+        let move_to = |row: usize| MoveTo(left as u16, (top + row) as u16);
+
+        let title = " Key bindings ";
+        print!(
+            "{}{ResetAttributes}┌─{title}{}┐",
+            move_to(0),
+            "─".repeat(inner_width.saturating_sub(title.len()) + 1),
+        );
+        for (i, row) in rows.iter().skip(scroll).take(visible_rows).enumerate() {
+            let (text, len) = match row {
+                Row::Heading(heading) => (
+                    format!(
+                        "{}{heading}{}",
+                        SetForegroundColor(config.colors.heading),
+                        SetForegroundColor(config.colors.foreground)
+                    ),
+                    heading.chars().count(),
+                ),
+                Row::Binding(keys, description) => {
+                    let description = description
+                        .chars()
+                        .take(inner_width.saturating_sub(KEY_WIDTH + 1))
+                        .collect::<String>();
+                    (
+                        format!(
+                            "{}{}{keys:<KEY_WIDTH$}{ResetAttributes} {description}",
+                            SetForegroundColor(config.colors.key),
+                            Attribute::Bold,
+                        ),
+                        keys.chars().count().max(KEY_WIDTH) + 1 + description.chars().count(),
+                    )
+                }
+            };
+            let text = if render::plain() {
+                render::strip_styles(&text)
+            } else {
+                text
+            };
+            print!(
+                "{}│ {text}{} │",
+                move_to(i + 1),
+                " ".repeat(inner_width.saturating_sub(len))
+            );
+        }
+        print!(
+            "{}└{}┘",
+            move_to(visible_rows + 1),
+            "─".repeat(width.saturating_sub(2))
+        );
+        top as u16..(top + visible_rows + 2) as u16
+    }
+
+    /// Scroll the bindings if they don't fit on the screen. Returns `false` when the key should
+    /// dismiss the overlay instead.
+    pub fn scroll(&mut self, action: Option<&Action>, term_height: u16) -> bool {
+        let max_scroll = rows(&config!().keymap)
+            .len()
+            .saturating_sub(usize::from(term_height).saturating_sub(2));
+        match action {
+            Some(Action::MoveDown) if max_scroll > 0 => {
+                self.scroll = (self.scroll + 1).min(max_scroll);
+            }
+            Some(Action::MoveUp) if max_scroll > 0 => self.scroll = self.scroll.saturating_sub(1),
+            _ => {
+                self.scroll = 0;
+                return false;
+            }
+        }
+        true
+    }
+}
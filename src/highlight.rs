@@ -0,0 +1,173 @@
+//! Module relating to syntax highlighting of diffs. The lines are split into tokens with a simple
+//! tokenizer that knows the keywords, strings, numbers and comments of common languages, which is
+//! good enough for reading a diff without pulling in full grammars.
+//!
+//! Only attributes are used for the highlighting, so that the colours of the added and deleted
+//! lines still show what changed and tokens are readable with any colour theme.
+
+use std::path::Path;
+
+use crossterm::style::Attribute;
+
+/// A language that can be highlighted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    Rust,
+    C,
+    Go,
+    JavaScript,
+    Python,
+    Shell,
+}
+
+impl Language {
+    /// The language of the file at `path`, from its extension.
+    pub fn from_path(path: &str) -> Option<Self> {
+        Some(match Path::new(path).extension()?.to_str()? {
+            "rs" => Self::Rust,
+            "c" | "h" | "cc" | "cpp" | "cxx" | "hh" | "hpp" => Self::C,
+            "go" => Self::Go,
+            "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" => Self::JavaScript,
+            "py" | "pyi" => Self::Python,
+            "sh" | "bash" | "zsh" => Self::Shell,
+            _ => return None,
+        })
+    }
+
+    /// The keywords of the language, separated by spaces.
+    const fn keywords(self) -> &'static str {
+        match self {
+            Self::Rust => {
+                "as async await break const continue crate dyn else enum extern false fn for if \
+                impl in let loop match mod move mut pub ref return self Self static struct super \
+                trait true type unsafe use where while"
+            }
+            Self::C => {
+                "auto bool break case char class const continue default delete do double else enum \
+                extern false float for goto if inline int long namespace new nullptr private \
+                protected public return short signed sizeof static struct switch template this \
+                true typedef union unsigned using virtual void volatile while"
+            }
+            Self::Go => {
+                "break case chan const continue default defer else fallthrough false for func go \
+                goto if import interface map nil package range return select struct switch true \
+                type var"
+            }
+            Self::JavaScript => {
+                "async await break case catch class const continue default delete do else export \
+                extends false finally for from function if import in instanceof interface let new \
+                null of return static super switch this throw true try type typeof undefined var \
+                void while yield"
+            }
+            Self::Python => {
+                "and as assert async await break class continue def del elif else except False \
+                finally for from global if import in is lambda None nonlocal not or pass raise \
+                return self True try while with yield"
+            }
+            Self::Shell => {
+                "case do done elif else esac export fi for function if in local return then until \
+                while"
+            }
+        }
+    }
+
+    const fn line_comment(self) -> &'static str {
+        match self {
+            Self::Rust | Self::C | Self::Go | Self::JavaScript => "//",
+            Self::Python | Self::Shell => "#",
+        }
+    }
+
+    /// Whether `quote` starts a string. Rust uses `'` for lifetimes as well as characters, which
+    /// can't be told apart without looking further ahead, so those aren't highlighted.
+    const fn is_quote(self, quote: char) -> bool {
+        match self {
+            Self::Rust => quote == '"',
+            Self::JavaScript | Self::Go | Self::Shell => matches!(quote, '"' | '\'' | '`'),
+            Self::C | Self::Python => matches!(quote, '"' | '\''),
+        }
+    }
+}
+
+/// `line` with the keywords in bold, the strings and numbers in italics and the comments dimmed.
+/// Whitespace is left as it is, so trailing whitespace can still be highlighted afterwards.
+pub fn highlight(line: &str, language: Language) -> String {
+    use std::fmt::Write;
+    let code = line.trim_end();
+    let mut highlighted = String::with_capacity(line.len());
+    let mut rest = code;
+    while let Some(c) = rest.chars().next() {
+        let (len, start, end) = if rest.starts_with(language.line_comment()) {
+            (rest.len(), Attribute::Dim, Attribute::NormalIntensity)
+        } else if language.is_quote(c) {
+            // The string ends at the next quote that isn't escaped, or at the end of the line.
+            let mut escaped = false;
+            let len = rest[1..]
+                .find(|next| {
+                    let end = next == c && !escaped;
+                    escaped = next == '\\' && !escaped;
+                    end
+                })
+                .map_or(rest.len(), |i| i + 2);
+            (len, Attribute::Italic, Attribute::NoItalic)
+        } else if c.is_alphanumeric() || c == '_' {
+            let len = rest
+                .find(|c: char| !c.is_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            if c.is_ascii_digit() {
+                (len, Attribute::Italic, Attribute::NoItalic)
+            } else if language
+                .keywords()
+                .split(' ')
+                .any(|keyword| keyword == &rest[..len])
+            {
+                (len, Attribute::Bold, Attribute::NormalIntensity)
+            } else {
+                highlighted.push_str(&rest[..len]);
+                rest = &rest[len..];
+                continue;
+            }
+        } else {
+            highlighted.push(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        };
+        let _ = write!(highlighted, "{start}{}{end}", &rest[..len]);
+        rest = &rest[len..];
+    }
+    highlighted.push_str(&line[code.len()..]);
+    highlighted
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::Language;
+
+    #[test_case("src/main.rs", Some(Language::Rust) ; "rust")]
+    #[test_case("web/app.tsx", Some(Language::JavaScript) ; "typescript")]
+    #[test_case("Makefile", None ; "no extension")]
+    #[test_case("notes.txt", None ; "unknown extension")]
+    fn language_from_path(path: &str, expected: Option<Language>) {
+        assert_eq!(Language::from_path(path), expected);
+    }
+
+    #[test_case(
+        "let x = 10; // ten  ",
+        Language::Rust,
+        "\x1b[1mlet\x1b[22m x = \x1b[3m10\x1b[23m; \x1b[2m// ten\x1b[22m  " ;
+        "keyword, number and comment"
+    )]
+    #[test_case(
+        r#"print("a \" # b")"#,
+        Language::Python,
+        "print(\x1b[3m\"a \\\" # b\"\x1b[23m)" ;
+        "string with escaped quote"
+    )]
+    #[test_case("fn f<'a>()", Language::Rust, "\x1b[1mfn\x1b[22m f<'a>()" ; "lifetime")]
+    #[test_case("echo 'unterminated", Language::Shell, "echo \x1b[3m'unterminated\x1b[23m" ; "unterminated")]
+    fn highlight(line: &str, language: Language, expected: &str) {
+        assert_eq!(super::highlight(line, language), expected);
+    }
+}
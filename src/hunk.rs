@@ -0,0 +1,259 @@
+//! Module relating to the hunk view, which shows the unstaged hunks of a single file so that they
+//! can be staged or discarded one at a time.
+
+use std::{
+    fmt,
+    io::Write,
+    process::{Command, Stdio},
+    rc::Rc,
+    sync::atomic::Ordering,
+};
+
+use anyhow::{Context, Result};
+use crossterm::{
+    event::{KeyCode, KeyEvent},
+    style::{Attribute, SetForegroundColor},
+};
+
+use crate::{
+    config::{Action, CONFIG},
+    diff::{self, DiffLine, Hunk},
+    git_process,
+    minibuffer::MiniBuffer,
+    parse,
+    render::{self, Renderer, ResetAttributes},
+    status, View,
+};
+
+/// The patch passed to `git apply` for `hunk` of the file at `path`, which has to match what
+/// `git diff` printed for it byte for byte.
+fn patch(path: &str, hunk: &str) -> String {
+    format!("--- a/{path}\n+++ b/{path}\n{hunk}\n")
+}
+
+/// Apply `hunk` of the file at `path` with `git apply`, passing it `args`.
+pub fn apply_hunk(path: &str, hunk: &str, args: &[&str]) -> Result<()> {
+    let mut child = Command::new("git")
+        .arg("apply")
+        .args(args)
+        .arg("-")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("failed to spawn `git apply`")?;
+    child
+        .stdin
+        .take()
+        .context("failed to open child stdin")?
+        .write_all(patch(path, hunk).as_bytes())
+        .context("failed to apply hunk")?;
+    MiniBuffer::push_command_output(
+        &child
+            .wait_with_output()
+            .context("failed to wait for `git apply`")?,
+    );
+    Ok(())
+}
+
+/// The unstaged hunks of a file.
+#[derive(Debug, Default)]
+pub struct HunkView {
+    path: String,
+    hunks: Vec<Hunk>,
+    /// The index of the selected hunk.
+    cursor: usize,
+}
+
+impl render::Render for HunkView {
+    fn render(&self, f: &mut Renderer) -> fmt::Result {
+        use fmt::Write;
+        let config = CONFIG.get().expect("config wasn't initialised");
+
+        writeln!(f, "\r{}{}{ResetAttributes}", Attribute::Bold, self.path)?;
+        if self.hunks.is_empty() {
+            return write!(
+                f,
+                "\r\n{}No unstaged changes left.{}\r\n\nPress Esc to go back.",
+                SetForegroundColor(config.colors.heading),
+                SetForegroundColor(config.colors.foreground),
+            );
+        }
+
+        for (i, hunk) in self.hunks.iter().enumerate() {
+            writeln!(f, "\r")?;
+            if i == self.cursor {
+                f.insert_cursor();
+                write!(f, "{}", Attribute::Reverse)?;
+            }
+            writeln!(
+                f,
+                "\r{}@@ -{},{} +{},{} @@{}{ResetAttributes}",
+                SetForegroundColor(config.colors.hunk_head),
+                hunk.old_start,
+                hunk.old_count,
+                hunk.new_start,
+                hunk.new_count,
+                hunk.heading,
+            )?;
+            for (line, words) in hunk.lines.iter().zip(diff::highlight_words(&hunk.lines)) {
+                match line {
+                    DiffLine::Added(line) => writeln!(
+                        f,
+                        "\r{}+{}{ResetAttributes}",
+                        SetForegroundColor(config.colors.addition),
+                        words.as_ref().unwrap_or(line),
+                    )?,
+                    DiffLine::Deleted(line) => writeln!(
+                        f,
+                        "\r{}-{}{ResetAttributes}",
+                        SetForegroundColor(config.colors.deletion),
+                        words.as_ref().unwrap_or(line),
+                    )?,
+                    DiffLine::Context(line) => {
+                        writeln!(f, "\r{} {line}{ResetAttributes}", Attribute::Dim)?;
+                    }
+                    DiffLine::NoNewline => writeln!(
+                        f,
+                        "\r{}\\ No newline at end of file{ResetAttributes}",
+                        Attribute::Dim
+                    )?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl HunkView {
+    /// Show the unstaged hunks of the file at `path`.
+    pub fn open(&mut self, path: &str) -> Result<()> {
+        path.clone_into(&mut self.path);
+        self.cursor = 0;
+        self.fetch()
+    }
+
+    /// The path of the file whose hunks are shown.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn fetch(&mut self) -> Result<()> {
+        let output = git_process(&[
+            "-c",
+            "core.quotePath=false",
+            "diff",
+            "--no-ext-diff",
+            "--no-color",
+            "--",
+            &self.path,
+        ])?;
+        let diff =
+            std::str::from_utf8(&output.stdout).context("malformed stdout from `git diff`")?;
+        self.hunks = parse::parse_diff(diff)?
+            .into_iter()
+            .next()
+            .map(|file| file.hunks)
+            .unwrap_or_default();
+        self.cursor = self.cursor.min(self.hunks.len().saturating_sub(1));
+        Ok(())
+    }
+
+    /// Handle a key press. Returns `true` when the user wants to leave the hunk view.
+    pub fn handle_input(
+        &mut self,
+        key_event: KeyEvent,
+        action: Option<&Action>,
+        minibuffer: &mut MiniBuffer,
+        view: &mut View,
+    ) -> Result<bool> {
+        let last_hunk = self.hunks.len().saturating_sub(1);
+        match action {
+            Some(Action::MoveDown | Action::NextFile) => {
+                self.cursor = (self.cursor + 1).min(last_hunk);
+            }
+            Some(Action::MoveUp | Action::PreviousFile) => {
+                self.cursor = self.cursor.saturating_sub(1);
+            }
+            Some(Action::GotoTop) => self.cursor = 0,
+            Some(Action::GotoBottom) => self.cursor = last_hunk,
+            _ => {}
+        }
+        if matches!(key_event.code, KeyCode::Esc | KeyCode::Char('q')) {
+            return Ok(true);
+        }
+
+        let Some(hunk) = self.hunks.get(self.cursor) else {
+            return Ok(false);
+        };
+        match key_event.code {
+            KeyCode::Char('w') => {
+                diff::WORD_DIFF.fetch_xor(true, Ordering::AcqRel);
+                // So that the status has the same highlighting when going back to it.
+                status::REFRESH_FLAG.store(true, Ordering::Release);
+            }
+            KeyCode::Char('e') => {
+                status::edit_file(&self.path, hunk.new_start.max(1))?;
+                self.fetch()?;
+            }
+            KeyCode::Char('s') => {
+                apply_hunk(&self.path, &hunk.to_string(), &["--cached"])?;
+                self.fetch()?;
+            }
+            KeyCode::Char('d') => {
+                let (path, diff) = (self.path.clone(), hunk.to_string());
+                minibuffer.confirm(
+                    Rc::new(move || {
+                        apply_hunk(&path, &diff, &["--reverse"])?;
+                        status::REFRESH_FLAG.store(true, Ordering::Release);
+                        Ok(())
+                    }),
+                    &format!("Discard hunk {} of {}?", self.cursor + 1, self.path),
+                    view,
+                    View::Hunks,
+                );
+            }
+            _ => {}
+        }
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use crate::parse;
+
+    const CHANGED: &str = "diff --git a/src/lib.rs b/src/lib.rs
+index 3b18e51..a042389 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,4 +1,4 @@ mod tests {
+ fn one() {}
+-fn two() {}
++fn zwei() {}
+ 
+ fn three() {}
+";
+
+    const CRLF_NO_NEWLINE: &str = "diff --git a/win.txt b/win.txt
+index db69dd7..2977631 100644
+--- a/win.txt
++++ b/win.txt
+@@ -1,2 +1,3 @@
+ a\r
+-b\r
++B\r
++c
+\\ No newline at end of file
+";
+
+    #[test_case(CHANGED ; "changed")]
+    #[test_case(CRLF_NO_NEWLINE ; "crlf and no newline at end of file")]
+    fn patch_round_trips(diff: &str) {
+        let file = &parse::parse_diff(diff).unwrap()[0];
+        let patch = super::patch(file.path(), &file.hunks[0].to_string());
+        assert_eq!(patch, diff[diff.find("--- ").unwrap()..]);
+    }
+}
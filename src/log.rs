@@ -0,0 +1,415 @@
+//! Module relating to the commit history view.
+
+use std::{fmt, process::Command, rc::Rc, sync::atomic::Ordering};
+
+use anyhow::{Context, Result};
+use crossterm::{
+    cursor,
+    event::{KeyCode, KeyEvent},
+    style::{Attribute, SetForegroundColor},
+};
+
+use crate::{
+    command,
+    config::{Action, CONFIG},
+    git_process, interactive_process,
+    minibuffer::{MessageType, MiniBuffer},
+    pager::Pager,
+    render::{self, Renderer, ResetAttributes},
+    status, View,
+};
+
+/// A single commit from `git log`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct LogEntry {
+    /// The abbreviated commit hash.
+    pub hash: String,
+    /// The refs pointing at this commit, e.g. `HEAD -> main, origin/main`.
+    pub decorations: Option<String>,
+    pub subject: String,
+    /// The part of the `--graph` drawn on the line of this commit.
+    pub graph: String,
+    /// The lines of the graph between this commit and the next, which connect their branches.
+    pub connectors: Vec<String>,
+}
+
+impl LogEntry {
+    /// Parse a line of `git log --graph --pretty=format:%h%x00%D%x00%s`. This contains the same
+    /// information as `--oneline --decorate=short`, but the fields are separated by NUL so that a
+    /// subject beginning with a parenthesis can't be mistaken for decorations.
+    fn parse(line: &str) -> Result<Self> {
+        let (graph, line) = split_graph(line);
+        let mut fields = line.splitn(3, '\0');
+        let (Some(hash), Some(decorations), Some(subject)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            anyhow::bail!("unexpected line in `git log` output: `{line}`");
+        };
+        Ok(Self {
+            hash: hash.to_string(),
+            decorations: (!decorations.is_empty()).then(|| decorations.to_string()),
+            subject: subject.to_string(),
+            graph: graph.to_string(),
+            connectors: Vec::new(),
+        })
+    }
+}
+
+/// Split a line of `git log --graph` into the graph and the rest of the line.
+fn split_graph(line: &str) -> (&str, &str) {
+    line.split_at(
+        line.find(|c: char| !matches!(c, '*' | '|' | '/' | '\\' | '_' | '-' | '.' | ' '))
+            .unwrap_or(line.len()),
+    )
+}
+
+/// Draw the graph from `git log --graph` with box-drawing characters.
+fn draw_graph(graph: &str) -> String {
+    graph
+        .chars()
+        .map(|c| match c {
+            '*' => '●',
+            '|' => '│',
+            '/' => '╱',
+            '\\' => '╲',
+            '-' => '─',
+            c => c,
+        })
+        .collect()
+}
+
+/// Why `name` isn't a valid ref name, following the rules of `git check-ref-format`.
+pub fn invalid_ref_name(name: &str) -> Option<&'static str> {
+    if name.is_empty() {
+        Some("it's empty")
+    } else if name == "@" {
+        Some("it can't be `@`")
+    } else if name.starts_with('-') {
+        // It would be taken for an option, and git refuses it as a tag or branch name anyway.
+        Some("it can't begin with `-`")
+    } else if let Some(c) = name.chars().find(|c| {
+        c.is_ascii_control() || matches!(c, ' ' | '~' | '^' | ':' | '?' | '*' | '[' | '\\')
+    }) {
+        Some(match c {
+            ' ' => "it can't contain spaces",
+            c if c.is_ascii_control() => "it can't contain control characters",
+            _ => "it can't contain any of `~^:?*[\\`",
+        })
+    } else if name.contains("..") {
+        Some("it can't contain `..`")
+    } else if name.contains("@{") {
+        Some("it can't contain `@{`")
+    } else if name.ends_with('.') {
+        Some("it can't end with `.`")
+    } else if name.split('/').any(|component| component.is_empty()) {
+        Some("it can't begin or end with `/` or contain `//`")
+    } else if name.split('/').any(|component| component.starts_with('.')) {
+        Some("no part of it can begin with `.`")
+    } else if name
+        .split('/')
+        .any(|component| component.strip_suffix(".lock").is_some())
+    {
+        Some("no part of it can end with `.lock`")
+    } else {
+        None
+    }
+}
+
+/// Ask for the name of a new tag on `target`, e.g. a commit hash or `HEAD`, and then whether it
+/// should be lightweight or annotated. `return_view` is shown again once the tag is created.
+pub fn new_tag(target: &str, return_view: View, minibuffer: &mut MiniBuffer, view: &mut View) {
+    let target = target.to_string();
+    let return_to = return_view.clone();
+    minibuffer.get_input_then(
+        Rc::new(move |name| {
+            print!("{}", cursor::Hide);
+            let Some(name) = name.map(str::trim) else {
+                return Ok(None);
+            };
+            if let Some(reason) = invalid_ref_name(name) {
+                MiniBuffer::push(
+                    &format!("`{name}` isn't a valid tag name: {reason}."),
+                    MessageType::Error,
+                );
+                return Ok(None);
+            }
+            let (name, target, return_view) = (name.to_string(), target.clone(), return_to.clone());
+            Ok(Some(Box::new(move |minibuffer, view| {
+                choose_tag_kind(name, target, return_view, minibuffer, view);
+            })))
+        }),
+        Some("Tag name: "),
+        view,
+        return_view,
+    );
+}
+
+/// Ask whether the tag that the user just named should be lightweight or annotated, and create
+/// it on `target`. Annotated tags are created in the editor so that the message can be written.
+fn choose_tag_kind(
+    name: String,
+    target: String,
+    return_view: View,
+    minibuffer: &mut MiniBuffer,
+    view: &mut View,
+) {
+    minibuffer.read_key(
+        Rc::new(move |key| {
+            let output = match key {
+                Some("l") => git_process(&["tag", "--", &name, &target])?,
+                Some("a") => interactive_process(
+                    Command::new("git").args(["tag", "-a", "--", &name, &target]),
+                )?,
+                _ => return Ok(()),
+            };
+            MiniBuffer::push_command_output(&output);
+            status::REFRESH_FLAG.store(true, Ordering::Release);
+            Ok(())
+        }),
+        "Lightweight or annotated tag? [l/a] ",
+        view,
+        return_view,
+    );
+}
+
+/// The commit history of the current branch.
+#[derive(Debug, Default)]
+pub struct LogView {
+    pub entries: Vec<LogEntry>,
+    /// The index of the selected commit.
+    pub cursor: usize,
+    /// The output of `git show` for the selected commit, if it's being shown. It's scrolled
+    /// separately from the cursor so that the position in the history isn't lost when going back.
+    show: Pager,
+    /// Whether the graph of the branches and merges is hidden.
+    hide_graph: bool,
+}
+
+impl render::Render for LogView {
+    fn render(&self, f: &mut Renderer) -> fmt::Result {
+        use fmt::Write;
+        let config = CONFIG.get().expect("config wasn't initialised");
+
+        if self.show.is_open() {
+            return self.show.render(f);
+        }
+
+        if self.entries.is_empty() {
+            return write!(
+                f,
+                "{}No commits yet.{}",
+                SetForegroundColor(config.colors.heading),
+                SetForegroundColor(config.colors.foreground),
+            );
+        }
+
+        for (i, entry) in self.entries.iter().enumerate() {
+            if i == self.cursor {
+                f.insert_cursor();
+            }
+            if !self.hide_graph {
+                write!(f, "\r{}", draw_graph(&entry.graph))?;
+            }
+            write!(
+                f,
+                "{}{}{} ",
+                SetForegroundColor(config.colors.hunk_head),
+                entry.hash,
+                SetForegroundColor(config.colors.foreground),
+            )?;
+            if let Some(decorations) = &entry.decorations {
+                write!(
+                    f,
+                    "{}({decorations}){} ",
+                    SetForegroundColor(config.colors.heading),
+                    SetForegroundColor(config.colors.foreground),
+                )?;
+            }
+            if i == self.cursor {
+                writeln!(
+                    f,
+                    "{}{}{ResetAttributes}",
+                    Attribute::Reverse,
+                    entry.subject
+                )?;
+            } else {
+                writeln!(f, "{}", entry.subject)?;
+            }
+            if !self.hide_graph {
+                for connector in &entry.connectors {
+                    writeln!(f, "\r{}", draw_graph(connector))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl LogView {
+    pub fn fetch(&mut self) -> Result<()> {
+        self.show.close();
+
+        let output = git_process(&["log", "--graph", "--pretty=format:%h%x00%D%x00%s"])?;
+        // This fails when there aren't any commits yet, which is displayed as an empty history.
+        if !output.status.success() {
+            self.entries.clear();
+            self.cursor = 0;
+            return Ok(());
+        }
+
+        self.entries.clear();
+        for line in std::str::from_utf8(&output.stdout)
+            .context("broken stdout from `git log`")?
+            .lines()
+        {
+            // Lines that are only part of the graph belong to the commit above them.
+            match self.entries.last_mut() {
+                Some(entry) if split_graph(line).1.is_empty() => {
+                    entry.connectors.push(line.to_string());
+                }
+                _ => self.entries.push(LogEntry::parse(line)?),
+            }
+        }
+        self.cursor = self.cursor.min(self.entries.len().saturating_sub(1));
+        Ok(())
+    }
+
+    /// Move the cursor to the commit `hash`, which may be abbreviated differently. Returns `false`
+    /// if it isn't in the history.
+    pub fn select(&mut self, hash: &str) -> bool {
+        let Some(index) = self
+            .entries
+            .iter()
+            .position(|entry| hash.starts_with(&entry.hash) || entry.hash.starts_with(hash))
+        else {
+            return false;
+        };
+        self.cursor = index;
+        true
+    }
+
+    /// Handle a key press. Returns `true` when the user wants to leave the log view.
+    pub fn handle_input(
+        &mut self,
+        key_event: KeyEvent,
+        action: Option<&Action>,
+        minibuffer: &mut MiniBuffer,
+        view: &mut View,
+    ) -> Result<bool> {
+        if self.show.handle_input(key_event, action) {
+            return Ok(false);
+        }
+
+        let last_entry = self.entries.len().saturating_sub(1);
+        match action {
+            Some(Action::MoveDown) => self.cursor = (self.cursor + 1).min(last_entry),
+            Some(Action::MoveUp) => self.cursor = self.cursor.saturating_sub(1),
+            Some(Action::GotoTop) => self.cursor = 0,
+            Some(Action::GotoBottom) => self.cursor = last_entry,
+            _ => {}
+        }
+        match key_event.code {
+            KeyCode::Esc | KeyCode::Char('q') => return Ok(true),
+            KeyCode::Char('r') => command::rebase(minibuffer, view)?,
+            KeyCode::Char('y') => {
+                if let Some(entry) = self.entries.get(self.cursor) {
+                    command::cherry_pick(&entry.hash, view)?;
+                }
+            }
+            KeyCode::Char('|') => self.hide_graph = !self.hide_graph,
+            KeyCode::Char('t') => {
+                if let Some(entry) = self.entries.get(self.cursor) {
+                    new_tag(&entry.hash, View::Log, minibuffer, view);
+                }
+            }
+            KeyCode::Enter => {
+                if let Some(entry) = self.entries.get(self.cursor) {
+                    let output = git_process(&[
+                        "-c",
+                        "core.quotePath=false",
+                        "show",
+                        "--no-ext-diff",
+                        "--no-color",
+                        &entry.hash,
+                    ])?;
+                    self.show.open(&output.stdout, true);
+                }
+            }
+            _ => {}
+        }
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::{invalid_ref_name, LogEntry};
+
+    #[test_case(
+        "e1020f3\0HEAD -> master, origin/master\0Show a placeholder for binary files",
+        &LogEntry {
+            hash: "e1020f3".to_string(),
+            decorations: Some("HEAD -> master, origin/master".to_string()),
+            subject: "Show a placeholder for binary files".to_string(),
+            graph: String::new(),
+            connectors: Vec::new(),
+        } ;
+        "decorated"
+    )]
+    #[test_case(
+        "c5597d9\0\0(wip) Add an inline commit message editor",
+        &LogEntry {
+            hash: "c5597d9".to_string(),
+            decorations: None,
+            subject: "(wip) Add an inline commit message editor".to_string(),
+            graph: String::new(),
+            connectors: Vec::new(),
+        } ;
+        "undecorated with parenthesis"
+    )]
+    #[test_case(
+        "2bc8724\0tag: v0.6.4\0",
+        &LogEntry {
+            hash: "2bc8724".to_string(),
+            decorations: Some("tag: v0.6.4".to_string()),
+            subject: String::new(),
+            graph: String::new(),
+            connectors: Vec::new(),
+        } ;
+        "empty subject"
+    )]
+    #[test_case(
+        "| * 9a1c2e4\0\0Merge branch 'log-graph'",
+        &LogEntry {
+            hash: "9a1c2e4".to_string(),
+            decorations: None,
+            subject: "Merge branch 'log-graph'".to_string(),
+            graph: "| * ".to_string(),
+            connectors: Vec::new(),
+        } ;
+        "with graph"
+    )]
+    fn parse_log_entry(input: &str, expected: &LogEntry) {
+        assert_eq!(&LogEntry::parse(input).unwrap(), expected);
+    }
+
+    #[test_case("v1.0.0", None ; "version")]
+    #[test_case("release/2024-01", None ; "nested")]
+    #[test_case("", Some("it's empty") ; "empty")]
+    #[test_case("@", Some("it can't be `@`") ; "at sign")]
+    #[test_case("-v", Some("it can't begin with `-`") ; "option")]
+    #[test_case("my tag", Some("it can't contain spaces") ; "space")]
+    #[test_case("v1^2", Some("it can't contain any of `~^:?*[\\`") ; "caret")]
+    #[test_case("v1..2", Some("it can't contain `..`") ; "double dot")]
+    #[test_case("v@{1}", Some("it can't contain `@{`") ; "reflog syntax")]
+    #[test_case("v1.", Some("it can't end with `.`") ; "trailing dot")]
+    #[test_case("/v1", Some("it can't begin or end with `/` or contain `//`") ; "leading slash")]
+    #[test_case("a//b", Some("it can't begin or end with `/` or contain `//`") ; "double slash")]
+    #[test_case("a/.b", Some("no part of it can begin with `.`") ; "hidden component")]
+    #[test_case("v1.lock", Some("no part of it can end with `.lock`") ; "lock")]
+    fn ref_name(name: &str, expected: Option<&str>) {
+        assert_eq!(invalid_ref_name(name), expected);
+    }
+}
@@ -9,20 +9,25 @@
 )]
 
 use std::{
-    cmp, env,
-    io::{stdin, stdout, BufRead, Write},
+    collections::VecDeque,
+    io::{stdin, stdout, BufRead, IsTerminal, Write},
     panic,
-    process::{self, Command, Output},
+    path::Path,
+    process::{self, Command, Output, Stdio},
     rc::Rc,
     sync::atomic::Ordering,
+    thread::JoinHandle,
+    time::{Duration, Instant},
 };
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use clap::Parser;
 use config::Clargs;
 use crossterm::{
     cursor,
-    event::{self, Event, KeyCode, KeyEventKind},
+    event::{
+        self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEventKind,
+    },
     style::{Attribute, SetForegroundColor},
     terminal::{self, ClearType},
 };
@@ -30,41 +35,104 @@ use git2::Repository;
 
 use crate::{
     command::GexCommand,
-    config::{Action, Config, CONFIG},
+    config::{Action, Config, GexAction, CONFIG},
     minibuffer::{Callback, MessageType, MiniBuffer},
     render::{Clear, Render, ResetAttributes},
 };
 
+mod blame;
 mod branch;
+mod clipboard;
 mod command;
+mod commit;
 mod config;
 mod debug;
+mod diff;
+mod error;
+mod help;
+mod highlight;
+mod hunk;
+mod log;
 mod minibuffer;
+mod pager;
 mod parse;
+mod remote;
 mod render;
+mod stash;
 mod status;
+mod statusbar;
+mod tag;
+mod watch;
+mod worktree;
 
+use blame::BlameView;
 use branch::BranchList;
+use commit::CommitEditor;
+use error::GexError;
+use help::Help;
+use hunk::HunkView;
+use log::LogView;
+use remote::RemoteList;
 use render::Renderer;
+use stash::StashList;
 use status::Status;
+use statusbar::StatusBar;
+use tag::TagList;
+use worktree::WorktreeList;
 
 pub struct State {
     view: View,
     minibuffer: MiniBuffer,
     status: Status,
+    status_bar: StatusBar,
     branch_list: BranchList,
-    repo: Repository,
+    commit_editor: CommitEditor,
+    log: LogView,
+    stash_list: StashList,
+    hunk_view: HunkView,
+    blame: BlameView,
+    remote_list: RemoteList,
+    worktree_list: WorktreeList,
+    tag_list: TagList,
+    help: Help,
     renderer: Renderer,
+    /// The `git fetch` running in the background, if any.
+    fetch: Option<JoinHandle<Result<Output>>>,
+    /// The `git pull --rebase` running in the background, if any.
+    pull: Option<JoinHandle<Result<Output>>>,
+    /// Printed to stdout once the TUI has been closed, e.g. the path of the worktree to go to.
+    print_on_exit: Option<String>,
+    /// The keys pressed while the status was loading, which are handled once it has loaded.
+    pending_keys: VecDeque<KeyEvent>,
 }
 
 #[derive(Clone)]
 pub enum View {
     Status,
     BranchList,
+    CommitEditor,
+    Log,
+    StashList,
+    /// The unstaged hunks of a single file.
+    Hunks,
+    /// The blame of a single file, opened from the view it returns to.
+    Blame(Box<Self>),
+    /// The remotes, opened from the view they return to.
+    RemoteList(Box<Self>),
+    WorktreeList,
+    TagList,
+    /// The key bindings, shown over the view they were opened from.
+    Help(Box<Self>),
     Command(GexCommand),
-    Input(Callback, Box<View>),
+    Input(Callback, Box<Self>),
 }
 
+const DOUBLE_CLICK_TIME: Duration = Duration::from_millis(500);
+/// How often to check whether a background fetch, pull or push has finished.
+const FETCH_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// How long to wait for the status to be fetched before showing the previous one while it loads.
+const REFRESH_WAIT: Duration = Duration::from_millis(100);
+
 pub fn git_process(args: &[&str]) -> Result<Output> {
     use std::fmt::Write;
     Command::new("git").args(args).output().with_context(|| {
@@ -78,11 +146,70 @@ pub fn git_process(args: &[&str]) -> Result<Output> {
     })
 }
 
+/// A `git` command with `args` that talks to a remote. There's no way to answer a prompt for
+/// credentials while the TUI is running, so git is told to fail instead of asking.
+#[must_use]
+pub fn remote_git(args: &[&str]) -> Command {
+    let mut command = Command::new("git");
+    command
+        .args(args)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null());
+    command
+}
+
+/// Suspend the TUI to run a command that needs the terminal, such as one that opens an editor.
+///
+/// Stderr is still captured so that it can be shown to the user afterwards, since anything printed
+/// to the main screen is lost once we return to the alternate screen.
+pub fn interactive_process(command: &mut Command) -> Result<Output> {
+    crossterm::execute!(
+        stdout(),
+        event::DisableMouseCapture,
+        terminal::LeaveAlternateScreen,
+        cursor::Show,
+        cursor::MoveToColumn(0)
+    )
+    .context("failed to leave alternate screen")?;
+    terminal::disable_raw_mode().context("failed to disable raw mode")?;
+
+    let output = command
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::piped())
+        .output()
+        .with_context(|| {
+            format!(
+                "failed to run `{}`",
+                command.get_program().to_string_lossy()
+            )
+        });
+
+    terminal::enable_raw_mode().context("failed to enable raw mode")?;
+    crossterm::execute!(stdout(), terminal::EnterAlternateScreen, cursor::Hide)
+        .context("failed to enter alternate screen")?;
+    enable_mouse_capture()?;
+    render::REDRAW_FLAG.store(true, Ordering::Release);
+    output
+}
+
 fn run(clargs: &Clargs) -> Result<()> {
+    // Offering to initialise a repository would create the directory, which is more likely to be a
+    // typo.
+    if !Path::new(&clargs.path).is_dir() {
+        return Err(anyhow!("{} is not a directory", clargs.path));
+    }
+
     // Attempt to find a git repository at or above current path
     let repo = if let Ok(repo) = Repository::discover(&clargs.path) {
         repo
     } else {
+        // There's nobody to answer the question, e.g. when input is piped in.
+        if !stdin().is_terminal() {
+            return Err(anyhow!(
+                "not a git repository (or any of the parent directories)"
+            ));
+        }
         print!("Not a git repository. Initialise one? [y/N]");
         drop(stdout().flush());
         let input = stdin()
@@ -99,13 +226,27 @@ fn run(clargs: &Clargs) -> Result<()> {
     };
 
     // Set working directory in case the repository is not the current directory
-    std::env::set_current_dir(repo.path().parent().context("`.git` cannot be root dir")?)
-        .context("failed to set working directory")?;
+    // This is also where to go when gex is started inside `.git`.
+    let workdir = repo.workdir().with_context(|| {
+        format!(
+            "{} is a bare repository, which has no working tree to show the status of",
+            repo.path().display()
+        )
+    })?;
+    // Where gex was started relative to the root, unless that was the root itself or `.git`.
+    let subdir = Path::new(&clargs.path)
+        .canonicalize()
+        .ok()
+        .zip(workdir.canonicalize().ok())
+        .and_then(|(start, root)| Some(start.strip_prefix(root).ok()?.display().to_string()))
+        .filter(|subdir| !subdir.is_empty() && !Path::new(subdir).starts_with(".git"));
+    let worktree = repo.is_worktree();
+    std::env::set_current_dir(workdir).context("failed to set working directory")?;
 
     let minibuffer = MiniBuffer::new();
 
     let config = CONFIG.get_or_init(|| {
-        Config::read_from_file(&clargs.config_file)
+        Config::read_from_file(clargs.config_file.as_deref())
             .unwrap_or_else(|e| {
                 MiniBuffer::push(&format!("{e:?}"), MessageType::Error);
                 Some((Config::default(), Vec::new()))
@@ -123,7 +264,28 @@ fn run(clargs: &Clargs) -> Result<()> {
             })
     });
 
-    let status = Status::new(&repo, &config.options)?;
+    // The colours are all reset when NO_COLOR is set, unless they're set in the config file.
+    let no_color = std::env::var("NO_COLOR").is_ok_and(|v| !v.is_empty())
+        && config.colors == config::Colors::default();
+    let piped = clargs.print && !stdout().is_terminal();
+    render::PLAIN.store(clargs.no_color || no_color || piped, Ordering::Relaxed);
+
+    let mut status = Status::new(&config.options, subdir, worktree)?;
+    if clargs.print {
+        for (msg, _) in minibuffer::MESSAGES
+            .try_lock()
+            .expect("couldn't get mutex lock")
+            .drain(..)
+        {
+            eprintln!("{msg}");
+        }
+        status.deselect();
+        let mut renderer = Renderer::default();
+        status.render(&mut renderer)?;
+        renderer.print_and_clear();
+        return Ok(());
+    }
+
     let branch_list = BranchList::new()?;
     let view = View::Status;
     let renderer = Renderer::default();
@@ -132,23 +294,28 @@ fn run(clargs: &Clargs) -> Result<()> {
         view,
         minibuffer,
         status,
+        status_bar: StatusBar::default(),
         branch_list,
-        repo,
+        commit_editor: CommitEditor::default(),
+        log: LogView::default(),
+        stash_list: StashList::default(),
+        hunk_view: HunkView::default(),
+        blame: BlameView::default(),
+        remote_list: RemoteList::default(),
+        worktree_list: WorktreeList::default(),
+        tag_list: TagList::default(),
+        help: Help::default(),
         renderer,
+        fetch: None,
+        pull: None,
+        print_on_exit: None,
+        pending_keys: VecDeque::new(),
     };
 
-    // Non-English locale settings are currently unsupported. See
-    // https://github.com/Piturnah/gex/issues/13.
-    if !env::var("LANG")
-        .map(|s| s.starts_with("en"))
-        .unwrap_or(true)
-    {
-        MiniBuffer::push("WARNING: Non-English locale detected. For now, Gex only supports English locale setting.
-Set locale to English, e.g.:
-
-        $ LANG=en_GB gex
-
-See https://github.com/Piturnah/gex/issues/13.", MessageType::Error);
+    if config.options.auto_refresh {
+        if let Err(e) = watch::start() {
+            MiniBuffer::push(&format!("{e:?}"), MessageType::Error);
+        }
     }
 
     // We are about to start messing with the terminal settings. So let's update the panic hook so
@@ -161,59 +328,158 @@ See https://github.com/Piturnah/gex/issues/13.", MessageType::Error);
 
     crossterm::execute!(stdout(), terminal::EnterAlternateScreen)
         .context("failed to enter alternate screen")?;
+    enable_mouse_capture()?;
     terminal::enable_raw_mode().context("failed to put terminal in raw mode")?;
     print!("{}", cursor::Hide);
 
     // Structure of the event loop
     //
-    // 1. Clear the terminal
-    // 2. Render status or branch list
+    // 1. Render status or branch list, drawing only what changed since the last frame
+    // 2. Mark the rows that the overlays below were drawn on, so they're drawn again next time
     // 3. Render option overlay
     // 4. Render minibuffer messages
     // 5. Wait for event and update state
     //
+    // The time and target of the last click, for detecting double clicks.
+    let mut last_click: Option<(Instant, (usize, usize))> = None;
+
     loop {
         let (term_width, term_height) =
             terminal::size().context("failed to query terminal dimensions")?;
 
+        if let Err(e) = command::poll_push() {
+            MiniBuffer::push(&format!("{e:?}"), MessageType::Error);
+        }
+        state.status.pushing = command::push_started();
+
         print!("{ResetAttributes}");
-        match state.view {
-            View::Status | View::Command(_) | View::Input(..) => {
-                // If the flag is set then we need to fetch the status again before rendering.
-                if status::REFRESH_FLAG.swap(false, Ordering::Acquire) {
-                    state.status.fetch(&state.repo, &config.options)?;
+        let view = match &state.view {
+            View::Help(previous) | View::Input(_, previous) => previous,
+            view => view,
+        };
+        match view {
+            View::Status | View::Command(_) => {
+                // If the flag is set then the status is fetched again in the background. If that
+                // fails then the previous status is shown along with the error.
+                if !state.status.loading && status::REFRESH_FLAG.swap(false, Ordering::Acquire) {
+                    state.status.refresh_in_background();
+                }
+                // Waiting a little while avoids showing the stale status when git is quick.
+                if state.status.loading {
+                    if let Err(e) = state.status.finish_refresh(REFRESH_WAIT, &config.options) {
+                        MiniBuffer::push(&format!("{e:?}"), MessageType::Error);
+                    }
                 }
                 state.status.render(&mut state.renderer)?;
             }
-            View::BranchList => state.branch_list.render(&mut state.renderer)?,
+            View::BranchList => {
+                // A branch may have been deleted.
+                if status::REFRESH_FLAG.swap(false, Ordering::Acquire) {
+                    if let Err(e) = state.branch_list.fetch() {
+                        MiniBuffer::push(&format!("{e:?}"), MessageType::Error);
+                    }
+                }
+                state.branch_list.render(&mut state.renderer)?;
+            }
+            View::CommitEditor => state.commit_editor.render(&mut state.renderer)?,
+            View::Log => {
+                // A tag may have been created.
+                if status::REFRESH_FLAG.swap(false, Ordering::Acquire) {
+                    if let Err(e) = state.log.fetch() {
+                        MiniBuffer::push(&format!("{e:?}"), MessageType::Error);
+                    }
+                }
+                state.log.render(&mut state.renderer)?;
+            }
+            View::StashList => {
+                // Something may have been stashed or dropped.
+                if status::REFRESH_FLAG.swap(false, Ordering::Acquire) {
+                    if let Err(e) = state.stash_list.fetch() {
+                        MiniBuffer::push(&format!("{e:?}"), MessageType::Error);
+                    }
+                }
+                state.stash_list.render(&mut state.renderer)?;
+            }
+            View::Blame(_) => state.blame.render(&mut state.renderer)?,
+            View::RemoteList(_) => {
+                // A remote may have been added or removed.
+                if status::REFRESH_FLAG.swap(false, Ordering::Acquire) {
+                    if let Err(e) = state.remote_list.fetch() {
+                        MiniBuffer::push(&format!("{e:?}"), MessageType::Error);
+                    }
+                }
+                state.remote_list.render(&mut state.renderer)?;
+            }
+            View::WorktreeList => {
+                // A worktree may have been added or removed.
+                if status::REFRESH_FLAG.swap(false, Ordering::Acquire) {
+                    if let Err(e) = state.worktree_list.fetch() {
+                        MiniBuffer::push(&format!("{e:?}"), MessageType::Error);
+                    }
+                }
+                state.worktree_list.render(&mut state.renderer)?;
+            }
+            View::TagList => {
+                // A tag may have been created or deleted.
+                if status::REFRESH_FLAG.swap(false, Ordering::Acquire) {
+                    if let Err(e) = state.tag_list.fetch() {
+                        MiniBuffer::push(&format!("{e:?}"), MessageType::Error);
+                    }
+                }
+                state.tag_list.render(&mut state.renderer)?;
+            }
+            View::Hunks => {
+                // A hunk may have been discarded.
+                if status::REFRESH_FLAG.swap(false, Ordering::Acquire) {
+                    if let Err(e) = state.hunk_view.fetch() {
+                        MiniBuffer::push(&format!("{e:?}"), MessageType::Error);
+                    }
+                }
+                state.hunk_view.render(&mut state.renderer)?;
+            }
+            View::Help(_) | View::Input(..) => {
+                unreachable!("help and input aren't opened from themselves")
+            }
         }
+        // The status bar takes up the last row.
+        let status_bar = matches!(state.view, View::Status);
         state.renderer.show_and_clear(
             term_width as usize,
-            term_height as usize,
+            usize::from(term_height) - usize::from(status_bar),
             config.options.lookahead_lines,
             config.options.truncate_lines,
         );
+        if let View::Help(_) = state.view {
+            let rows = state.help.draw(term_width, term_height);
+            state.renderer.invalidate_rows(rows);
+        }
         drop(stdout().flush());
 
         // Display the available subcommands
         if let View::Command(cmd) = state.view {
             use std::fmt::Write;
             let subcmds = cmd.subcommands();
+            let menu = subcmds.iter().fold(String::new(), |mut acc, (k, v)| {
+                let _ = write!(
+                    acc,
+                    "\r\n {}{}{k}{} => {v}",
+                    SetForegroundColor(config.colors.key),
+                    Attribute::Bold,
+                    ResetAttributes
+                );
+                acc
+            });
+            let top = term_height - 1 - subcmds.len() as u16;
+            state.renderer.invalidate_rows(top..term_height);
             print!(
-                "{}{title:═^term_width$}{}{}{}",
-                cursor::MoveTo(0, term_height - 1 - subcmds.len() as u16),
+                "{}{title:═^term_width$}{}{}",
+                cursor::MoveTo(0, top),
                 Clear(ClearType::FromCursorDown),
-                subcmds.iter().fold(String::new(), |mut acc, (k, v)| {
-                    let _ = write!(
-                        acc,
-                        "\r\n {}{}{k}{} => {v}",
-                        SetForegroundColor(config.colors.key),
-                        Attribute::Bold,
-                        ResetAttributes
-                    );
-                    acc
-                }),
-                SetForegroundColor(config.colors.foreground),
+                if render::plain() {
+                    render::strip_styles(&menu)
+                } else {
+                    format!("{menu}{}", SetForegroundColor(config.colors.foreground))
+                },
                 term_width = term_width as usize,
                 title = format!(" {cmd:?} Options "),
             );
@@ -222,179 +488,598 @@ See https://github.com/Piturnah/gex/issues/13.", MessageType::Error);
         }
 
         // Draw the current `debug!` window.
-        debug_draw!();
+        debug_draw!(state.renderer);
 
+        if status_bar {
+            state.status_bar.take_message();
+            state.status_bar.draw(
+                state.status.section(),
+                state.status.search_prompt().as_deref(),
+                state.status.ignore_rule(),
+                term_width,
+                term_height,
+            );
+        }
         state.minibuffer.pop_message();
-        state.minibuffer.render(term_width, term_height)?;
+        let rows = state.minibuffer.render(term_width, term_height)?;
+        state.renderer.invalidate_rows(rows);
 
         // Handle input
         //
-        // Check what event we get. If we got an event other than a key event, we don't need to
-        // handle it so we break. If we got a key event with KeyEventKind::Release, we try again in
-        // the loop to avoid re-rendering. If it's a key event without KeyEventKind::Release,
-        // handle it and break.
+        // Check what event we get. If the terminal was resized, we just need to re-render so we
+        // break. If we got another event that isn't a key press, or a key event with
+        // KeyEventKind::Release, we try again in the loop to avoid re-rendering. If it's a key
+        // event without KeyEventKind::Release, handle it and break.
+        //
+        // Resizing only needs the screen to be drawn again from scratch, since the terminal size is
+        // queried and the scroll position recalculated every frame. The size is queried rather
+        // than taken from the event because on some platforms the event can arrive before the new
+        // size is reported, and several can arrive in quick succession while the window is being
+        // dragged.
         loop {
-            let Event::Key(event) = event::read().context("failed to read a terminal event")?
-            else {
+            // Wake up regularly while fetching or pulling in the background to check whether it's
+            // done.
+            if let Some(fetch) = state.fetch.take_if(|fetch| fetch.is_finished()) {
+                let output = fetch
+                    .join()
+                    .unwrap_or_else(|_| Err(GexError::ThreadPanicked("fetch").into()));
+                match output {
+                    Ok(output) => command::report_remote_output(&output),
+                    Err(e) => MiniBuffer::push(&format!("{e:?}"), MessageType::Error),
+                }
+                state.status.fetching = None;
+                status::REFRESH_FLAG.store(true, Ordering::Release);
                 break;
-            };
-            if event.kind == KeyEventKind::Release {
-                continue;
             }
-
-            if !MiniBuffer::is_empty() {
+            if let Some(pull) = state.pull.take_if(|pull| pull.is_finished()) {
+                let reported = pull
+                    .join()
+                    .unwrap_or_else(|_| Err(GexError::ThreadPanicked("pull").into()))
+                    .and_then(|output| command::report_pull(&output, &mut state.view));
+                if let Err(e) = reported {
+                    MiniBuffer::push(&format!("{e:?}"), MessageType::Error);
+                }
+                state.status.pulling = None;
+                status::REFRESH_FLAG.store(true, Ordering::Release);
                 break;
             }
+            if state.status.loading {
+                match state.status.finish_refresh(Duration::ZERO, &config.options) {
+                    Ok(true) => break,
+                    Ok(false) => {}
+                    Err(e) => {
+                        MiniBuffer::push(&format!("{e:?}"), MessageType::Error);
+                        break;
+                    }
+                }
+            }
+            // Keys that were pressed while the status was loading are handled once it's there.
+            let queued = if state.status.loading {
+                None
+            } else {
+                state.pending_keys.pop_front()
+            };
+            let event = if let Some(event) = queued {
+                event
+            } else {
+                // The spinner needs to be redrawn while fetching, pulling, pushing or loading, and
+                // the status when the repository has been changed by something else.
+                let spinning = state.fetch.is_some()
+                    || state.pull.is_some()
+                    || state.status.pushing.is_some()
+                    || state.status.loading;
+                // A message in the status bar is cleared after a while.
+                let message_time_left = state.status_bar.message_time_left();
+                let timeout = message_time_left
+                    .map_or(FETCH_POLL_INTERVAL, |left| left.min(FETCH_POLL_INTERVAL));
+                if (spinning || config.options.auto_refresh || message_time_left.is_some())
+                    && !event::poll(timeout).context("failed to poll terminal events")?
+                {
+                    if watch::take_change() {
+                        status::REFRESH_FLAG.store(true, Ordering::Release);
+                        break;
+                    }
+                    if spinning || state.status_bar.clear_expired_message() {
+                        break;
+                    }
+                    continue;
+                }
 
-            match state.view {
-                View::Status => {
-                    match config.keymap.navigation.get(&event.code) {
-                        Some(Action::MoveDown) => state.status.down()?,
-                        Some(Action::MoveUp) => state.status.up()?,
-                        Some(Action::NextFile) => state.status.file_down()?,
-                        Some(Action::PreviousFile) => state.status.file_up()?,
-                        Some(Action::ToggleExpand) => state.status.expand()?,
-                        Some(Action::GotoBottom) => state.status.cursor_last()?,
-                        Some(Action::GotoTop) => state.status.cursor_first()?,
-                        _ => {}
-                    };
-
-                    match event.code {
-                        KeyCode::Char('s') => {
-                            if state.status.cursor
-                                < state.status.count_untracked + state.status.count_unstaged
-                            {
-                                state.status.stage()?;
-                                status::REFRESH_FLAG.store(true, Ordering::Release);
-                            }
-                        }
-                        KeyCode::Char('S') => {
-                            MiniBuffer::push_command_output(&git_process(&["add", "."])?);
-                            status::REFRESH_FLAG.store(true, Ordering::Release);
+                let event = match event::read().context("failed to read a terminal event")? {
+                    Event::Key(event) => event,
+                    Event::Resize(..) => {
+                        render::REDRAW_FLAG.store(true, Ordering::Release);
+                        break;
+                    }
+                    Event::Mouse(event) => {
+                        // The items may move once the status has loaded.
+                        if !MiniBuffer::is_empty()
+                            || !matches!(state.view, View::Status)
+                            || state.status.loading
+                        {
+                            continue;
                         }
-                        KeyCode::Char('u') => {
-                            if state.status.cursor
-                                >= state.status.count_untracked + state.status.count_unstaged
-                            {
-                                state.status.unstage()?;
-                                status::REFRESH_FLAG.store(true, Ordering::Release);
+                        match event.kind {
+                            MouseEventKind::Down(MouseButton::Left) => {
+                                let Some((item, hunk)) = state.renderer.item_at(event.row) else {
+                                    continue;
+                                };
+                                state.minibuffer.dismiss_message();
+                                state.status.select(item, hunk);
+                                // Clicking the same thing again quickly toggles it, like Tab.
+                                let now = Instant::now();
+                                if last_click.is_some_and(|(time, clicked)| {
+                                    clicked == (item, hunk) && now - time < DOUBLE_CLICK_TIME
+                                }) {
+                                    if let Err(e) = state.status.expand() {
+                                        MiniBuffer::push(&format!("{e:?}"), MessageType::Error);
+                                    }
+                                    last_click = None;
+                                } else {
+                                    last_click = Some((now, (item, hunk)));
+                                }
                             }
+                            MouseEventKind::ScrollDown => state.status.down(),
+                            MouseEventKind::ScrollUp => state.status.up(),
+                            _ => continue,
                         }
-                        KeyCode::Char('U') => {
-                            MiniBuffer::push_command_output(&git_process(&["reset"])?);
-                            status::REFRESH_FLAG.store(true, Ordering::Release);
-                        }
-                        KeyCode::Char('e') => {
-                            state.status.open_editor()?;
-                            status::REFRESH_FLAG.store(true, Ordering::Release);
-                        }
-                        KeyCode::Char('F') => {
-                            MiniBuffer::push_command_output(&git_process(&["pull"])?);
-                            status::REFRESH_FLAG.store(true, Ordering::Release);
-                        }
-                        KeyCode::Char('r') => status::REFRESH_FLAG.store(true, Ordering::Release),
-                        KeyCode::Char(':') => {
-                            state.minibuffer.command(true, &mut state.view);
-                            status::REFRESH_FLAG.store(true, Ordering::Release);
-                        }
-                        KeyCode::Char('!') => {
-                            state.minibuffer.command(false, &mut state.view);
-                            status::REFRESH_FLAG.store(true, Ordering::Release);
-                        }
-                        KeyCode::Char('q') => {
-                            terminal::disable_raw_mode().context("failed to disable raw mode")?;
-                            crossterm::execute!(
-                                stdout(),
-                                terminal::LeaveAlternateScreen,
-                                cursor::Show,
-                                cursor::MoveToColumn(0)
-                            )
-                            .context("failed to leave alternate screen")?;
-                            process::exit(0);
-                        }
-                        KeyCode::Char(c1) => {
-                            if let Some((_, cmd)) =
-                                GexCommand::commands().iter().find(|(c2, _)| c1 == *c2)
-                            {
-                                state.view = View::Command(*cmd);
-                            }
+                        break;
+                    }
+                    _ => continue,
+                };
+                if event.kind == KeyEventKind::Release {
+                    continue;
+                }
+                // The message that was being displayed has been seen now.
+                state.minibuffer.dismiss_message();
+
+                if !MiniBuffer::is_empty() {
+                    break;
+                }
+
+                // The keys are for the status that's loading, except for quitting which shouldn't
+                // have to wait.
+                let quit = matches!(state.view, View::Status)
+                    && config.keymap.actions.get(&event.code) == Some(&GexAction::Quit);
+                if state.status.loading && !quit {
+                    state.pending_keys.push_back(event);
+                    continue;
+                }
+                event
+            };
+
+            match handle_key(&mut state, event) {
+                Ok(true) => {
+                    if let Some(output) = state.print_on_exit {
+                        restore_terminal();
+                        // When stdout is piped, e.g. through `tee /dev/tty` so that the TUI is
+                        // still shown, the output needs a line of its own to be picked out.
+                        if !stdout().is_terminal() {
+                            println!();
                         }
-                        _ => {}
+                        println!("{output}");
+                    }
+                    return Ok(());
+                }
+                Ok(false) => {}
+                Err(e) => MiniBuffer::push(&format!("{e:?}"), MessageType::Error),
+            }
+            break;
+        }
+    }
+}
+
+/// Handle a key press in the current view. Returns `true` when the user wants to quit.
+fn handle_key(state: &mut State, event: KeyEvent) -> Result<bool> {
+    let config = CONFIG.get().expect("config wasn't initialised");
+    match state.view {
+        View::Status if state.status.is_searching() => state.status.search_input(event),
+        View::Status if event.code == KeyCode::Esc && command::cancel_push()? => {}
+        View::Status => {
+            if event.code == KeyCode::Esc {
+                state.status.clear_marks();
+                state.status.clear_search();
+            }
+            // A page is as many positions as there are rows, which is a screenful when nothing is
+            // expanded.
+            let (_, term_height) =
+                terminal::size().context("failed to query terminal dimensions")?;
+            let page = usize::from(term_height);
+            // Ctrl-d and Ctrl-u move half a page, like in vim. Any other key with Ctrl or Alt
+            // isn't bound, since the keymap would take it for the plain key.
+            if event
+                .modifiers
+                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+            {
+                if event.modifiers == KeyModifiers::CONTROL
+                    && matches!(event.code, KeyCode::Char('d' | 'u'))
+                {
+                    state
+                        .status
+                        .page(event.code == KeyCode::Char('d'), page / 2);
+                }
+                return Ok(false);
+            }
+            match config.keymap.navigation.get(&event.code) {
+                Some(Action::MoveDown) => state.status.down(),
+                Some(Action::MoveUp) => state.status.up(),
+                Some(Action::NextFile) => state.status.file_down(),
+                Some(Action::PreviousFile) => state.status.file_up(),
+                Some(Action::ToggleExpand) => state.status.expand()?,
+                Some(Action::GotoBottom) => state.status.cursor_last(),
+                Some(Action::GotoTop) => state.status.cursor_first(),
+                Some(Action::PageDown) => state.status.page(true, page),
+                Some(Action::PageUp) => state.status.page(false, page),
+                _ => {}
+            }
+
+            match config.keymap.actions.get(&event.code) {
+                Some(GexAction::Stage) => {
+                    if state.status.stage()? {
+                        status::REFRESH_FLAG.store(true, Ordering::Release);
+                    }
+                }
+                Some(GexAction::StageAll) => {
+                    MiniBuffer::push_command_output(&git_process(&["add", "."])?);
+                    status::REFRESH_FLAG.store(true, Ordering::Release);
+                }
+                Some(GexAction::ForceAdd) => {
+                    state
+                        .status
+                        .force_add(&mut state.minibuffer, &mut state.view);
+                }
+                Some(GexAction::Unstage) => {
+                    if state.status.unstage()? {
+                        status::REFRESH_FLAG.store(true, Ordering::Release);
+                    }
+                }
+                Some(GexAction::UnstageAll) => {
+                    // `git restore` needs HEAD to restore the index from, but without any
+                    // commits everything in the index is new so it can just be removed.
+                    let args: &[_] = if command::has_commits()? {
+                        &["restore", "--staged", "."]
+                    } else {
+                        &["rm", "--cached", "-r", "-q", "."]
                     };
+                    MiniBuffer::push_command_output(&git_process(args)?);
+                    status::REFRESH_FLAG.store(true, Ordering::Release);
                 }
-                View::BranchList => {
-                    match config.keymap.navigation.get(&event.code) {
-                        Some(Action::MoveDown) => {
-                            state.branch_list.cursor = cmp::min(
-                                state.branch_list.cursor + 1,
-                                state.branch_list.branches.len() - 1,
-                            );
-                        }
-                        Some(Action::MoveUp) => {
-                            state.branch_list.cursor = state.branch_list.cursor.saturating_sub(1);
-                        }
-                        Some(Action::GotoBottom) => {
-                            state.branch_list.cursor = state.branch_list.branches.len() - 1;
+                Some(GexAction::Mark) => state.status.toggle_mark(),
+                Some(GexAction::MarkRange) => state.status.toggle_mark_range(),
+                Some(GexAction::WordDiff) => {
+                    diff::WORD_DIFF.fetch_xor(true, Ordering::AcqRel);
+                    // The hunks are highlighted when they're fetched.
+                    status::REFRESH_FLAG.store(true, Ordering::Release);
+                }
+                Some(GexAction::Discard) => {
+                    state.status.discard(&mut state.minibuffer, &mut state.view);
+                }
+                Some(GexAction::Edit) => {
+                    state.status.open_editor()?;
+                    status::REFRESH_FLAG.store(true, Ordering::Release);
+                }
+                Some(GexAction::CopyPath) => {
+                    // The key is shared with the commands for the cherry-pick in progress, if any.
+                    let cherry_pick = GexCommand::commands().iter().any(|&(key, cmd)| {
+                        event.code == KeyCode::Char(key) && matches!(cmd, GexCommand::CherryPick)
+                    });
+                    if cherry_pick && status::cherry_pick_in_progress()? {
+                        state.view = View::Command(GexCommand::CherryPick);
+                    } else if let Some(path) = state.status.selected_path() {
+                        if let Err(e) = clipboard::copy(path) {
+                            MiniBuffer::push(&e.to_string(), MessageType::Error);
+                        } else {
+                            MiniBuffer::push(&format!("Copied {path}"), MessageType::Note);
                         }
-                        Some(Action::GotoTop) => state.branch_list.cursor = 0,
-                        _ => {}
                     }
-                    match event.code {
-                        KeyCode::Char(' ') | KeyCode::Enter => {
-                            MiniBuffer::push_command_output(&state.branch_list.checkout()?);
-                            status::REFRESH_FLAG.store(true, Ordering::Release);
-                            state.view = View::Status;
-                        }
-                        KeyCode::Esc => state.view = View::Status,
-                        KeyCode::Char('q') => {
-                            terminal::disable_raw_mode().context("failed to disable raw mode")?;
-                            crossterm::execute!(
-                                stdout(),
-                                terminal::LeaveAlternateScreen,
-                                cursor::Show,
-                                cursor::MoveToColumn(0)
-                            )
-                            .context("failed to leave alternate screen")?;
-                            process::exit(0);
+                }
+                Some(GexAction::Blame) => {
+                    if let Some(path) = state.status.selected_path() {
+                        if state.blame.open(path)? {
+                            state.view = View::Blame(Box::new(View::Status));
                         }
-                        _ => {}
                     }
                 }
-                View::Command(cmd) => match event.code {
-                    KeyCode::Esc => state.view = View::Status,
-                    KeyCode::Char('q') => {
-                        terminal::disable_raw_mode().context("failed to exit raw mode")?;
-                        crossterm::execute!(
-                            stdout(),
-                            terminal::LeaveAlternateScreen,
-                            cursor::Show,
-                            cursor::MoveToColumn(0)
-                        )
-                        .context("failed to leave alternate screen")?;
-                        process::exit(0);
+                Some(GexAction::Amend) => {
+                    if command::amend(false)? {
+                        status::REFRESH_FLAG.store(true, Ordering::Release);
                     }
-                    KeyCode::Char(c) => cmd.handle_input(c, &mut state)?,
-                    _ => {}
-                },
-                View::Input(ref callback, ref return_view) => {
-                    // This clone should be very cheap as we should never be constructing a
-                    // View::Input with the return view as View::Input.
-                    //
-                    // NOTE: This all indicates there is probably a better way to represent the
-                    // View type, as it never actually needs to be recursive -- then we would also
-                    // be able to just #[derive(Copy)].
-                    debug_assert!(!matches!(**return_view, View::Input(..)));
-                    state.minibuffer.handle_input(
-                        event,
-                        &Rc::clone(callback),
-                        (**return_view).clone(),
-                        &mut state.view,
-                    )?;
                 }
-            };
-            break;
+                Some(GexAction::Log) => {
+                    state.log.fetch()?;
+                    state.view = View::Log;
+                }
+                Some(GexAction::StashList) => {
+                    state.stash_list.fetch()?;
+                    state.view = View::StashList;
+                }
+                Some(GexAction::RemoteList) => {
+                    state.remote_list.fetch()?;
+                    state.view = View::RemoteList(Box::new(View::Status));
+                }
+                Some(GexAction::WorktreeList) => {
+                    state.worktree_list.fetch()?;
+                    state.view = View::WorktreeList;
+                }
+                Some(GexAction::TagList) => {
+                    state.tag_list.fetch()?;
+                    state.view = View::TagList;
+                }
+                Some(GexAction::Search) => state.status.start_search(),
+                Some(GexAction::SearchNext) => state.status.search_next(true),
+                Some(GexAction::SearchPrevious) => state.status.search_next(false),
+                Some(GexAction::Stash) => {
+                    MiniBuffer::push_command_output(&git_process(&["stash", "push"])?);
+                    status::REFRESH_FLAG.store(true, Ordering::Release);
+                }
+                Some(GexAction::Push) => {
+                    command::push(false, &mut state.minibuffer, &mut state.view)?;
+                }
+                Some(GexAction::Fetch) => {
+                    if state.fetch.is_none() {
+                        state.fetch = Some(command::fetch_in_background());
+                        state.status.fetching = Some(Instant::now());
+                    }
+                }
+                Some(GexAction::Pull) => command::pull()?,
+                Some(GexAction::PullRebase) => {
+                    if state.pull.is_none() {
+                        state.pull = Some(command::pull_rebase_in_background());
+                        state.status.pulling = Some(Instant::now());
+                    }
+                }
+                Some(GexAction::Refresh) => {
+                    status::REFRESH_FLAG.store(true, Ordering::Release);
+                }
+                Some(GexAction::GitCommand) => {
+                    state.minibuffer.command(true, &mut state.view);
+                    status::REFRESH_FLAG.store(true, Ordering::Release);
+                }
+                Some(GexAction::ShellCommand) => {
+                    state.minibuffer.command(false, &mut state.view);
+                    status::REFRESH_FLAG.store(true, Ordering::Release);
+                }
+                Some(GexAction::Help) => {
+                    state.view = View::Help(Box::new(View::Status));
+                }
+                Some(GexAction::Quit) => {
+                    return Ok(true);
+                }
+                Some(GexAction::Open) => {
+                    if let Some(path) = state.status.unstaged_path() {
+                        state.hunk_view.open(path)?;
+                        state.view = View::Hunks;
+                    } else if let Some(path) = state.status.submodule_path() {
+                        // A submodule is a repository of its own, so it gets a gex of its own.
+                        let gex = std::env::current_exe().context("failed to find gex")?;
+                        MiniBuffer::push_command_output(&interactive_process(
+                            Command::new(gex).arg(path),
+                        )?);
+                        status::REFRESH_FLAG.store(true, Ordering::Release);
+                    }
+                }
+                None => {
+                    if let KeyCode::Char(c1) = event.code {
+                        if let Some((_, cmd)) =
+                            GexCommand::commands().iter().find(|(c2, _)| c1 == *c2)
+                        {
+                            // These only go on with one that's in progress, so there's nothing to
+                            // show otherwise.
+                            let idle = match cmd {
+                                GexCommand::CherryPick => {
+                                    (!status::cherry_pick_in_progress()?).then_some("cherry-pick")
+                                }
+                                GexCommand::Merge => {
+                                    (!status::merge_in_progress()?).then_some("merge")
+                                }
+                                _ => None,
+                            };
+                            if let Some(operation) = idle {
+                                MiniBuffer::push(
+                                    &format!("No {operation} in progress."),
+                                    MessageType::Note,
+                                );
+                            } else {
+                                state.view = View::Command(*cmd);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        // The keymap doesn't take modifiers into account, so e.g. Ctrl-d would delete in the branch
+        // list. Keys with Ctrl or Alt are only for typing into the minibuffer or the commit editor.
+        _ if event
+            .modifiers
+            .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+            && !matches!(state.view, View::CommitEditor | View::Input(..)) => {}
+        View::BranchList
+            if !state.branch_list.is_searching()
+                && config.keymap.actions.get(&event.code) == Some(&GexAction::Quit) =>
+        {
+            return Ok(true);
+        }
+        View::BranchList
+            if !state.branch_list.is_searching()
+                && config.keymap.actions.get(&event.code) == Some(&GexAction::RemoteList) =>
+        {
+            state.remote_list.fetch()?;
+            state.view = View::RemoteList(Box::new(View::BranchList));
+        }
+        View::BranchList
+            if !state.branch_list.is_searching()
+                && config.keymap.actions.get(&event.code) == Some(&GexAction::Help) =>
+        {
+            state.view = View::Help(Box::new(View::BranchList));
+        }
+        View::BranchList => {
+            if state.branch_list.handle_input(
+                event,
+                config.keymap.navigation.get(&event.code),
+                &mut state.minibuffer,
+                &mut state.view,
+            )? {
+                status::REFRESH_FLAG.store(true, Ordering::Release);
+                state.view = View::Status;
+            }
+        }
+        View::CommitEditor => {
+            if state.commit_editor.handle_input(event)? {
+                status::REFRESH_FLAG.store(true, Ordering::Release);
+                state.view = View::Status;
+            }
+        }
+        View::Log if config.keymap.actions.get(&event.code) == Some(&GexAction::Help) => {
+            state.view = View::Help(Box::new(View::Log));
+        }
+        View::Log => {
+            if state.log.handle_input(
+                event,
+                config.keymap.navigation.get(&event.code),
+                &mut state.minibuffer,
+                &mut state.view,
+            )? {
+                status::REFRESH_FLAG.store(true, Ordering::Release);
+                state.view = View::Status;
+            }
+        }
+        View::StashList if config.keymap.actions.get(&event.code) == Some(&GexAction::Help) => {
+            state.view = View::Help(Box::new(View::StashList));
+        }
+        View::StashList => {
+            if state.stash_list.handle_input(
+                event,
+                config.keymap.navigation.get(&event.code),
+                &mut state.minibuffer,
+                &mut state.view,
+            )? {
+                status::REFRESH_FLAG.store(true, Ordering::Release);
+                state.view = View::Status;
+            }
+        }
+        View::Hunks if config.keymap.actions.get(&event.code) == Some(&GexAction::Blame) => {
+            if state.blame.open(state.hunk_view.path())? {
+                state.view = View::Blame(Box::new(View::Hunks));
+            }
+        }
+        View::Hunks => {
+            if state.hunk_view.handle_input(
+                event,
+                config.keymap.navigation.get(&event.code),
+                &mut state.minibuffer,
+                &mut state.view,
+            )? {
+                status::REFRESH_FLAG.store(true, Ordering::Release);
+                state.view = View::Status;
+            }
+        }
+        View::RemoteList(ref previous) => {
+            let previous = *previous.clone();
+            if state.remote_list.handle_input(
+                event,
+                config.keymap.navigation.get(&event.code),
+                &mut state.minibuffer,
+                &mut state.view,
+            )? {
+                status::REFRESH_FLAG.store(true, Ordering::Release);
+                state.view = previous;
+            }
+        }
+        View::WorktreeList if event.code == KeyCode::Enter => {
+            // The path is printed for the shell to `cd` to, since gex can't change its directory.
+            if let Some(path) = state.worktree_list.selected_path() {
+                state.print_on_exit = Some(path.to_string());
+                return Ok(true);
+            }
+        }
+        View::WorktreeList => {
+            if state.worktree_list.handle_input(
+                event,
+                config.keymap.navigation.get(&event.code),
+                &mut state.minibuffer,
+                &mut state.view,
+            ) {
+                status::REFRESH_FLAG.store(true, Ordering::Release);
+                state.view = View::Status;
+            }
+        }
+        View::TagList => {
+            if state.tag_list.handle_input(
+                event,
+                config.keymap.navigation.get(&event.code),
+                &mut state.minibuffer,
+                &mut state.view,
+            )? {
+                status::REFRESH_FLAG.store(true, Ordering::Release);
+                state.view = View::Status;
+            }
+        }
+        View::Blame(_) if event.code == KeyCode::Enter => {
+            if let Some(hash) = state.blame.selected_commit() {
+                state.log.fetch()?;
+                if state.log.select(hash) {
+                    state.view = View::Log;
+                } else {
+                    MiniBuffer::push(
+                        &format!("{hash:.8} isn't in the history of the current branch."),
+                        MessageType::Error,
+                    );
+                }
+            }
+        }
+        View::Blame(ref previous) => {
+            if state
+                .blame
+                .handle_input(event, config.keymap.navigation.get(&event.code))
+            {
+                state.view = *previous.clone();
+            }
+        }
+        View::Help(ref previous) => {
+            let (_, term_height) =
+                terminal::size().context("failed to query terminal dimensions")?;
+            if !state
+                .help
+                .scroll(config.keymap.navigation.get(&event.code), term_height)
+            {
+                state.view = *previous.clone();
+            }
+        }
+        View::Command(cmd) => match event.code {
+            KeyCode::Esc => state.view = View::Status,
+            _ if config.keymap.actions.get(&event.code) == Some(&GexAction::Quit) => {
+                return Ok(true);
+            }
+            KeyCode::Char(c) => cmd.handle_input(c, state)?,
+            _ => {}
+        },
+        View::Input(ref callback, ref return_view) => {
+            // This clone should be very cheap as we should never be constructing a
+            // View::Input with the return view as View::Input.
+            //
+            // NOTE: This all indicates there is probably a better way to represent the
+            // View type, as it never actually needs to be recursive -- then we would also
+            // be able to just #[derive(Copy)].
+            debug_assert!(!matches!(**return_view, View::Input(..)));
+            state.minibuffer.handle_input(
+                event,
+                &Rc::clone(callback),
+                (**return_view).clone(),
+                &mut state.view,
+            )?;
         }
     }
+    Ok(false)
+}
+
+/// Start receiving mouse events, unless mouse support is turned off in the config so that the
+/// terminal's own text selection works.
+pub fn enable_mouse_capture() -> Result<()> {
+    if CONFIG.get().is_some_and(|config| config.options.mouse) {
+        crossterm::execute!(stdout(), event::EnableMouseCapture)
+            .context("failed to enable mouse capture")?;
+    }
+    Ok(())
 }
 
 /// Restore the terminal to its original state from before we messed with it.
@@ -402,6 +1087,7 @@ fn restore_terminal() {
     drop(terminal::disable_raw_mode());
     drop(crossterm::execute!(
         stdout(),
+        event::DisableMouseCapture,
         terminal::LeaveAlternateScreen,
         cursor::Show,
         cursor::MoveToColumn(0)
@@ -409,8 +1095,10 @@ fn restore_terminal() {
 }
 
 fn main() -> Result<()> {
-    run(&Clargs::parse()).map_err(|e| {
+    let result = run(&Clargs::parse());
+    // Errors from before the TUI was set up shouldn't be preceded by escape codes.
+    if terminal::is_raw_mode_enabled().unwrap_or(true) {
         restore_terminal();
-        e
-    })
+    }
+    result
 }
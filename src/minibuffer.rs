@@ -4,6 +4,7 @@
 use std::{
     env,
     io::{stdout, Write},
+    ops::Range,
     process::{Command, Output},
     rc::Rc,
     str,
@@ -19,19 +20,33 @@ use crossterm::{
 };
 use itertools::Itertools;
 
-use crate::{config, git_process, render::Clear, View};
+use crate::{
+    config, git_process,
+    render::{self, Clear},
+    View,
+};
 
 /// The messages to be sent to the buffer are maintained in this mutex as a stack.
 pub static MESSAGES: Mutex<Vec<(String, MessageType)>> = Mutex::new(Vec::new());
 
-/// The callback type for getting input.
-pub type Callback = Rc<dyn Fn(Option<&str>) -> Result<()>>;
+/// The callback type for getting input. It can return a [`FollowUp`] to be run once the input is
+/// finished with.
+pub type Callback = Rc<dyn Fn(Option<&str>) -> Result<Option<FollowUp>>>;
+
+/// Something to do after the minibuffer has handled some input, such as asking another question
+/// that depends on the answer.
+pub type FollowUp = Box<dyn FnOnce(&mut MiniBuffer, &mut View)>;
+
+/// A [`Callback`] without a follow-up.
+pub type SimpleCallback = Rc<dyn Fn(Option<&str>) -> Result<()>>;
 
 #[derive(PartialEq, Eq, Default)]
 enum State {
     #[default]
     Normal,
     Input,
+    /// Waiting for a single keypress to answer a question.
+    Key,
 }
 
 #[derive(Default)]
@@ -49,7 +64,7 @@ pub struct MiniBuffer {
     command_history: Vec<String>,
 
     buffer: String,
-    prompt: &'static str,
+    prompt: String,
     cursor: usize,
     history_cursor: usize,
     // Which history to use.
@@ -82,6 +97,8 @@ impl MiniBuffer {
         }
     }
 
+    /// Push the stdout and stderr of a process as a note and an error respectively. If the process
+    /// failed without writing anything to stderr, its exit status is pushed instead.
     pub fn push_command_output(output: &Output) {
         match str::from_utf8(&output.stdout) {
             Ok(s) => Self::push(s, MessageType::Note),
@@ -97,13 +114,35 @@ impl MiniBuffer {
                 MessageType::Error,
             ),
         }
+        if !output.status.success() && output.stderr.trim_ascii().is_empty() {
+            Self::push(
+                &format!("Process failed with {}", output.status),
+                MessageType::Error,
+            );
+        }
     }
 
     /// Get some user input from this minibuffer and run `callback` on it.
     pub fn get_input(
+        &mut self,
+        callback: SimpleCallback,
+        prompt: Option<&str>,
+        view: &mut View,
+        return_view: View,
+    ) {
+        self.get_input_then(
+            Rc::new(move |input| callback(input).map(|()| None)),
+            prompt,
+            view,
+            return_view,
+        );
+    }
+
+    /// Like [`MiniBuffer::get_input`], but `callback` can return a [`FollowUp`].
+    pub fn get_input_then(
         &mut self,
         callback: Callback,
-        prompt: Option<&'static str>,
+        prompt: Option<&str>,
         view: &mut View,
         return_view: View,
     ) {
@@ -111,11 +150,93 @@ impl MiniBuffer {
         self.buffer.clear();
         self.history_cursor = 0;
         self.state = State::Input;
-        self.prompt = prompt.unwrap_or("");
+        prompt.unwrap_or("").clone_into(&mut self.prompt);
         *view = View::Input(callback, Box::new(return_view));
     }
 
-    /// `return_view`: the [`View`](crate::View) to switch to after exiting `View::Input`.
+    /// Start the input that is being asked for with `input`, e.g. so that an existing name can be
+    /// edited.
+    pub fn prefill(&mut self, input: &str) {
+        input.clone_into(&mut self.buffer);
+        self.cursor = self.buffer.len();
+    }
+
+    /// Ask the user a question that's answered with a single key, running `callback` with the key
+    /// that was pressed, or `None` if it wasn't a character.
+    pub fn read_key(
+        &mut self,
+        callback: SimpleCallback,
+        prompt: &str,
+        view: &mut View,
+        return_view: View,
+    ) {
+        self.read_key_then(
+            Rc::new(move |input| callback(input).map(|()| None)),
+            prompt,
+            view,
+            return_view,
+        );
+    }
+
+    fn read_key_then(
+        &mut self,
+        callback: Callback,
+        prompt: &str,
+        view: &mut View,
+        return_view: View,
+    ) {
+        self.get_input_then(
+            Rc::new(move |input| {
+                print!("{}", cursor::Hide);
+                callback(input)
+            }),
+            Some(prompt),
+            view,
+            return_view,
+        );
+        self.state = State::Key;
+    }
+
+    /// Ask the user a yes/no question, running `callback` if they answer yes by pressing
+    /// <kbd>y</kbd>. Any other key cancels.
+    pub fn confirm(
+        &mut self,
+        callback: Rc<dyn Fn() -> Result<()>>,
+        prompt: &str,
+        view: &mut View,
+        return_view: View,
+    ) {
+        self.confirm_then(
+            Rc::new(move || callback().map(|()| None)),
+            prompt,
+            view,
+            return_view,
+        );
+    }
+
+    /// Like [`MiniBuffer::confirm`], but `callback` can return a [`FollowUp`].
+    pub fn confirm_then(
+        &mut self,
+        callback: Rc<dyn Fn() -> Result<Option<FollowUp>>>,
+        prompt: &str,
+        view: &mut View,
+        return_view: View,
+    ) {
+        self.read_key_then(
+            Rc::new(move |input| {
+                if input.is_some_and(|key| key.eq_ignore_ascii_case("y")) {
+                    return callback();
+                }
+                Ok(None)
+            }),
+            &format!("{prompt} [y/N] "),
+            view,
+            return_view,
+        );
+    }
+
+    /// `return_view`: the [`View`](crate::View) to switch to after exiting `View::Input`, before
+    /// any [`FollowUp`] that the callback returns is run.
     ///
     /// # Notes
     ///
@@ -133,14 +254,28 @@ impl MiniBuffer {
             ref mut history_cursor,
             ..
         } = self;
+        if self.state == State::Key {
+            self.state = State::Normal;
+            *view = return_view;
+            let follow_up = match key_event.code {
+                KeyCode::Char(c) => callback(Some(c.encode_utf8(&mut [0; 4])))?,
+                _ => callback(None)?,
+            };
+            if let Some(follow_up) = follow_up {
+                follow_up(self, view);
+            }
+            return Ok(());
+        }
+
         let history = match self.history {
             History::Command => &mut self.command_history,
             History::Git => &mut self.git_command_history,
         };
+        let mut follow_up = None;
         match (key_event.code, key_event.modifiers) {
             (KeyCode::Enter, _) => {
                 history.push(self.buffer.clone());
-                callback(Some(&self.buffer))?;
+                follow_up = callback(Some(&self.buffer))?;
                 self.state = State::Normal;
                 self.buffer.clear();
                 *view = return_view;
@@ -148,17 +283,17 @@ impl MiniBuffer {
             (KeyCode::Left, _) | (KeyCode::Char('b'), KeyModifiers::CONTROL) => {
                 *cursor = cursor.saturating_sub(1);
             }
-            (KeyCode::Right, _) | (KeyCode::Char('f'), KeyModifiers::CONTROL) => {
-                if *cursor < buffer.len() {
-                    *cursor += 1;
-                }
+            (KeyCode::Right, _) | (KeyCode::Char('f'), KeyModifiers::CONTROL)
+                if *cursor < buffer.len() =>
+            {
+                *cursor += 1;
             }
-            (KeyCode::Up, _) | (KeyCode::Char('p'), KeyModifiers::CONTROL) => {
-                if *history_cursor < history.len() {
-                    *history_cursor += 1;
-                    history[history.len() - *history_cursor].clone_into(buffer);
-                    *cursor = buffer.len();
-                }
+            (KeyCode::Up, _) | (KeyCode::Char('p'), KeyModifiers::CONTROL)
+                if *history_cursor < history.len() =>
+            {
+                *history_cursor += 1;
+                history[history.len() - *history_cursor].clone_into(buffer);
+                *cursor = buffer.len();
             }
             (KeyCode::Down, _) | (KeyCode::Char('n'), KeyModifiers::CONTROL) => {
                 *history_cursor = history_cursor.saturating_sub(1);
@@ -187,15 +322,14 @@ impl MiniBuffer {
                     }
                 }
             }
-            (KeyCode::Char(c), _) => {
+            // Other keys with Ctrl or Alt aren't typed.
+            (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
                 buffer.insert(*cursor, c);
                 *cursor += 1;
             }
-            (KeyCode::Backspace, _) => {
-                if *cursor > 0 {
-                    *cursor -= 1;
-                    buffer.remove(*cursor);
-                }
+            (KeyCode::Backspace, _) if *cursor > 0 => {
+                *cursor -= 1;
+                buffer.remove(*cursor);
             }
             (KeyCode::Delete, _) => {
                 if (*cursor) < buffer.len() || *cursor == 0 && buffer.len() == 1 {
@@ -206,13 +340,16 @@ impl MiniBuffer {
                 }
             }
             (KeyCode::Esc, _) => {
-                callback(None)?;
+                follow_up = callback(None)?;
                 self.state = State::Normal;
                 self.buffer.clear();
                 *view = return_view;
             }
             _ => {}
         }
+        if let Some(follow_up) = follow_up {
+            follow_up(self, view);
+        }
         Ok(())
     }
 
@@ -268,11 +405,11 @@ impl MiniBuffer {
         );
     }
 
-    /// Render the contents of the buffer.
-    pub fn render(&mut self, term_width: u16, term_height: u16) -> Result<()> {
+    /// Render the contents of the buffer. Returns the rows it was rendered on.
+    pub fn render(&self, term_width: u16, term_height: u16) -> Result<Range<u16>> {
         if self.state == State::Normal {
             if self.buffer.is_empty() {
-                return Ok(());
+                return Ok(term_height..term_height);
             }
             // Make sure raw mode is disabled so we can just print the message.
             terminal::disable_raw_mode().context("failed to exit raw mode")?;
@@ -280,7 +417,7 @@ impl MiniBuffer {
 
         let (border, prompt) = match self.state {
             State::Normal => ("─", ""),
-            State::Input => ("\u{2574}", self.prompt),
+            State::Input | State::Key => ("\u{2574}", self.prompt.as_str()),
         };
 
         let current_height = std::cmp::max(self.buffer.lines().count() + 1, 2) as u16;
@@ -295,9 +432,8 @@ impl MiniBuffer {
         match self.state {
             State::Normal => {
                 terminal::enable_raw_mode().context("failed to enable raw mode")?;
-                self.buffer.clear();
             }
-            State::Input => {
+            State::Input | State::Key => {
                 print!(
                     "{}{}{}",
                     cursor::Show,
@@ -312,7 +448,15 @@ impl MiniBuffer {
         }
 
         drop(stdout().flush());
-        Ok(())
+        Ok(term_height.saturating_sub(current_height)..term_height)
+    }
+
+    /// Stop displaying the current message, if there is one. It's kept until then so that it's
+    /// displayed again if the screen is redrawn, e.g. because the terminal was resized.
+    pub fn dismiss_message(&mut self) {
+        if self.state == State::Normal {
+            self.buffer.clear();
+        }
     }
 
     /// Pops the most recent message sent into the minibuffer.
@@ -323,6 +467,7 @@ impl MiniBuffer {
         };
         self.buffer = match msg_type {
             MessageType::Note => msg,
+            MessageType::Error if render::plain() => msg,
             MessageType::Error => format!("{}{msg}", SetForegroundColor(config!().colors.error)),
         };
     }
@@ -335,7 +480,5 @@ fn word_boundary(buffer: &str, idx: usize) -> bool {
         .chars()
         .tuple_windows()
         .nth(idx.saturating_sub(1))
-        .map_or(true, |(c1, c2)| {
-            !c1.is_alphanumeric() && c2.is_alphanumeric()
-        })
+        .is_none_or(|(c1, c2)| !c1.is_alphanumeric() && c2.is_alphanumeric())
 }
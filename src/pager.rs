@@ -0,0 +1,92 @@
+//! Module relating to showing the output of a git command, such as `git show`, in place of a list
+//! until it's closed.
+
+use std::fmt;
+
+use crossterm::{
+    event::{KeyCode, KeyEvent},
+    style::SetForegroundColor,
+};
+
+use crate::{
+    config::{Action, CONFIG},
+    render::{self, Renderer},
+};
+
+/// The output of a git command being shown, which is scrolled through a line at a time.
+#[derive(Debug, Default)]
+pub struct Pager {
+    /// The output being shown, if any.
+    output: Option<String>,
+    /// The first line of the output that is displayed.
+    scroll: usize,
+    /// Whether the output is a diff, whose lines are coloured.
+    diff: bool,
+}
+
+impl render::Render for Pager {
+    fn render(&self, f: &mut Renderer) -> fmt::Result {
+        use fmt::Write;
+        let config = CONFIG.get().expect("config wasn't initialised");
+        let Some(output) = &self.output else {
+            return Ok(());
+        };
+
+        f.insert_cursor();
+        for line in output.lines().skip(self.scroll) {
+            if !self.diff {
+                writeln!(f, "\r{line}")?;
+                continue;
+            }
+            let color = match line.chars().next() {
+                Some('+') => config.colors.addition,
+                Some('-') => config.colors.deletion,
+                _ if line.starts_with("@@") => config.colors.hunk_head,
+                _ => config.colors.foreground,
+            };
+            writeln!(f, "\r{}{line}", SetForegroundColor(color))?;
+        }
+        write!(f, "{}", SetForegroundColor(config.colors.foreground))
+    }
+}
+
+impl Pager {
+    /// Show `output` from the top, colouring it if it's a `diff`. Control characters are replaced,
+    /// since they could mess up the terminal.
+    pub fn open(&mut self, output: &[u8], diff: bool) {
+        self.output = Some(String::from_utf8_lossy(output).replace(
+            |c: char| c.is_control() && c != '\n' && c != '\t',
+            "\u{fffd}",
+        ));
+        self.scroll = 0;
+        self.diff = diff;
+    }
+
+    pub fn close(&mut self) {
+        self.output = None;
+    }
+
+    pub const fn is_open(&self) -> bool {
+        self.output.is_some()
+    }
+
+    /// Handle a key press if the output is being shown, scrolling through it or closing it with
+    /// Esc or `q`. Returns whether the key was handled.
+    pub fn handle_input(&mut self, key_event: KeyEvent, action: Option<&Action>) -> bool {
+        let Some(output) = &self.output else {
+            return false;
+        };
+        let last_line = output.lines().count().saturating_sub(1);
+        match action {
+            Some(Action::MoveDown) => self.scroll = (self.scroll + 1).min(last_line),
+            Some(Action::MoveUp) => self.scroll = self.scroll.saturating_sub(1),
+            Some(Action::GotoTop) => self.scroll = 0,
+            Some(Action::GotoBottom) => self.scroll = last_line,
+            _ => {}
+        }
+        if matches!(key_event.code, KeyCode::Esc | KeyCode::Char('q')) {
+            self.close();
+        }
+        true
+    }
+}
@@ -1,58 +1,521 @@
-use std::collections::HashMap;
-
-use anyhow::{Context, Result};
-use itertools::Itertools;
-use nom::{bytes::complete::tag, character::complete::not_line_ending, IResult};
-
-/// The returned hashmap associates a filename with a `Vec` of `String` where the strings contain
-/// the content of each hunk.
-pub fn parse_diff(input: &str) -> Result<HashMap<&str, Vec<String>>> {
-    let mut diffs = HashMap::new();
-    for diff in input
-        .lines()
-        .collect::<Vec<_>>()
-        .split(|l| l.starts_with("diff"))
-        .skip(1)
+use std::ops::BitOr;
+
+use anyhow::{anyhow, Context, Result};
+use nom::{
+    bytes::complete::{tag, take_till1},
+    character::complete::{anychar, char, digit1},
+    combinator::{map_res, opt, rest},
+    multi::count,
+    sequence::{preceded, terminated, tuple},
+    IResult,
+};
+
+use crate::diff::{DiffLine, Hunk};
+
+/// The output of `git status --porcelain=v2 --branch -z`.
+///
+/// <https://git-scm.com/docs/git-status#_porcelain_format_version_2>
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct PorcelainStatus<'a> {
+    /// The name of the current branch, or `None` if HEAD is detached.
+    pub head: Option<&'a str>,
+    /// The upstream branch, if one is set.
+    pub upstream: Option<&'a str>,
+    /// The number of commits ahead of and behind the upstream branch. This is `None` if there
+    /// isn't an upstream or it no longer exists.
+    pub ahead_behind: Option<(usize, usize)>,
+    pub entries: Vec<StatusEntry<'a>>,
+}
+
+/// A single file entry from `git status --porcelain=v2`. The `index` and `worktree` fields are the
+/// `XY` status codes, where `.` means unmodified.
+#[derive(Debug, PartialEq, Eq)]
+pub enum StatusEntry<'a> {
+    Changed {
+        index: char,
+        worktree: char,
+        path: &'a str,
+    },
+    Renamed {
+        index: char,
+        worktree: char,
+        path: &'a str,
+        orig_path: &'a str,
+    },
+    /// A path with a merge conflict. `index` and `worktree` describe the change made by us and by
+    /// them respectively, where `U` means modified.
+    Unmerged {
+        index: char,
+        worktree: char,
+        path: &'a str,
+    },
+    Untracked(&'a str),
+    /// A path that is ignored, which is only listed with `--ignored`.
+    Ignored(&'a str),
+    /// A submodule that has changed, either in the index and worktree like a file or in its own
+    /// working tree. It can come from a changed, renamed or unmerged entry, and for the last the
+    /// `index` and `worktree` fields are the codes of the conflict.
+    Submodule {
+        index: char,
+        worktree: char,
+        submodule: Submodule<'a>,
+    },
+}
+
+/// The ways that a submodule can differ from the commit recorded for it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SubmoduleState(u8);
+
+impl SubmoduleState {
+    /// A different commit is checked out.
+    pub const NEW_COMMITS: Self = Self(1);
+    /// Tracked files have been modified.
+    pub const MODIFIED: Self = Self(1 << 1);
+    /// There are untracked files.
+    pub const UNTRACKED: Self = Self(1 << 2);
+    /// A different commit has been staged for it.
+    pub const STAGED: Self = Self(1 << 3);
+    /// It has a merge conflict, i.e. the two sides recorded different commits for it.
+    pub const CONFLICTED: Self = Self(1 << 4);
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Parse the `<sub>` field of a porcelain status entry, e.g. `SC.U`. Returns `None` if the
+    /// entry isn't a submodule, in which case the field is `N...`.
+    fn from_field(sub: &str) -> Option<Self> {
+        let mut codes = sub.strip_prefix('S')?.chars();
+        let mut state = Self::default();
+        for (code, flag) in [
+            ('C', Self::NEW_COMMITS),
+            ('M', Self::MODIFIED),
+            ('U', Self::UNTRACKED),
+        ] {
+            if codes.next() == Some(code) {
+                state = state | flag;
+            }
+        }
+        Some(state)
+    }
+}
+
+impl BitOr for SubmoduleState {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+/// A submodule from `git status --porcelain=v2`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Submodule<'a> {
+    pub path: &'a str,
+    /// The commit recorded for the submodule in HEAD.
+    pub head_oid: &'a str,
+    pub state: SubmoduleState,
+}
+
+/// A space-terminated field of a porcelain status entry.
+fn field(input: &str) -> IResult<&str, &str> {
+    terminated(take_till1(|c| c == ' '), char(' '))(input)
+}
+
+/// The entry for a submodule, if `sub` is the `<sub>` field of one. `head_oid` is the commit
+/// recorded for it in HEAD, and `state` any changes that aren't told by `<sub>`.
+fn submodule_entry<'a>(
+    index: char,
+    worktree: char,
+    sub: &str,
+    head_oid: &'a str,
+    path: &'a str,
+    state: SubmoduleState,
+) -> Option<StatusEntry<'a>> {
+    let state = SubmoduleState::from_field(sub)? | state;
+    Some(StatusEntry::Submodule {
+        index,
+        worktree,
+        submodule: Submodule {
+            path,
+            head_oid,
+            state,
+        },
+    })
+}
+
+/// The change to the index that an `<X>` status code is, as far as a submodule is concerned.
+fn staged(index: char) -> SubmoduleState {
+    if index == '.' {
+        SubmoduleState::default()
+    } else {
+        SubmoduleState::STAGED
+    }
+}
+
+/// `1 <XY> <sub> <mH> <mI> <mW> <hH> <hI> <path>`
+fn changed_entry(input: &str) -> IResult<&str, StatusEntry<'_>> {
+    let (input, (_, index, worktree, _, fields, path)) = tuple((
+        tag("1 "),
+        anychar,
+        anychar,
+        char(' '),
+        count(field, 6),
+        rest,
+    ))(input)?;
+    if let Some(entry) = submodule_entry(index, worktree, fields[0], fields[4], path, staged(index))
     {
-        diffs.insert(get_path(diff)?, get_hunks(diff)?);
+        return Ok((input, entry));
     }
-    Ok(diffs)
+    Ok((
+        input,
+        StatusEntry::Changed {
+            index,
+            worktree,
+            path,
+        },
+    ))
 }
 
-fn get_path<'a>(diff: &[&'a str]) -> Result<&'a str> {
-    let diff: IResult<&str, &str> = tag("+++ b/")(diff.get(2).unwrap_or(&""));
-    let Ok((diff, _)) = diff else { return Ok("") };
-    let path: IResult<&str, &str> = not_line_ending(diff);
-    let (_, path) = path
-        .map_err(|e| e.to_owned())
-        .context("failed to parse a path from diff")?;
-    Ok(path)
+/// `2 <XY> <sub> <mH> <mI> <mW> <hH> <hI> <X><score> <path>`
+///
+/// The `<origPath>` is in the following NUL-terminated record, so it's filled in by the caller.
+/// A moved submodule is listed under its new path.
+fn renamed_entry(input: &str) -> IResult<&str, StatusEntry<'_>> {
+    let (input, (_, index, worktree, _, fields, path)) = tuple((
+        tag("2 "),
+        anychar,
+        anychar,
+        char(' '),
+        count(field, 7),
+        rest,
+    ))(input)?;
+    if let Some(entry) = submodule_entry(index, worktree, fields[0], fields[4], path, staged(index))
+    {
+        return Ok((input, entry));
+    }
+    Ok((
+        input,
+        StatusEntry::Renamed {
+            index,
+            worktree,
+            path,
+            orig_path: "",
+        },
+    ))
 }
 
-fn get_hunks(diff: &[&str]) -> Result<Vec<String>> {
-    let mut hunks = Vec::new();
-    let hunk_groups = diff.iter().group_by(|line| line.starts_with("@@"));
-    let mut hunk_groups = hunk_groups.into_iter();
-    loop {
-        let Some((key, mut lines)) = hunk_groups.next() else {
+/// `u <XY> <sub> <m1> <m2> <m3> <mW> <h1> <h2> <h3> <path>`
+///
+/// For a submodule, the commit of our side, `<h2>`, is taken as the one recorded in HEAD.
+fn unmerged_entry(input: &str) -> IResult<&str, StatusEntry<'_>> {
+    let (input, (_, index, worktree, _, fields, path)) = tuple((
+        tag("u "),
+        anychar,
+        anychar,
+        char(' '),
+        count(field, 8),
+        rest,
+    ))(input)?;
+    if let Some(entry) = submodule_entry(
+        index,
+        worktree,
+        fields[0],
+        fields[6],
+        path,
+        SubmoduleState::CONFLICTED,
+    ) {
+        return Ok((input, entry));
+    }
+    Ok((
+        input,
+        StatusEntry::Unmerged {
+            index,
+            worktree,
+            path,
+        },
+    ))
+}
+
+/// `# branch.ab +<ahead> -<behind>`
+fn ahead_behind(input: &str) -> IResult<&str, (usize, usize)> {
+    let (input, (_, ahead, _, behind)) = tuple((
+        char('+'),
+        map_res(digit1, str::parse),
+        tag(" -"),
+        map_res(digit1, str::parse),
+    ))(input)?;
+    Ok((input, (ahead, behind)))
+}
+
+/// Parse the output of `git status --porcelain=v2 --branch -z`. Records are NUL-terminated and
+/// paths are never quoted, so any path can be represented.
+pub fn parse_porcelain_status(input: &str) -> Result<PorcelainStatus<'_>> {
+    let mut status = PorcelainStatus::default();
+    let mut records = input.split_terminator('\0');
+    while let Some(record) = records.next() {
+        let parsed = match record
+            .split_at_checked(2)
+            .map_or(record, |(prefix, _)| prefix)
+        {
+            "# " => {
+                if let Some(head) = record.strip_prefix("# branch.head ") {
+                    status.head = (head != "(detached)").then_some(head);
+                } else if let Some(upstream) = record.strip_prefix("# branch.upstream ") {
+                    status.upstream = Some(upstream);
+                } else if let Some(ab) = record.strip_prefix("# branch.ab ") {
+                    let (_, ab) =
+                        ahead_behind(ab)
+                            .map_err(|e| e.to_owned())
+                            .with_context(|| {
+                                format!("failed to parse `git status` header: `{record}`")
+                            })?;
+                    status.ahead_behind = Some(ab);
+                }
+                continue;
+            }
+            "1 " => changed_entry(record),
+            "2 " => {
+                // The original path has to be read even when it isn't used, e.g. for a submodule.
+                let original = records.next().unwrap_or_default();
+                renamed_entry(record).map(|(input, mut entry)| {
+                    if let StatusEntry::Renamed {
+                        ref mut orig_path, ..
+                    } = entry
+                    {
+                        *orig_path = original;
+                    }
+                    (input, entry)
+                })
+            }
+            "? " => {
+                status.entries.push(StatusEntry::Untracked(&record[2..]));
+                continue;
+            }
+            "u " => unmerged_entry(record),
+            "! " => {
+                status.entries.push(StatusEntry::Ignored(&record[2..]));
+                continue;
+            }
+            _ => {
+                return Err(anyhow!(
+                    "unexpected record in `git status` output: `{record}`"
+                ))
+            }
+        };
+        let (_, entry) = parsed
+            .map_err(|e| e.to_owned())
+            .with_context(|| format!("failed to parse `git status` entry: `{record}`"))?;
+        status.entries.push(entry);
+    }
+    Ok(status)
+}
+
+/// The changes to a single file in the output of `git diff`.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct FilePatch {
+    /// The path before the change, or `None` if the file was added.
+    pub old_path: Option<String>,
+    /// The path after the change, or `None` if the file was deleted.
+    pub new_path: Option<String>,
+    /// The mode before the change, e.g. `100644`, if the file existed and git reported it.
+    pub old_mode: Option<String>,
+    /// The mode after the change, if the file still exists and git reported it.
+    pub new_mode: Option<String>,
+    pub hunks: Vec<Hunk>,
+    /// Whether the file is binary, in which case git doesn't show the changes.
+    pub binary: bool,
+}
+
+impl FilePatch {
+    /// The path of the file, which is the old path if it was deleted.
+    pub fn path(&self) -> &str {
+        self.new_path
+            .as_deref()
+            .or(self.old_path.as_deref())
+            .unwrap_or_default()
+    }
+}
+
+fn number(input: &str) -> IResult<&str, usize> {
+    map_res(digit1, str::parse)(input)
+}
+
+/// `@@ -<old start>[,<old count>] +<new start>[,<new count>] @@[heading]`. The counts are left out
+/// when they're 1.
+fn hunk_header(input: &str) -> IResult<&str, Hunk> {
+    let (input, (_, old_start, old_count, _, new_start, new_count, _, heading)) = tuple((
+        tag("@@ -"),
+        number,
+        opt(preceded(char(','), number)),
+        tag(" +"),
+        number,
+        opt(preceded(char(','), number)),
+        tag(" @@"),
+        rest,
+    ))(input)?;
+    Ok((
+        input,
+        Hunk {
+            old_start,
+            old_count: old_count.unwrap_or(1),
+            new_start,
+            new_count: new_count.unwrap_or(1),
+            heading: heading.to_string(),
+            lines: Vec::new(),
+        },
+    ))
+}
+
+/// A path from a `---` or `+++` line, which is `/dev/null` for a file that doesn't exist on that
+/// side. git appends a tab to paths containing spaces.
+fn path(path: &str, prefix: &str) -> Option<String> {
+    unquote(path.trim_end_matches('\t'))
+        .strip_prefix(prefix)
+        .map(str::to_string)
+}
+
+/// Undo the C-style quoting git uses for paths containing quotes, backslashes or control
+/// characters, and for non-ASCII paths unless `core.quotePath` is off, e.g. `"caf\303\251.txt"`.
+/// Paths that aren't quoted are returned unchanged.
+fn unquote(path: &str) -> String {
+    let Some(quoted) = path.strip_prefix('"').and_then(|p| p.strip_suffix('"')) else {
+        return path.to_string();
+    };
+    let mut bytes = Vec::with_capacity(quoted.len());
+    let mut input = quoted.bytes().peekable();
+    while let Some(byte) = input.next() {
+        if byte != b'\\' {
+            bytes.push(byte);
+            continue;
+        }
+        let Some(escaped) = input.next() else {
             break;
         };
-        let hunk_head = *lines.next().context("expected another line in diff")?;
-        if !key {
+        bytes.push(match escaped {
+            b'a' => 0x07,
+            b'b' => 0x08,
+            b't' => b'\t',
+            b'n' => b'\n',
+            b'v' => 0x0b,
+            b'f' => 0x0c,
+            b'r' => b'\r',
+            // Three octal digits, with each byte of a multibyte character escaped separately.
+            b'0'..=b'7' => {
+                let mut value = escaped - b'0';
+                for _ in 0..2 {
+                    if let Some(digit) = input.next_if(|b| (b'0'..=b'7').contains(b)) {
+                        value = value.wrapping_mul(8).wrapping_add(digit - b'0');
+                    }
+                }
+                value
+            }
+            other => other,
+        });
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// The old and new paths on a `diff --git` line, either of which may be quoted. Unquoted paths are
+/// only ambiguous here if they contain ` b/`, in which case they're given again in the extended
+/// header unless the file is unchanged apart from its mode.
+fn git_diff_paths(paths: &str) -> (String, String) {
+    let (old, new) = if paths.starts_with('"') {
+        // The closing quote is the first one that isn't escaped.
+        let mut escaped = false;
+        let end = paths
+            .char_indices()
+            .skip(1)
+            .find(|&(_, c)| {
+                let end = c == '"' && !escaped;
+                escaped = c == '\\' && !escaped;
+                end
+            })
+            .map_or(paths.len(), |(i, _)| i + 1);
+        let (old, new) = paths.split_at(end);
+        (old, new.trim_start_matches(' '))
+    } else if let Some(i) = paths.ends_with('"').then(|| paths.rfind(" \"b/")).flatten() {
+        (&paths[..i], &paths[i + 1..])
+    } else {
+        paths
+            .find(" b/")
+            .map_or((paths, ""), |i| (&paths[..i], &paths[i + 1..]))
+    };
+    (
+        path(old, "a/").unwrap_or_default(),
+        path(new, "b/").unwrap_or_default(),
+    )
+}
+
+/// Parse the output of `git diff`. Anything that isn't part of a `diff --git` is skipped, such as
+/// the combined diffs of unmerged files, which are shown whole instead, or the summaries of
+/// submodules with `diff.submodule = log`.
+pub fn parse_diff(input: &str) -> Result<Vec<FilePatch>> {
+    let mut files = Vec::new();
+    // Only split on `\n`, since a `\r` at the end of a line belongs to the line in files with
+    // CRLF line endings, and has to be kept for `git apply`.
+    let mut lines = input.split_terminator('\n').peekable();
+    while let Some(line) = lines.next() {
+        let Some(paths) = line.strip_prefix("diff --git ") else {
             continue;
+        };
+        let (old_path, new_path) = git_diff_paths(paths);
+        let mut file = FilePatch {
+            old_path: Some(old_path),
+            new_path: Some(new_path),
+            ..FilePatch::default()
+        };
+
+        // The extended header, up to the first hunk.
+        while let Some(line) = lines.next_if(|l| !l.starts_with("@@") && !l.starts_with("diff ")) {
+            if let Some(mode) = line.strip_prefix("old mode ") {
+                file.old_mode = Some(mode.to_string());
+            } else if let Some(mode) = line.strip_prefix("new mode ") {
+                file.new_mode = Some(mode.to_string());
+            } else if let Some(mode) = line.strip_prefix("deleted file mode ") {
+                file.old_mode = Some(mode.to_string());
+                file.new_path = None;
+            } else if let Some(mode) = line.strip_prefix("new file mode ") {
+                file.new_mode = Some(mode.to_string());
+                file.old_path = None;
+            } else if let Some(index) = line.strip_prefix("index ") {
+                // The mode is only given here if it didn't change.
+                if let Some((_, mode)) = index.split_once(' ') {
+                    file.old_mode = Some(mode.to_string());
+                    file.new_mode = Some(mode.to_string());
+                }
+            } else if let Some(path) = line
+                .strip_prefix("rename from ")
+                .or_else(|| line.strip_prefix("copy from "))
+            {
+                file.old_path = Some(unquote(path));
+            } else if let Some(path) = line
+                .strip_prefix("rename to ")
+                .or_else(|| line.strip_prefix("copy to "))
+            {
+                file.new_path = Some(unquote(path));
+            } else if let Some(old) = line.strip_prefix("--- ") {
+                file.old_path = path(old, "a/");
+            } else if let Some(new) = line.strip_prefix("+++ ") {
+                file.new_path = path(new, "b/");
+            } else if line.starts_with("Binary files ") {
+                file.binary = true;
+            }
         }
-        // XXX: `lines` should never have more than one item but we're not actually checking that.
-
-        let (_key, hunk_tail) = hunk_groups
-            .next()
-            .context("strange output from `git diff`")?;
-        hunks.push(
-            std::iter::once(hunk_head)
-                .chain(hunk_tail.copied())
-                .join("\n"),
-        );
+
+        while let Some(line) = lines.next_if(|l| l.starts_with("@@")) {
+            let (_, mut hunk) = hunk_header(line)
+                .map_err(|e| anyhow!("failed to parse hunk header `{line}`: {e}"))?;
+            while let Some(line) =
+                lines.next_if(|l| matches!(l.chars().next(), None | Some(' ' | '+' | '-' | '\\')))
+            {
+                hunk.lines.push(DiffLine::parse(line)?);
+            }
+            file.hunks.push(hunk);
+        }
+        files.push(file);
     }
-    Ok(hunks)
+    Ok(files)
 }
 
 /// Gets the `old` part of the hunk header
@@ -79,10 +542,221 @@ pub fn parse_hunk_new(header: &str) -> Result<&str> {
     Ok(old)
 }
 
+/// Parse `%(upstream:track)` from `git for-each-ref` into the number of commits ahead of and behind
+/// the upstream, e.g. `[ahead 1, behind 2]`. It's empty when the branch is in sync with its
+/// upstream, and `[gone]` if the upstream no longer exists, which gives `None`.
+pub fn parse_upstream_track(track: &str) -> Result<Option<(usize, usize)>> {
+    let counts = match track.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+        _ if track.is_empty() => return Ok(Some((0, 0))),
+        Some("gone") => return Ok(None),
+        Some(counts) => counts,
+        None => return Err(anyhow!("unexpected upstream tracking info: `{track}`")),
+    };
+    let (mut ahead, mut behind) = (0, 0);
+    for count in counts.split(", ") {
+        match count.split_once(' ') {
+            Some(("ahead", n)) => ahead = n.parse().context("invalid count of commits ahead")?,
+            Some(("behind", n)) => behind = n.parse().context("invalid count of commits behind")?,
+            _ => return Err(anyhow!("unexpected upstream tracking info: `{track}`")),
+        }
+    }
+    Ok(Some((ahead, behind)))
+}
+
 #[cfg(test)]
 mod tests {
     use test_case::test_case;
 
+    use super::{FilePatch, PorcelainStatus, StatusEntry, Submodule, SubmoduleState};
+    use crate::diff::{DiffLine, Hunk};
+
+    const STATUS_CLEAN: &str = concat!(
+        "# branch.oid e68603fb46c68ef088ebee51c9ab297cdae92f47\0",
+        "# branch.head main\0",
+    );
+
+    const STATUS_INITIAL: &str = concat!(
+        "# branch.oid (initial)\0",
+        "# branch.head main\0",
+        "1 A. N... 000000 100644 100644 0000000000000000000000000000000000000000 78981922613b2afb6025042ff6bd878ac1994e85 a\0",
+        "? un tracked\0",
+        "? \"new\nline\"\0",
+        "! target/\0",
+    );
+
+    const STATUS_DETACHED: &str = concat!(
+        "# branch.oid 97c90639e7e44e85e67b64df420aacecfa95a98e\0",
+        "# branch.head (detached)\0",
+        "1 .M N... 100644 100644 100644 78981922613b2afb6025042ff6bd878ac1994e85 78981922613b2afb6025042ff6bd878ac1994e85 src/main.rs\0",
+    );
+
+    const STATUS_RENAMED_COPIED_DELETED: &str = concat!(
+        "# branch.oid e68603fb46c68ef088ebee51c9ab297cdae92f47\0",
+        "# branch.head main\0",
+        "# branch.upstream origin/main\0",
+        "# branch.ab +1 -2\0",
+        "2 RM N... 100644 100644 100644 78981922613b2afb6025042ff6bd878ac1994e85 422c2b7ab3b3c668038da977e4e93a5fc623169c R50 b\0",
+        "a\0",
+        "2 C. N... 100644 100644 100644 78981922613b2afb6025042ff6bd878ac1994e85 78981922613b2afb6025042ff6bd878ac1994e85 C100 src/copy.rs\0",
+        "src/orig.rs\0",
+        "1 D. N... 100644 000000 000000 78981922613b2afb6025042ff6bd878ac1994e85 0000000000000000000000000000000000000000 deleted.rs\0",
+        "1 .D N... 100644 100644 000000 78981922613b2afb6025042ff6bd878ac1994e85 78981922613b2afb6025042ff6bd878ac1994e85 with space.rs\0",
+    );
+
+    const STATUS_UPSTREAM_GONE: &str = concat!(
+        "# branch.oid e68603fb46c68ef088ebee51c9ab297cdae92f47\0",
+        "# branch.head feature\0",
+        "# branch.upstream origin/feature\0",
+    );
+
+    const STATUS_CONFLICT: &str = concat!(
+        "# branch.oid ba9e56f217e07587dafe1b31b8bef0e962cd1fa8\0",
+        "# branch.head main\0",
+        "u UU N... 100644 100644 100644 100644 de980441c3ab03a8c07dda1ad27b8a11f39deb1e 975fbec8256d3e8a3797e7a3611380f27c49f4ac 587be6b4c3f93f93c489c0111bba5596147a26cb b\0",
+        "u DU N... 100644 000000 100644 100644 de980441c3ab03a8c07dda1ad27b8a11f39deb1e 0000000000000000000000000000000000000000 587be6b4c3f93f93c489c0111bba5596147a26cb deleted by us\0",
+    );
+
+    const STATUS_SUBMODULES: &str = concat!(
+        "# branch.oid e68603fb46c68ef088ebee51c9ab297cdae92f47\0",
+        "# branch.head main\0",
+        "1 .M SC.U 160000 160000 160000 2bc8724a1f0e6d7c3b9a8e5f4d2c1b0a9e8f7d6c 2bc8724a1f0e6d7c3b9a8e5f4d2c1b0a9e8f7d6c vendor/lib\0",
+        "1 .M S.M. 160000 160000 160000 9e8f7d6c2bc8724a1f0e6d7c3b9a8e5f4d2c1b0a 9e8f7d6c2bc8724a1f0e6d7c3b9a8e5f4d2c1b0a docs\0",
+        "1 .M N... 100644 100644 100644 78981922613b2afb6025042ff6bd878ac1994e85 78981922613b2afb6025042ff6bd878ac1994e85 .gitmodules\0",
+        "1 M. SC.. 160000 160000 160000 5d2c1b0a9e8f7d6c2bc8724a1f0e6d7c3b9a8e5f 7d6c2bc8724a1f0e6d7c3b9a8e5f4d2c1b0a9e8f tools\0",
+        "2 R. S... 160000 160000 160000 3b9a8e5f4d2c1b0a9e8f7d6c2bc8724a1f0e6d7c 3b9a8e5f4d2c1b0a9e8f7d6c2bc8724a1f0e6d7c R100 third_party/lib\0lib\0",
+        "u UU S... 160000 160000 160000 160000 0a9e8f7d6c2bc8724a1f0e6d7c3b9a8e5f4d2c1b c1b0a9e8f7d6c2bc8724a1f0e6d7c3b9a8e5f4d2 e5f4d2c1b0a9e8f7d6c2bc8724a1f0e6d7c3b9a8 themes\0",
+    );
+
+    #[test_case(STATUS_CLEAN, &PorcelainStatus { head: Some("main"), upstream: None, ahead_behind: None, entries: vec![] } ; "clean")]
+    #[test_case(
+        STATUS_INITIAL,
+        &PorcelainStatus {
+            head: Some("main"),
+            upstream: None,
+            ahead_behind: None,
+            entries: vec![
+                StatusEntry::Changed { index: 'A', worktree: '.', path: "a" },
+                StatusEntry::Untracked("un tracked"),
+                StatusEntry::Untracked("\"new\nline\""),
+                StatusEntry::Ignored("target/"),
+            ],
+        } ;
+        "initial commit"
+    )]
+    #[test_case(
+        STATUS_DETACHED,
+        &PorcelainStatus {
+            head: None,
+            upstream: None,
+            ahead_behind: None,
+            entries: vec![StatusEntry::Changed { index: '.', worktree: 'M', path: "src/main.rs" }],
+        } ;
+        "detached"
+    )]
+    #[test_case(
+        STATUS_RENAMED_COPIED_DELETED,
+        &PorcelainStatus {
+            head: Some("main"),
+            upstream: Some("origin/main"),
+            ahead_behind: Some((1, 2)),
+            entries: vec![
+                StatusEntry::Renamed { index: 'R', worktree: 'M', path: "b", orig_path: "a" },
+                StatusEntry::Renamed {
+                    index: 'C',
+                    worktree: '.',
+                    path: "src/copy.rs",
+                    orig_path: "src/orig.rs",
+                },
+                StatusEntry::Changed { index: 'D', worktree: '.', path: "deleted.rs" },
+                StatusEntry::Changed { index: '.', worktree: 'D', path: "with space.rs" },
+            ],
+        } ;
+        "renamed, copied and deleted"
+    )]
+    #[test_case(
+        STATUS_UPSTREAM_GONE,
+        &PorcelainStatus {
+            head: Some("feature"),
+            upstream: Some("origin/feature"),
+            ahead_behind: None,
+            entries: vec![],
+        } ;
+        "upstream gone"
+    )]
+    #[test_case(
+        STATUS_CONFLICT,
+        &PorcelainStatus {
+            head: Some("main"),
+            upstream: None,
+            ahead_behind: None,
+            entries: vec![
+                StatusEntry::Unmerged { index: 'U', worktree: 'U', path: "b" },
+                StatusEntry::Unmerged { index: 'D', worktree: 'U', path: "deleted by us" },
+            ],
+        } ;
+        "merge conflict"
+    )]
+    #[test_case(
+        STATUS_SUBMODULES,
+        &PorcelainStatus {
+            head: Some("main"),
+            upstream: None,
+            ahead_behind: None,
+            entries: vec![
+                StatusEntry::Submodule {
+                    index: '.',
+                    worktree: 'M',
+                    submodule: Submodule {
+                        path: "vendor/lib",
+                        head_oid: "2bc8724a1f0e6d7c3b9a8e5f4d2c1b0a9e8f7d6c",
+                        state: SubmoduleState::NEW_COMMITS | SubmoduleState::UNTRACKED,
+                    },
+                },
+                StatusEntry::Submodule {
+                    index: '.',
+                    worktree: 'M',
+                    submodule: Submodule {
+                        path: "docs",
+                        head_oid: "9e8f7d6c2bc8724a1f0e6d7c3b9a8e5f4d2c1b0a",
+                        state: SubmoduleState::MODIFIED,
+                    },
+                },
+                StatusEntry::Changed { index: '.', worktree: 'M', path: ".gitmodules" },
+                StatusEntry::Submodule {
+                    index: 'M',
+                    worktree: '.',
+                    submodule: Submodule {
+                        path: "tools",
+                        head_oid: "5d2c1b0a9e8f7d6c2bc8724a1f0e6d7c3b9a8e5f",
+                        state: SubmoduleState::NEW_COMMITS | SubmoduleState::STAGED,
+                    },
+                },
+                StatusEntry::Submodule {
+                    index: 'R',
+                    worktree: '.',
+                    submodule: Submodule {
+                        path: "third_party/lib",
+                        head_oid: "3b9a8e5f4d2c1b0a9e8f7d6c2bc8724a1f0e6d7c",
+                        state: SubmoduleState::STAGED,
+                    },
+                },
+                StatusEntry::Submodule {
+                    index: 'U',
+                    worktree: 'U',
+                    submodule: Submodule {
+                        path: "themes",
+                        head_oid: "c1b0a9e8f7d6c2bc8724a1f0e6d7c3b9a8e5f4d2",
+                        state: SubmoduleState::CONFLICTED,
+                    },
+                },
+            ],
+        } ;
+        "submodules"
+    )]
+    fn parse_porcelain_status(input: &str, expected: &PorcelainStatus) {
+        assert_eq!(&super::parse_porcelain_status(input).unwrap(), expected);
+    }
+
     const ISSUE_62: &str = "diff --git a/asteroid-loop/index.html b/asteroid-loop/index.html
 index d79df71..e2d1e9f 100644
 --- a/asteroid-loop/index.html
@@ -112,6 +786,23 @@ index d79df71..e2d1e9f 100644
 \\ No newline at end of file
 +</html>";
 
+    const DELETED_AND_SPACES: &str = "diff --git a/a.txt b/a.txt
+deleted file mode 100644
+index ddc897f..0000000
+--- a/a.txt
++++ /dev/null
+@@ -1,3 +0,0 @@
+-one
+-TWO
+-three
+diff --git a/sp ace.txt b/sp ace.txt
+index 45b983b..65ef226 100644
+--- a/sp ace.txt\t
++++ b/sp ace.txt\t
+@@ -1 +1,2 @@
+ hi
++more";
+
     #[test_case(ISSUE_62 ; "issue 62")]
     fn parse(diff: &str) {
         let parsed = super::parse_diff(diff);
@@ -119,4 +810,272 @@ index d79df71..e2d1e9f 100644
         let parsed = parsed.unwrap();
         assert_eq!(parsed.len(), 1);
     }
+
+    #[test]
+    fn parse_paths() {
+        let parsed = super::parse_diff(DELETED_AND_SPACES).unwrap();
+        let paths = parsed
+            .iter()
+            .map(|file| (file.path(), file.hunks.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(paths, [("a.txt", 1), ("sp ace.txt", 1)]);
+        // Counts of 1 are left out, like git does.
+        assert_eq!(parsed[1].hunks[0].to_string(), "@@ -1 +1,2 @@\n hi\n+more");
+    }
+
+    const RENAMED: &str = "diff --git a/old.txt b/new.txt
+similarity index 78%
+rename from old.txt
+rename to new.txt
+index b566061..dfc6c23 100644
+--- a/old.txt
++++ b/new.txt
+@@ -1,6 +1,6 @@
+ one
+ two
+-three
++THREE
+ four
+ five
+ six
+";
+
+    const BINARY: &str = "diff --git a/img.bin b/img.bin
+index 88768ef..3e3315e 100644
+Binary files a/img.bin and b/img.bin differ
+";
+
+    const NO_NEWLINE: &str = "diff --git a/nonl.txt b/nonl.txt
+index 0a207c0..817f660 100644
+--- a/nonl.txt
++++ b/nonl.txt
+@@ -1,2 +1,2 @@ heading
+ a
+-b
+\\ No newline at end of file
++c
+\\ No newline at end of file
+";
+
+    const CRLF: &str = "diff --git a/m.c b/m.c
+index db69dd7..2977631 100644
+--- a/m.c
++++ b/m.c
+@@ -3,4 +3,5 @@ int main() {
+   b;\r
+   c;\r
+-  e;\r
++  E;\r
+ }\r
++tail
+\\ No newline at end of file
+";
+
+    const NEW_FILE_AND_MODE_CHANGE: &str = "diff --git a/nonl.txt b/nonl.txt
+old mode 100644
+new mode 100755
+diff --git a/run.sh b/run.sh
+new file mode 100755
+index 0000000..587be6b
+--- /dev/null
++++ b/run.sh
+@@ -0,0 +1 @@
++x
+";
+
+    fn context(line: &str) -> DiffLine {
+        DiffLine::Context(line.to_string())
+    }
+
+    #[test]
+    fn parse_renamed() {
+        assert_eq!(
+            super::parse_diff(RENAMED).unwrap(),
+            [FilePatch {
+                old_path: Some("old.txt".to_string()),
+                new_path: Some("new.txt".to_string()),
+                old_mode: Some("100644".to_string()),
+                new_mode: Some("100644".to_string()),
+                hunks: vec![Hunk {
+                    old_start: 1,
+                    old_count: 6,
+                    new_start: 1,
+                    new_count: 6,
+                    heading: String::new(),
+                    lines: vec![
+                        context("one"),
+                        context("two"),
+                        DiffLine::Deleted("three".to_string()),
+                        DiffLine::Added("THREE".to_string()),
+                        context("four"),
+                        context("five"),
+                        context("six"),
+                    ],
+                }],
+                binary: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn parse_binary() {
+        let files = super::parse_diff(BINARY).unwrap();
+        assert_eq!(files.len(), 1);
+        assert!(files[0].binary);
+        assert!(files[0].hunks.is_empty());
+        assert_eq!(files[0].new_path.as_deref(), Some("img.bin"));
+    }
+
+    #[test]
+    fn parse_no_newline() {
+        let files = super::parse_diff(NO_NEWLINE).unwrap();
+        let hunk = &files[0].hunks[0];
+        assert_eq!(hunk.heading, " heading");
+        assert_eq!(
+            hunk.lines,
+            [
+                context("a"),
+                DiffLine::Deleted("b".to_string()),
+                DiffLine::NoNewline,
+                DiffLine::Added("c".to_string()),
+                DiffLine::NoNewline,
+            ]
+        );
+        // Displaying the hunk gives back what was parsed.
+        assert_eq!(
+            format!("{hunk}\n"),
+            NO_NEWLINE
+                .split_once("@@")
+                .map(|(_, h)| format!("@@{h}"))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_crlf() {
+        let files = super::parse_diff(CRLF).unwrap();
+        let hunk = &files[0].hunks[0];
+        assert_eq!(hunk.lines[0], context("  b;\r"));
+        assert_eq!(hunk.lines[5], DiffLine::Added("tail".to_string()));
+        assert_eq!(
+            format!("{hunk}\n"),
+            CRLF.split_once("@@")
+                .map(|(_, h)| format!("@@{h}"))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_new_file_and_mode_change() {
+        let files = super::parse_diff(NEW_FILE_AND_MODE_CHANGE).unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].old_mode.as_deref(), Some("100644"));
+        assert_eq!(files[0].new_mode.as_deref(), Some("100755"));
+        assert!(files[0].hunks.is_empty());
+        assert_eq!(files[1].old_path, None);
+        assert_eq!(files[1].new_path.as_deref(), Some("run.sh"));
+        assert_eq!(files[1].new_mode.as_deref(), Some("100755"));
+        assert_eq!(
+            files[1].hunks[0],
+            Hunk {
+                old_start: 0,
+                old_count: 0,
+                new_start: 1,
+                new_count: 1,
+                heading: String::new(),
+                lines: vec![DiffLine::Added("x".to_string())],
+            }
+        );
+    }
+
+    const QUOTED: &str = r#"diff --git "a/caf\303\251.txt" "b/caf\303\251.txt"
+index 7898192..6178079 100644
+--- "a/caf\303\251.txt"
++++ "b/caf\303\251.txt"
+@@ -1 +1 @@
+-a
++b
+diff --git "a/say \"hi\".txt" "b/say \"hi\".txt"
+deleted file mode 100644
+index 587be6b..0000000
+--- "a/say \"hi\".txt"	
++++ /dev/null
+@@ -1 +0,0 @@
+-x
+diff --git "a/caf\303\251 b/x.txt" "b/\303\251 \"q\".txt"
+similarity index 100%
+rename from "caf\303\251 b/x.txt"
+rename to "\303\251 \"q\".txt"
+diff --git a/plain b/x.txt "b/tab\tx.txt"
+old mode 100644
+new mode 100755
+"#;
+
+    #[test]
+    fn parse_quoted_paths() {
+        let files = super::parse_diff(QUOTED).unwrap();
+        let paths = files
+            .iter()
+            .map(|f| (f.old_path.as_deref(), f.new_path.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            [
+                (Some("café.txt"), Some("café.txt")),
+                (Some("say \"hi\".txt"), None),
+                (Some("café b/x.txt"), Some("é \"q\".txt")),
+                (Some("plain b/x.txt"), Some("tab\tx.txt")),
+            ]
+        );
+        assert_eq!(files[0].hunks.len(), 1);
+    }
+
+    const COMBINED: &str = "diff --cc both.txt
+index 7898192,6178079..0000000
+--- a/both.txt
++++ b/both.txt
+@@@ -1,1 -1,1 +1,5 @@@
+++<<<<<<< HEAD
+ +ours
+++=======
++ theirs
+++>>>>>>> other
+diff --git a/a.txt b/a.txt
+index 7898192..6178079 100644
+--- a/a.txt
++++ b/a.txt
+@@ -1 +1 @@
+-a
++b
+";
+
+    #[test]
+    fn parse_skips_combined_diff() {
+        let files = super::parse_diff(COMBINED).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path(), "a.txt");
+        assert_eq!(files[0].hunks[0].lines.len(), 2);
+    }
+
+    #[test_case("@@ -305,6 +305,7 @@ fn main() {", Some("305,6"), Some("305,7") ; "with context")]
+    #[test_case("@@ -1 +1,2 @@", Some("1"), Some("1,2") ; "single line")]
+    #[test_case("@@ garbage", None, None ; "malformed")]
+    fn parse_hunk_header(header: &str, old: Option<&str>, new: Option<&str>) {
+        assert_eq!(super::parse_hunk_old(header).ok(), old);
+        assert_eq!(super::parse_hunk_new(header).ok(), new);
+    }
+
+    #[test_case("", Some((0, 0)) ; "in sync")]
+    #[test_case("[ahead 3]", Some((3, 0)) ; "ahead")]
+    #[test_case("[behind 12]", Some((0, 12)) ; "behind")]
+    #[test_case("[ahead 1, behind 2]", Some((1, 2)) ; "diverged")]
+    #[test_case("[gone]", None ; "gone")]
+    fn parse_upstream_track(track: &str, expected: Option<(usize, usize)>) {
+        assert_eq!(super::parse_upstream_track(track).unwrap(), expected);
+    }
+
+    #[test]
+    fn parse_malformed_upstream_track() {
+        assert!(super::parse_upstream_track("[ahead lots]").is_err());
+    }
 }
@@ -0,0 +1,254 @@
+//! Module relating to the list of remotes.
+
+use std::{fmt, rc::Rc, sync::atomic::Ordering};
+
+use anyhow::{Context, Result};
+use crossterm::{
+    cursor,
+    event::{KeyCode, KeyEvent},
+    style::{Attribute, SetForegroundColor},
+};
+
+use crate::{
+    config::{Action, CONFIG},
+    git_process,
+    minibuffer::{MessageType, MiniBuffer},
+    pager::Pager,
+    remote_git,
+    render::{self, Renderer, ResetAttributes},
+    status, View,
+};
+
+/// A remote from `git remote -v`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Remote {
+    pub name: String,
+    pub fetch_url: String,
+    pub push_url: String,
+}
+
+/// Parse the output of `git remote -v`, which has a line for the fetch URL and one for the push URL
+/// of each remote. The name is separated from the URL by a tab, e.g.
+/// `origin\thttps://github.com/Piturnah/gex (fetch)`.
+fn parse_remotes(output: &str) -> Result<Vec<Remote>> {
+    let mut remotes: Vec<Remote> = Vec::new();
+    for line in output.lines() {
+        let Some((name, rest)) = line.split_once('\t') else {
+            anyhow::bail!("unexpected line in `git remote -v` output: `{line}`");
+        };
+        let (url, kind) = rest
+            .rsplit_once(' ')
+            .with_context(|| format!("unexpected line in `git remote -v` output: `{line}`"))?;
+        let remote = match remotes.last_mut() {
+            Some(remote) if remote.name == name => remote,
+            _ => {
+                remotes.push(Remote {
+                    name: name.to_string(),
+                    fetch_url: String::new(),
+                    push_url: String::new(),
+                });
+                remotes.last_mut().expect("a remote was just pushed")
+            }
+        };
+        match kind {
+            "(fetch)" => url.clone_into(&mut remote.fetch_url),
+            "(push)" => url.clone_into(&mut remote.push_url),
+            _ => anyhow::bail!("unexpected line in `git remote -v` output: `{line}`"),
+        }
+    }
+    Ok(remotes)
+}
+
+/// The remotes of the repository.
+#[derive(Debug, Default)]
+pub struct RemoteList {
+    pub remotes: Vec<Remote>,
+    /// The index of the selected remote.
+    pub cursor: usize,
+    /// The output of `git remote show` for the selected remote, if it's being shown.
+    show: Pager,
+}
+
+impl render::Render for RemoteList {
+    fn render(&self, f: &mut Renderer) -> fmt::Result {
+        use fmt::Write;
+        let config = CONFIG.get().expect("config wasn't initialised");
+
+        if self.show.is_open() {
+            return self.show.render(f);
+        }
+
+        if self.remotes.is_empty() {
+            return write!(
+                f,
+                "{}No remotes.{}\r\n\nPress a to add one.",
+                SetForegroundColor(config.colors.heading),
+                SetForegroundColor(config.colors.foreground),
+            );
+        }
+
+        let name_width = self
+            .remotes
+            .iter()
+            .map(|remote| remote.name.chars().count())
+            .max()
+            .unwrap_or_default();
+        for (i, remote) in self.remotes.iter().enumerate() {
+            if i == self.cursor {
+                f.insert_cursor();
+                write!(f, "{}", Attribute::Reverse)?;
+            }
+            write!(
+                f,
+                "\r{}{:name_width$}{ResetAttributes} ",
+                SetForegroundColor(config.colors.heading),
+                remote.name,
+            )?;
+            // The push URL is usually the same, in which case it's only shown once.
+            if remote.fetch_url == remote.push_url {
+                writeln!(f, "{}", remote.fetch_url)?;
+            } else {
+                writeln!(
+                    f,
+                    "{} {}(fetch){ResetAttributes}\r\n{:name_width$} {} {}(push){ResetAttributes}",
+                    remote.fetch_url,
+                    Attribute::Dim,
+                    "",
+                    remote.push_url,
+                    Attribute::Dim,
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl RemoteList {
+    pub fn fetch(&mut self) -> Result<()> {
+        self.show.close();
+
+        let output = git_process(&["remote", "-v"])?;
+        self.remotes = parse_remotes(
+            std::str::from_utf8(&output.stdout).context("broken stdout from `git remote -v`")?,
+        )?;
+        self.cursor = self.cursor.min(self.remotes.len().saturating_sub(1));
+        Ok(())
+    }
+
+    /// Handle a key press. Returns `true` when the user wants to leave the remote list.
+    pub fn handle_input(
+        &mut self,
+        key_event: KeyEvent,
+        action: Option<&Action>,
+        minibuffer: &mut MiniBuffer,
+        view: &mut View,
+    ) -> Result<bool> {
+        if self.show.handle_input(key_event, action) {
+            return Ok(false);
+        }
+
+        let last_remote = self.remotes.len().saturating_sub(1);
+        match action {
+            Some(Action::MoveDown) => self.cursor = (self.cursor + 1).min(last_remote),
+            Some(Action::MoveUp) => self.cursor = self.cursor.saturating_sub(1),
+            Some(Action::GotoTop) => self.cursor = 0,
+            Some(Action::GotoBottom) => self.cursor = last_remote,
+            _ => {}
+        }
+        if matches!(key_event.code, KeyCode::Esc | KeyCode::Char('q')) {
+            return Ok(true);
+        }
+        // The list may have been opened from the status or the branch list, which it goes back to.
+        let return_view = view.clone();
+        if key_event.code == KeyCode::Char('a') {
+            minibuffer.get_input(
+                Rc::new(|input| {
+                    print!("{}", cursor::Hide);
+                    let Some(input) = input else {
+                        return Ok(());
+                    };
+                    let mut words = input.split_whitespace();
+                    let (Some(name), Some(url), None) = (words.next(), words.next(), words.next())
+                    else {
+                        MiniBuffer::push(
+                            "Enter the name of the remote followed by its URL.",
+                            MessageType::Error,
+                        );
+                        return Ok(());
+                    };
+                    MiniBuffer::push_command_output(&git_process(&["remote", "add", name, url])?);
+                    status::REFRESH_FLAG.store(true, Ordering::Release);
+                    Ok(())
+                }),
+                Some("Name and URL of the new remote: "),
+                view,
+                return_view,
+            );
+            return Ok(false);
+        }
+
+        let Some(remote) = self.remotes.get(self.cursor) else {
+            return Ok(false);
+        };
+        match key_event.code {
+            KeyCode::Enter => {
+                // This asks the remote for its branches.
+                let output = remote_git(&["remote", "show", &remote.name])
+                    .output()
+                    .context("failed to run `git remote show`")?;
+                if !output.status.success() {
+                    MiniBuffer::push_command_output(&output);
+                    return Ok(false);
+                }
+                self.show.open(&output.stdout, false);
+            }
+            KeyCode::Char('d') => {
+                let name = remote.name.clone();
+                minibuffer.confirm(
+                    Rc::new(move || {
+                        MiniBuffer::push_command_output(&git_process(&[
+                            "remote", "remove", &name,
+                        ])?);
+                        status::REFRESH_FLAG.store(true, Ordering::Release);
+                        Ok(())
+                    }),
+                    &format!("Remove remote {}?", remote.name),
+                    view,
+                    return_view,
+                );
+            }
+            _ => {}
+        }
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Remote;
+
+    #[test]
+    fn parse_remotes() {
+        const INPUT: &str = "\
+origin\thttps://github.com/Piturnah/gex (fetch)
+origin\tgit@github.com:Piturnah/gex.git (push)
+upstream\t/home/user/gex upstream (fetch)
+upstream\t/home/user/gex upstream (push)
+";
+        assert_eq!(
+            super::parse_remotes(INPUT).unwrap(),
+            [
+                Remote {
+                    name: "origin".to_string(),
+                    fetch_url: "https://github.com/Piturnah/gex".to_string(),
+                    push_url: "git@github.com:Piturnah/gex.git".to_string(),
+                },
+                Remote {
+                    name: "upstream".to_string(),
+                    fetch_url: "/home/user/gex upstream".to_string(),
+                    push_url: "/home/user/gex upstream".to_string(),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,420 @@
+//! A grid of the cells on the screen, so that only the cells that changed since the previous frame
+//! have to be drawn again instead of clearing the screen and drawing everything.
+
+use std::{fmt::Write, ops::Range};
+
+use crossterm::{
+    cursor::MoveTo,
+    style::{Attribute, Attributes, Color, SetAttribute, SetBackgroundColor, SetForegroundColor},
+};
+
+use crate::render::{plain, ResetAttributes};
+
+/// The symbol of the cell to the right of a wide character, which the wide character covers.
+const CONTINUATION: char = '\0';
+
+/// Changed cells with at most this many unchanged cells between them are drawn together, since
+/// moving the cursor over the unchanged cells takes about as many bytes as drawing them.
+const MERGE_GAP: usize = 4;
+
+/// A single character on the screen and how it's styled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub symbol: char,
+    pub foreground: Color,
+    pub background: Color,
+    pub attributes: Attributes,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            symbol: ' ',
+            foreground: Color::Reset,
+            background: Color::Reset,
+            attributes: Attributes::default(),
+        }
+    }
+}
+
+impl Cell {
+    fn same_style(&self, other: &Self) -> bool {
+        (self.foreground, self.background, self.attributes)
+            == (other.foreground, other.background, other.attributes)
+    }
+
+    /// Update the style with the parameters of an SGR escape sequence, e.g. `1;38;5;28`.
+    fn apply_sgr(&mut self, params: &vte::Params) {
+        let mut params = params.iter();
+        while let Some(param) = params.next() {
+            // Extended colours can be given either as sub-parameters (`38:5:28`) or as the
+            // parameters that follow (`38;5;28`).
+            let mut color = |param: &[u16]| {
+                let mut rest = param[1..].to_vec();
+                let wanted = match rest.first() {
+                    Some(5) => 2,
+                    Some(2) => 4,
+                    _ if rest.is_empty() => match params.next() {
+                        Some(&[5]) => {
+                            rest.push(5);
+                            2
+                        }
+                        Some(&[2]) => {
+                            rest.push(2);
+                            4
+                        }
+                        _ => return None,
+                    },
+                    _ => return None,
+                };
+                while rest.len() < wanted {
+                    rest.push(params.next()?.first().copied()?);
+                }
+                let byte = |i: usize| u8::try_from(rest[i]).unwrap_or(u8::MAX);
+                Some(match rest[0] {
+                    5 => Color::AnsiValue(byte(1)),
+                    _ => Color::Rgb {
+                        r: byte(1),
+                        g: byte(2),
+                        b: byte(3),
+                    },
+                })
+            };
+            let ansi = |n: u16| Color::AnsiValue(u8::try_from(n).unwrap_or_default());
+            match param[0] {
+                0 => *self = Self::default(),
+                1 => self.attributes.set(Attribute::Bold),
+                2 => self.attributes.set(Attribute::Dim),
+                3 => self.attributes.set(Attribute::Italic),
+                4 => self.attributes.set(Attribute::Underlined),
+                5 => self.attributes.set(Attribute::SlowBlink),
+                7 => self.attributes.set(Attribute::Reverse),
+                8 => self.attributes.set(Attribute::Hidden),
+                9 => self.attributes.set(Attribute::CrossedOut),
+                22 => {
+                    self.attributes.unset(Attribute::Bold);
+                    self.attributes.unset(Attribute::Dim);
+                }
+                23 => self.attributes.unset(Attribute::Italic),
+                24 => self.attributes.unset(Attribute::Underlined),
+                25 => self.attributes.unset(Attribute::SlowBlink),
+                27 => self.attributes.unset(Attribute::Reverse),
+                28 => self.attributes.unset(Attribute::Hidden),
+                29 => self.attributes.unset(Attribute::CrossedOut),
+                n @ 30..=37 => self.foreground = ansi(n - 30),
+                38 => {
+                    if let Some(color) = color(param) {
+                        self.foreground = color;
+                    }
+                }
+                39 => self.foreground = Color::Reset,
+                n @ 40..=47 => self.background = ansi(n - 40),
+                48 => {
+                    if let Some(color) = color(param) {
+                        self.background = color;
+                    }
+                }
+                49 => self.background = Color::Reset,
+                n @ 90..=97 => self.foreground = ansi(n - 90 + 8),
+                n @ 100..=107 => self.background = ansi(n - 100 + 8),
+                _ => {}
+            }
+        }
+    }
+
+    /// Write the escape sequences that switch the terminal to this style.
+    fn write_style(&self, out: &mut String) {
+        let _ = write!(out, "{ResetAttributes}");
+        if self.foreground != Color::Reset {
+            let _ = write!(out, "{}", SetForegroundColor(self.foreground));
+        }
+        if self.background != Color::Reset {
+            let _ = write!(out, "{}", SetBackgroundColor(self.background));
+        }
+        for attribute in Attribute::iterator().filter(|&a| self.attributes.has(a)) {
+            let _ = write!(out, "{}", SetAttribute(attribute));
+        }
+    }
+}
+
+/// The number of columns that `c` takes up on the screen. Wide characters take up two columns and
+/// combining characters none, since they're drawn over the character before them. These are only
+/// the common ranges, and anything else is assumed to take up one column.
+const fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x0300..=0x036F | 0x200B..=0x200F | 0x20D0..=0x20FF | 0xFE00..=0xFE0F => 0,
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+/// The cells of the screen, which text is written to the same way it would be printed to the
+/// terminal.
+#[derive(Debug, Default)]
+pub struct Frame {
+    width: usize,
+    rows: Vec<Vec<Cell>>,
+    /// Rows that something else has been drawn over, so they have to be drawn in full next time.
+    stale: Vec<bool>,
+    /// Whether text that doesn't fit on its row carries on on the next one, like in the terminal,
+    /// or is cut off.
+    wrap: bool,
+    /// The style that text is written in, which is changed by the escape sequences in it.
+    pen: Cell,
+}
+
+/// Writes the characters parsed from text to a row of a [`Frame`].
+struct Writer<'a> {
+    frame: &'a mut Frame,
+    row: usize,
+    column: usize,
+}
+
+impl vte::Perform for Writer<'_> {
+    fn print(&mut self, c: char) {
+        let width = char_width(c);
+        if width == 0 {
+            return;
+        }
+        if self.column + width > self.frame.width {
+            if !self.frame.wrap {
+                return;
+            }
+            self.row += 1;
+            self.column = 0;
+        }
+        let Some(row) = self.frame.rows.get_mut(self.row) else {
+            return;
+        };
+        // Half of a wide character that is drawn over is left blank.
+        if row
+            .get(self.column)
+            .is_some_and(|cell| cell.symbol == CONTINUATION)
+        {
+            row[self.column - 1].symbol = ' ';
+        }
+        let end = self.column + width;
+        if row.get(end).is_some_and(|cell| cell.symbol == CONTINUATION) {
+            row[end].symbol = ' ';
+        }
+        row[self.column] = Cell {
+            symbol: c,
+            ..self.frame.pen
+        };
+        if width == 2 {
+            row[self.column + 1] = Cell {
+                symbol: CONTINUATION,
+                ..self.frame.pen
+            };
+        }
+        self.column = end;
+    }
+
+    fn execute(&mut self, byte: u8) {
+        match byte {
+            b'\r' => self.column = 0,
+            b'\t' => self.column = ((self.column / 8 + 1) * 8).min(self.frame.width),
+            _ => {}
+        }
+    }
+
+    fn csi_dispatch(
+        &mut self,
+        params: &vte::Params,
+        intermediates: &[u8],
+        _ignore: bool,
+        action: char,
+    ) {
+        if action == 'm' && intermediates.is_empty() {
+            self.frame.pen.apply_sgr(params);
+        }
+    }
+}
+
+impl Frame {
+    pub fn new(width: usize, height: usize, wrap: bool) -> Self {
+        Self {
+            width,
+            rows: vec![vec![Cell::default(); width]; height],
+            stale: vec![false; height],
+            wrap,
+            pen: Cell::default(),
+        }
+    }
+
+    /// Write `text` starting at the beginning of `row`. The style it ends in carries on to the
+    /// next text that is written.
+    pub fn write(&mut self, row: usize, text: &str) {
+        let mut parser = vte::Parser::new();
+        let mut writer = Writer {
+            frame: self,
+            row,
+            column: 0,
+        };
+        for byte in text.bytes() {
+            parser.advance(&mut writer, byte);
+        }
+    }
+
+    /// Mark `rows` as having been drawn over by something else.
+    pub fn invalidate(&mut self, rows: Range<usize>) {
+        let end = rows.end.min(self.stale.len());
+        if let Some(stale) = self.stale.get_mut(rows.start.min(end)..end) {
+            stale.fill(true);
+        }
+    }
+
+    /// The runs of cells that are different from `previous`, as the row and the columns.
+    fn changes(&self, previous: &Self) -> Vec<(usize, Range<usize>)> {
+        let mut changes = Vec::new();
+        for (i, row) in self.rows.iter().enumerate() {
+            let previous_row = previous
+                .rows
+                .get(i)
+                .filter(|_| previous.width == self.width && !previous.stale[i]);
+            let Some(previous_row) = previous_row else {
+                changes.push((i, 0..self.width));
+                continue;
+            };
+            let mut run: Option<Range<usize>> = None;
+            for column in (0..self.width).filter(|&c| row[c] != previous_row[c]) {
+                match &mut run {
+                    Some(run) if column - run.end <= MERGE_GAP => run.end = column + 1,
+                    _ => {
+                        changes.extend(run.take().map(|run| (i, run)));
+                        run = Some(column..column + 1);
+                    }
+                }
+            }
+            changes.extend(run.map(|run| (i, run)));
+        }
+        // Wide characters have to be drawn whole.
+        for (i, columns) in &mut changes {
+            let row = &self.rows[*i];
+            if row[columns.start].symbol == CONTINUATION {
+                columns.start -= 1;
+            }
+            if row
+                .get(columns.end)
+                .is_some_and(|c| c.symbol == CONTINUATION)
+            {
+                columns.end += 1;
+            }
+        }
+        changes
+    }
+
+    /// The output that changes the screen from showing `previous` to showing this frame.
+    pub fn draw(&self, previous: &Self) -> String {
+        let mut out = String::new();
+        let mut style: Option<&Cell> = None;
+        for (row, columns) in self.changes(previous) {
+            let _ = write!(out, "{}", MoveTo(columns.start as u16, row as u16));
+            for cell in &self.rows[row][columns] {
+                if cell.symbol == CONTINUATION {
+                    continue;
+                }
+                if !plain() && !style.is_some_and(|style| style.same_style(cell)) {
+                    cell.write_style(&mut out);
+                    style = Some(cell);
+                }
+                out.push(cell.symbol);
+            }
+        }
+        if style.is_some() {
+            let _ = write!(out, "{ResetAttributes}");
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::style::{Attribute, Color};
+    use test_case::test_case;
+
+    use super::{Cell, Frame, CONTINUATION};
+
+    fn symbols(frame: &Frame) -> Vec<String> {
+        frame
+            .rows
+            .iter()
+            .map(|row| row.iter().map(|cell| cell.symbol).collect())
+            .collect()
+    }
+
+    #[test_case(false, &["abcd", "    "] ; "cut off")]
+    #[test_case(true, &["abcd", "ef  "] ; "wrapped")]
+    fn write_long_line(wrap: bool, expected: &[&str]) {
+        let mut frame = Frame::new(4, 2, wrap);
+        frame.write(0, "\rabcdef");
+        assert_eq!(symbols(&frame), expected);
+    }
+
+    #[test]
+    fn write_styles() {
+        let mut frame = Frame::new(4, 1, false);
+        frame.write(0, "\x1b[1m\x1b[38;5;28ma\x1b[22mb\x1b[0mc");
+        let [a, b, c, _] = frame.rows[0][..] else {
+            panic!("the row should have four cells");
+        };
+        assert_eq!(a.foreground, Color::AnsiValue(28));
+        assert!(a.attributes.has(Attribute::Bold));
+        assert_eq!(b.foreground, Color::AnsiValue(28));
+        assert!(!b.attributes.has(Attribute::Bold));
+        assert_eq!(
+            c,
+            Cell {
+                symbol: 'c',
+                ..Cell::default()
+            }
+        );
+    }
+
+    #[test]
+    fn write_wide() {
+        let mut frame = Frame::new(5, 1, false);
+        frame.write(0, "a日本");
+        assert_eq!(
+            symbols(&frame),
+            [format!("a日{CONTINUATION}本{CONTINUATION}")]
+        );
+        // Drawing over half of a wide character blanks the other half.
+        frame.write(0, "ab");
+        assert_eq!(symbols(&frame), [format!("ab 本{CONTINUATION}")]);
+    }
+
+    #[test_case("abcdefghijkl", &[] ; "unchanged")]
+    #[test_case("aXcdefghijkl", &[(0, 1..2)] ; "one cell")]
+    #[test_case("aXcdeYghijkl", &[(0, 1..6)] ; "close together")]
+    #[test_case("aXcdefghijYl", &[(0, 1..2), (0, 10..11)] ; "far apart")]
+    #[test_case("a日defghijkl", &[(0, 1..3)] ; "wide")]
+    fn changes(new: &str, expected: &[(usize, std::ops::Range<usize>)]) {
+        let mut previous = Frame::new(12, 1, false);
+        previous.write(0, "abcdefghijkl");
+        let mut frame = Frame::new(12, 1, false);
+        frame.write(0, new);
+        assert_eq!(frame.changes(&previous), expected);
+    }
+
+    #[test]
+    fn changes_stale() {
+        let mut previous = Frame::new(3, 2, false);
+        previous.invalidate(1..5);
+        let frame = Frame::new(3, 3, false);
+        assert_eq!(frame.changes(&previous), [(1, 0..3), (2, 0..3)]);
+    }
+}
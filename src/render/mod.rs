@@ -1,5 +1,6 @@
+mod frame;
 mod renderer;
 mod terminal;
 
-pub use renderer::{Render, Renderer};
-pub use terminal::{Clear, ResetAttributes, ResetColor};
+pub use renderer::{Render, Renderer, REDRAW_FLAG};
+pub use terminal::{plain, strip_styles, Clear, ResetAttributes, ResetColor, PLAIN};
@@ -1,8 +1,16 @@
-use std::fmt;
+use std::{
+    fmt,
+    ops::Range,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
-use crossterm::{cursor::MoveTo, terminal::ClearType};
+use crossterm::terminal::ClearType;
 
-use crate::render::{Clear, ResetAttributes};
+use crate::render::{frame::Frame, plain, strip_styles, Clear, ResetAttributes};
+
+/// Set when the screen has been drawn over by something else, e.g. an editor that has been opened,
+/// so the next frame has to be drawn from scratch instead of only the cells that changed.
+pub static REDRAW_FLAG: AtomicBool = AtomicBool::new(false);
 
 /// The [`Renderer`] type contains a buffer to be rendered to the screen. It handles scrolling based
 /// on the cursor's position and will only write the lines that should be visible.
@@ -15,6 +23,23 @@ pub struct Renderer {
     /// show. Can also be thought of as a "target" starting line, which will be updated based on
     /// where the cursor is.
     start_line: usize,
+    /// The indexes of lines that are section headings. The heading of the section at the top of
+    /// the screen is kept visible even once it has been scrolled past.
+    headings: Vec<usize>,
+    /// The lines where clickable items start, along with the item and sub-item they belong to.
+    items: Vec<(usize, (usize, usize))>,
+    /// The clickable items and headings that were shown by the previous call to
+    /// [`Renderer::show_and_clear`], and the buffer line that each row of the screen showed.
+    shown: Shown,
+}
+
+#[derive(Default)]
+struct Shown {
+    items: Vec<(usize, (usize, usize))>,
+    headings: Vec<usize>,
+    rows: Vec<usize>,
+    /// What's on the screen, which the next frame is compared to.
+    frame: Frame,
 }
 
 /// Types implementing [`Render`] can write to the given [`Renderer`] and update its cursor
@@ -35,26 +60,6 @@ impl fmt::Write for Renderer {
     }
 }
 
-/// Truncates a string to the given `length`, ignoring ANSI escape sequences.
-fn truncate_ansi(s: &str, length: usize) -> &str {
-    struct Performer(usize);
-    impl vte::Perform for Performer {
-        fn print(&mut self, _c: char) {
-            self.0 += 1;
-        }
-    }
-    let mut performer = Performer(0);
-    let mut parser = vte::Parser::new();
-    let bytes = s.as_bytes().iter().enumerate();
-    for (i, b) in bytes {
-        parser.advance(&mut performer, *b);
-        if performer.0 > length {
-            return &s[0..i];
-        }
-    }
-    s
-}
-
 impl Renderer {
     /// Insert the cursor at the next line.
     pub fn insert_cursor(&mut self) {
@@ -62,6 +67,41 @@ impl Renderer {
         self.selected_item = (next_line, next_line);
     }
 
+    /// Mark the next line as a section heading.
+    pub fn insert_heading(&mut self) {
+        let next_line = self.buffer.lines().count();
+        self.headings.push(next_line);
+    }
+
+    /// Mark the next line as the start of the clickable item `item`, so that it can later be found
+    /// with [`Renderer::item_at`].
+    pub fn insert_item(&mut self, item: usize) {
+        let next_line = self.buffer.lines().count();
+        self.items.push((next_line, (item, 0)));
+    }
+
+    /// Mark the next line as the start of part `sub_item` of the last item inserted with
+    /// [`Renderer::insert_item`], e.g. a hunk of a file.
+    pub fn insert_sub_item(&mut self, sub_item: usize) {
+        let next_line = self.buffer.lines().count();
+        if let Some(&(_, (item, _))) = self.items.last() {
+            self.items.push((next_line, (item, sub_item)));
+        }
+    }
+
+    /// The item and sub-item displayed at `row` of the screen the last time it was shown, if any.
+    pub fn item_at(&self, row: u16) -> Option<(usize, usize)> {
+        let line = *self.shown.rows.get(usize::from(row))?;
+        let item = self.shown.items.iter().rev().find(|(l, _)| *l <= line)?;
+        // Headings (and the blank line before them) don't belong to the item above them.
+        let heading_between = self
+            .shown
+            .headings
+            .iter()
+            .any(|&h| item.0 < h && h <= line + 1);
+        (!heading_between).then_some(item.1)
+    }
+
     /// Insert after the end of the selected item. For if your "cursor" is more than one line long.
     ///
     /// E.g.
@@ -74,7 +114,31 @@ impl Renderer {
         self.selected_item.1 = self.buffer.lines().count() - 1;
     }
 
-    /// Render to stdout and clear the buffer.
+    /// Print the buffer to stdout line by line, for when it isn't shown in the TUI, and clear it.
+    pub fn print_and_clear(&mut self) {
+        for line in self.buffer.lines() {
+            let line = line.replace('\r', "");
+            if plain() {
+                println!("{}", strip_styles(&line));
+            } else {
+                println!("{line}{ResetAttributes}");
+            }
+        }
+        self.buffer.clear();
+        self.headings.clear();
+        self.items.clear();
+    }
+
+    /// Mark `rows` of the screen as having been drawn over since the buffer was last shown, e.g. by
+    /// an overlay, so that they're drawn in full next time.
+    pub fn invalidate_rows(&mut self, rows: Range<u16>) {
+        self.shown
+            .frame
+            .invalidate(usize::from(rows.start)..usize::from(rows.end));
+    }
+
+    /// Render to stdout and clear the buffer. Only the cells that are different from what was shown
+    /// last time are drawn.
     pub fn show_and_clear(
         &mut self,
         width: usize,
@@ -82,8 +146,6 @@ impl Renderer {
         lookahead: usize,
         truncate: bool,
     ) {
-        print!("{}", Clear(ClearType::All));
-
         let (cursor_start_idx, cursor_end_idx) = self.selected_item;
         let count_lines = self.buffer.lines().count();
 
@@ -107,29 +169,68 @@ impl Renderer {
             self.start_line = count_lines.saturating_sub(height);
         }
 
-        if truncate {
-            for (row, l) in self
-                .buffer
-                .lines()
-                .skip(self.start_line)
-                .take(height)
-                .map(|l| truncate_ansi(l, width))
-                .enumerate()
-            {
-                print!("{}{l}{}", MoveTo(0, row as u16), ResetAttributes);
+        // The heading of the section that has been scrolled into, if any. Pinning it hides the
+        // bottom line, so scroll one further if that would hide the cursor.
+        let pinned_heading = |start_line| {
+            self.headings
+                .iter()
+                .rev()
+                .find(|&&h| h < start_line)
+                .copied()
+        };
+        let mut heading = pinned_heading(self.start_line);
+        if heading.is_some()
+            && cursor_end_idx + 1 >= self.start_line + height
+            && cursor_start_idx > self.start_line
+        {
+            self.start_line += 1;
+            heading = pinned_heading(self.start_line);
+        }
+
+        self.shown.rows = heading
+            .into_iter()
+            .chain(self.start_line..count_lines)
+            .take(height)
+            .collect();
+        let lines = heading
+            .and_then(|h| self.buffer.lines().nth(h))
+            .into_iter()
+            .chain(self.buffer.lines().skip(self.start_line))
+            .take(height);
+        // Lines that are too long are cut off at the edge of the frame unless they should wrap.
+        let mut frame = Frame::new(width, height, !truncate);
+        if plain() {
+            // Without reverse video the cursor is marked at the start of its line instead. Carriage
+            // returns would move back over the marker, and each line starts in the first column
+            // anyway.
+            for (row, l) in lines.enumerate() {
+                let marker = if self.shown.rows[row] == cursor_start_idx {
+                    '>'
+                } else {
+                    ' '
+                };
+                frame.write(
+                    row,
+                    &format!("{marker}{}", strip_styles(l).replace('\r', "")),
+                );
+            }
+        } else if truncate {
+            for (row, l) in lines.enumerate() {
+                frame.write(row, &format!("{l}{ResetAttributes}"));
             }
         } else {
-            for (row, l) in self
-                .buffer
-                .lines()
-                .skip(self.start_line)
-                .take(height)
-                .enumerate()
-            {
-                print!("{}{l}", MoveTo(0, row as u16));
+            for (row, l) in lines.enumerate() {
+                frame.write(row, l);
             }
-            print!("{ResetAttributes}");
         }
+        if REDRAW_FLAG.swap(false, Ordering::Acquire) {
+            print!("{}", Clear(ClearType::All));
+            self.shown.frame = Frame::default();
+        }
+        print!("{}", frame.draw(&self.shown.frame));
+        self.shown.frame = frame;
+        self.shown.headings = std::mem::take(&mut self.headings);
+        self.shown.items = std::mem::take(&mut self.items);
         self.buffer.clear();
     }
 }
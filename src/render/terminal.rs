@@ -3,7 +3,10 @@
 //! This exists because when resetting the terminal colours or clearing the screen we may have to
 //! handle the case where the user has set custom FG/BG colours specially.
 
-use std::fmt;
+use std::{
+    fmt,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
 use crossterm::{
     cursor,
@@ -13,12 +16,43 @@ use crossterm::{
 
 use crate::config;
 
+/// Set when colours and other text attributes shouldn't be used at all, either because of the
+/// `--no-color` flag or because `NO_COLOR` is set.
+pub static PLAIN: AtomicBool = AtomicBool::new(false);
+
+/// Whether colours and other text attributes are turned off, see [`PLAIN`].
+pub fn plain() -> bool {
+    PLAIN.load(Ordering::Relaxed)
+}
+
+/// `s` without the escape sequences that set colours and text attributes. Other escape sequences,
+/// like those moving the cursor, are kept.
+pub fn strip_styles(s: &str) -> String {
+    let mut stripped = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find("\x1b[") {
+        stripped.push_str(&rest[..start]);
+        let sequence = &rest[start..];
+        // The parameters are followed by a single final byte, which is `m` for styles.
+        let end = sequence[2..]
+            .find(|c: char| !c.is_ascii_digit() && c != ';' && c != ':')
+            .map_or(sequence.len(), |i| i + 3);
+        if !sequence[..end].ends_with('m') {
+            stripped.push_str(&sequence[..end]);
+        }
+        rest = &sequence[end..];
+    }
+    stripped.push_str(rest);
+    stripped
+}
+
 /// See [`Clear`](`crossterm::terminal::Clear`).
 pub struct Clear(pub ClearType);
 
 impl fmt::Display for Clear {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match config!().colors.background {
+            _ if plain() => write!(f, "{}", crossterm::terminal::Clear(self.0)),
             Color::Reset => write!(f, "{}", crossterm::terminal::Clear(self.0)),
             color => {
                 let Ok((cols, rows)) = terminal::size() else {
@@ -97,6 +131,9 @@ pub struct ResetColor;
 
 impl fmt::Display for ResetColor {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if plain() {
+            return Ok(());
+        }
         write!(
             f,
             "{}{}",
@@ -113,6 +150,23 @@ pub struct ResetAttributes;
 
 impl fmt::Display for ResetAttributes {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}{}", crossterm::style::Attribute::Reset, ResetColor,)
+        if plain() {
+            return Ok(());
+        }
+        write!(f, "{}{}", crossterm::style::Attribute::Reset, ResetColor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    #[test_case("plain", "plain" ; "no escapes")]
+    #[test_case("\x1b[1mbold\x1b[22m", "bold" ; "attribute")]
+    #[test_case("\x1b[38;5;28m+added\x1b[39m", "+added" ; "ansi colour")]
+    #[test_case("\x1b[38;2;137;180;250mblue", "blue" ; "rgb colour")]
+    #[test_case("\x1b[2;5Hmoved", "\x1b[2;5Hmoved" ; "cursor movement")]
+    fn strip_styles(input: &str, expected: &str) {
+        assert_eq!(super::strip_styles(input), expected);
     }
 }
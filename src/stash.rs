@@ -0,0 +1,252 @@
+//! Module relating to the list of stashes.
+
+use std::{fmt, rc::Rc, sync::atomic::Ordering};
+
+use anyhow::{Context, Result};
+use crossterm::{
+    cursor,
+    event::{KeyCode, KeyEvent},
+    style::{Attribute, SetForegroundColor},
+};
+
+use crate::{
+    config::{Action, CONFIG},
+    git_process,
+    minibuffer::MiniBuffer,
+    pager::Pager,
+    render::{self, Renderer, ResetAttributes},
+    status, View,
+};
+
+/// A single stash from `git stash list`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct StashEntry {
+    /// The name of the stash, e.g. `stash@{0}`.
+    pub name: String,
+    /// The branch the stash was made on.
+    pub branch: Option<String>,
+    pub message: String,
+}
+
+impl StashEntry {
+    /// Parse a line of `git stash list`, e.g. `stash@{0}: On main: message`. Stashes made without a
+    /// message are described as `WIP on main: abc1234 subject` instead.
+    fn parse(line: &str) -> Result<Self> {
+        let (name, description) = line
+            .split_once(": ")
+            .with_context(|| format!("unexpected line in `git stash list` output: `{line}`"))?;
+        let (branch, message) = match description.split_once(": ") {
+            Some((on, message)) => (
+                on.strip_prefix("On ")
+                    .or_else(|| on.strip_prefix("WIP on "))
+                    .map(str::to_string),
+                message,
+            ),
+            None => (None, description),
+        };
+        Ok(Self {
+            name: name.to_string(),
+            branch,
+            message: message.to_string(),
+        })
+    }
+}
+
+/// The stashes in the repository, most recent first.
+#[derive(Debug, Default)]
+pub struct StashList {
+    pub entries: Vec<StashEntry>,
+    /// The index of the selected stash.
+    pub cursor: usize,
+    /// The diff of the selected stash, if it's being shown.
+    show: Pager,
+}
+
+impl render::Render for StashList {
+    fn render(&self, f: &mut Renderer) -> fmt::Result {
+        use fmt::Write;
+        let config = CONFIG.get().expect("config wasn't initialised");
+
+        if self.show.is_open() {
+            return self.show.render(f);
+        }
+
+        if self.entries.is_empty() {
+            return write!(
+                f,
+                "{}No stashes.{}\r\n\nPress n to stash the current changes.",
+                SetForegroundColor(config.colors.heading),
+                SetForegroundColor(config.colors.foreground),
+            );
+        }
+
+        for (i, entry) in self.entries.iter().enumerate() {
+            if i == self.cursor {
+                f.insert_cursor();
+            }
+            write!(
+                f,
+                "\r{}{}{} ",
+                SetForegroundColor(config.colors.hunk_head),
+                entry.name,
+                SetForegroundColor(config.colors.foreground),
+            )?;
+            if let Some(branch) = &entry.branch {
+                write!(
+                    f,
+                    "{}({branch}){} ",
+                    SetForegroundColor(config.colors.heading),
+                    SetForegroundColor(config.colors.foreground),
+                )?;
+            }
+            if i == self.cursor {
+                writeln!(
+                    f,
+                    "{}{}{ResetAttributes}",
+                    Attribute::Reverse,
+                    entry.message
+                )?;
+            } else {
+                writeln!(f, "{}", entry.message)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl StashList {
+    pub fn fetch(&mut self) -> Result<()> {
+        self.show.close();
+
+        let output = git_process(&["stash", "list"])?;
+        self.entries = std::str::from_utf8(&output.stdout)
+            .context("broken stdout from `git stash list`")?
+            .lines()
+            .map(StashEntry::parse)
+            .collect::<Result<_>>()?;
+        self.cursor = self.cursor.min(self.entries.len().saturating_sub(1));
+        Ok(())
+    }
+
+    /// Handle a key press. Returns `true` when the user wants to leave the stash list.
+    pub fn handle_input(
+        &mut self,
+        key_event: KeyEvent,
+        action: Option<&Action>,
+        minibuffer: &mut MiniBuffer,
+        view: &mut View,
+    ) -> Result<bool> {
+        if self.show.handle_input(key_event, action) {
+            return Ok(false);
+        }
+
+        let last_entry = self.entries.len().saturating_sub(1);
+        match action {
+            Some(Action::MoveDown) => self.cursor = (self.cursor + 1).min(last_entry),
+            Some(Action::MoveUp) => self.cursor = self.cursor.saturating_sub(1),
+            Some(Action::GotoTop) => self.cursor = 0,
+            Some(Action::GotoBottom) => self.cursor = last_entry,
+            _ => {}
+        }
+        if matches!(key_event.code, KeyCode::Esc | KeyCode::Char('q')) {
+            return Ok(true);
+        }
+        if key_event.code == KeyCode::Char('n') {
+            minibuffer.get_input(
+                Rc::new(|input| {
+                    print!("{}", cursor::Hide);
+                    if let Some(message) = input {
+                        let mut args = vec!["stash", "push"];
+                        if !message.trim().is_empty() {
+                            args.extend(["--message", message]);
+                        }
+                        MiniBuffer::push_command_output(&git_process(&args)?);
+                        status::REFRESH_FLAG.store(true, Ordering::Release);
+                    }
+                    Ok(())
+                }),
+                Some("Stash message: "),
+                view,
+                View::StashList,
+            );
+            return Ok(false);
+        }
+
+        let Some(entry) = self.entries.get(self.cursor) else {
+            return Ok(false);
+        };
+        match key_event.code {
+            KeyCode::Enter => {
+                let output = git_process(&[
+                    "-c",
+                    "core.quotePath=false",
+                    "stash",
+                    "show",
+                    "--patch",
+                    "--no-ext-diff",
+                    "--no-color",
+                    &entry.name,
+                ])?;
+                self.show.open(&output.stdout, true);
+            }
+            KeyCode::Char(c @ ('z' | 'a')) => {
+                let subcommand = if c == 'z' { "pop" } else { "apply" };
+                MiniBuffer::push_command_output(&git_process(&["stash", subcommand, &entry.name])?);
+                status::REFRESH_FLAG.store(true, Ordering::Release);
+            }
+            KeyCode::Char('d') => {
+                let name = entry.name.clone();
+                minibuffer.confirm(
+                    Rc::new(move || {
+                        MiniBuffer::push_command_output(&git_process(&["stash", "drop", &name])?);
+                        status::REFRESH_FLAG.store(true, Ordering::Release);
+                        Ok(())
+                    }),
+                    &format!("Drop {}?", entry.name),
+                    view,
+                    View::StashList,
+                );
+            }
+            _ => {}
+        }
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::StashEntry;
+
+    #[test_case(
+        "stash@{0}: On main: half-finished config parsing",
+        &StashEntry {
+            name: "stash@{0}".to_string(),
+            branch: Some("main".to_string()),
+            message: "half-finished config parsing".to_string(),
+        } ;
+        "with message"
+    )]
+    #[test_case(
+        "stash@{12}: WIP on feature/log: c5597d9 Add an inline commit message editor",
+        &StashEntry {
+            name: "stash@{12}".to_string(),
+            branch: Some("feature/log".to_string()),
+            message: "c5597d9 Add an inline commit message editor".to_string(),
+        } ;
+        "without message"
+    )]
+    #[test_case(
+        "stash@{1}: autostash",
+        &StashEntry {
+            name: "stash@{1}".to_string(),
+            branch: None,
+            message: "autostash".to_string(),
+        } ;
+        "autostash"
+    )]
+    fn parse_stash_entry(input: &str, expected: &StashEntry) {
+        assert_eq!(&StashEntry::parse(input).unwrap(), expected);
+    }
+}
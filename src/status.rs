@@ -2,118 +2,318 @@
 
 use std::{
     borrow::Cow,
+    cell::OnceCell,
+    collections::HashMap,
     fmt, fs,
     io::{stdout, Read, Write},
+    ops::Range,
+    path::Path,
     process::{Command, Output, Stdio},
-    sync::atomic::AtomicBool,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError},
+    },
+    thread,
+    time::{Duration, Instant},
 };
 
 use anyhow::{anyhow, Context, Error, Result};
 use crossterm::{
     cursor,
-    style::{self, Attribute},
+    event::{self, KeyCode, KeyEvent},
+    style::{self, Attribute, Color},
     terminal,
 };
-use git2::{ErrorCode::UnbornBranch, Repository};
-use nom::{bytes::complete::take_until, IResult};
 
 use crate::{
+    command,
     config::{Config, Options, CONFIG},
+    diff::{self, DiffLine},
+    enable_mouse_capture,
+    error::GexError,
     git_process,
+    highlight::{self, Language},
+    hunk,
     minibuffer::{MessageType, MiniBuffer},
-    parse::{self, parse_hunk_new, parse_hunk_old},
+    parse::{self, parse_hunk_new, parse_hunk_old, StatusEntry, SubmoduleState},
+    remote_git,
     render::{self, Renderer, ResetAttributes, ResetColor},
+    watch, View,
 };
 
 pub static REFRESH_FLAG: AtomicBool = AtomicBool::new(false);
 
 pub trait Expand {
     fn toggle_expand(&mut self);
-    fn expanded(&self) -> bool;
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 enum DiffType {
     Modified,
     Created,
     Untracked,
     Renamed,
+    Copied,
     Deleted,
+    Conflicted(Conflict),
+    Submodule(SubmoduleState),
+}
+
+impl DiffType {
+    /// Get the kind of change from an `XY` status code of `git status --porcelain=v2`. Returns
+    /// `None` for `.`, which means unmodified.
+    fn from_status_code(code: char) -> Result<Option<Self>> {
+        match code {
+            '.' => Ok(None),
+            // A typechange (e.g. regular file to symlink) is displayed just like a modification.
+            'M' | 'T' => Ok(Some(Self::Modified)),
+            'A' => Ok(Some(Self::Created)),
+            'R' => Ok(Some(Self::Renamed)),
+            'C' => Ok(Some(Self::Copied)),
+            'D' => Ok(Some(Self::Deleted)),
+            _ => Err(anyhow!(
+                "unknown status code in `git status` output: `{code}`"
+            )),
+        }
+    }
+}
+
+/// The kind of merge conflict of an unmerged path.
+#[derive(Debug, Clone, Copy)]
+pub enum Conflict {
+    BothModified,
+    BothAdded,
+    BothDeleted,
+    AddedByUs,
+    AddedByThem,
+    DeletedByUs,
+    DeletedByThem,
+}
+
+impl Conflict {
+    /// Get the kind of conflict from the `XY` status code of an unmerged path.
+    fn from_status_codes(index: char, worktree: char) -> Result<Self> {
+        match (index, worktree) {
+            ('U', 'U') => Ok(Self::BothModified),
+            ('A', 'A') => Ok(Self::BothAdded),
+            ('D', 'D') => Ok(Self::BothDeleted),
+            ('A', 'U') => Ok(Self::AddedByUs),
+            ('U', 'A') => Ok(Self::AddedByThem),
+            ('D', 'U') => Ok(Self::DeletedByUs),
+            ('U', 'D') => Ok(Self::DeletedByThem),
+            _ => Err(anyhow!(
+                "unknown conflict in `git status` output: `{index}{worktree}`"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::BothModified => "both modified",
+            Self::BothAdded => "both added",
+            Self::BothDeleted => "both deleted",
+            Self::AddedByUs => "added by us",
+            Self::AddedByThem => "added by them",
+            Self::DeletedByUs => "deleted by us",
+            Self::DeletedByThem => "deleted by them",
+        })
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Hunk {
     diff: String,
     expanded: bool,
+    /// The header and lines of the hunk as they're displayed. Highlighting them is slow, so it's
+    /// done once when the diff is fetched rather than every time the status is drawn.
+    header: String,
+    lines: Vec<String>,
 }
 
 impl fmt::Display for Hunk {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        use fmt::Write;
         let config = CONFIG.get().expect("config wasn't initialised");
-
-        let mut lines = self.diff.lines();
-        let Some(head) = lines.next() else {
-            return Ok(());
-        };
-        let mut outbuf = format!(
+        write!(
+            f,
             "{}{}{}",
             style::SetForegroundColor(config.colors.hunk_head),
             if self.expanded { "⌄" } else { "›" },
-            head.replace(" @@", &format!(" @@{ResetAttributes}"))
-        );
-
+            self.header
+        )?;
         if self.expanded {
-            let ws_error_highlight = CONFIG
-                .get()
-                .expect("config is initialised at the start of the program")
-                .options
-                .ws_error_highlight;
-            for line in lines {
-                match line.chars().next() {
-                    Some('+') => write!(
-                        &mut outbuf,
-                        "\r\n{}{}",
-                        style::SetForegroundColor(config.colors.addition),
-                        if ws_error_highlight.new {
-                            format_trailing_whitespace(line, config)
-                        } else {
-                            Cow::Borrowed(line)
-                        }
-                    ),
-                    Some('-') => write!(
-                        &mut outbuf,
-                        "\r\n{}{}",
-                        style::SetForegroundColor(config.colors.deletion),
-                        if ws_error_highlight.old {
-                            format_trailing_whitespace(line, config)
-                        } else {
-                            Cow::Borrowed(line)
-                        }
-                    ),
-                    Some(c) => write!(
-                        &mut outbuf,
-                        "\r\n{}{c}{}",
-                        style::SetForegroundColor(config.colors.foreground),
-                        if ws_error_highlight.context {
-                            format_trailing_whitespace(&line[1..], config)
-                        } else {
-                            Cow::Borrowed(&line[1..])
-                        }
-                    ),
-                    // I think this case never happens, but if it does, it just means the line was
-                    // empty.
-                    None => {
-                        outbuf.push('\n');
-                        Ok(())
-                    }
-                }?;
+            for line in &self.lines {
+                write!(f, "\r\n{line}")?;
             }
         }
-        write!(f, "{outbuf}")
+        Ok(())
     }
 }
 
+/// How many bytes at the start of an untracked file to check for NUL bytes when deciding if it's
+/// binary.
+const BINARY_CHECK_BYTES: usize = 8000;
+/// The maximum number of lines of an untracked file to display when it's expanded.
+const MAX_UNTRACKED_LINES: usize = 1000;
+/// How much of an untracked file is read to display it, which is plenty for
+/// [`MAX_UNTRACKED_LINES`] lines.
+const MAX_UNTRACKED_BYTES: u64 = 256 * 1024;
+
+/// The frames of the animation shown while fetching, pulling, pushing or loading the status, and
+/// how long each is shown for.
+const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+const SPINNER_FRAME_TIME: Duration = Duration::from_millis(100);
+
+/// Whether hunks `a` and `b` are the same hunk in different versions of the diff, i.e. either the
+/// old or the new side of their headers match.
+fn same_hunk(a: &str, b: &str) -> Result<bool> {
+    let a = a.lines().next().context("empty hunk")?;
+    let b = b.lines().next().context("empty hunk")?;
+    Ok(parse_hunk_new(a)? == parse_hunk_new(b)? || parse_hunk_old(a)? == parse_hunk_old(b)?)
+}
+
+/// Whether a cherry-pick has stopped, e.g. because of conflicts, and is waiting to be continued.
+pub fn cherry_pick_in_progress() -> Result<bool> {
+    let output = git_process(&["rev-parse", "--git-path", "CHERRY_PICK_HEAD"])?;
+    let path = std::str::from_utf8(&output.stdout).context("invalid utf8 from `git rev-parse`")?;
+    Ok(Path::new(path.trim()).exists())
+}
+
+/// Whether a merge has stopped because of conflicts, and is waiting to be continued.
+pub fn merge_in_progress() -> Result<bool> {
+    let output = GexError::run("rev-parse", &["rev-parse", "--git-path", "MERGE_HEAD"])?;
+    let path = std::str::from_utf8(&output.stdout).context("invalid utf8 from `git rev-parse`")?;
+    Ok(Path::new(path.trim()).exists())
+}
+
+/// Where `needle` first appears in `haystack`, ignoring case. An empty `needle` isn't anywhere.
+fn find_ignoring_case(haystack: &str, needle: &str) -> Option<Range<usize>> {
+    if needle.is_empty() {
+        return None;
+    }
+    haystack.char_indices().find_map(|(start, _)| {
+        let mut end = start;
+        let mut chars = haystack[start..].chars();
+        for n in needle.chars() {
+            let c = chars.next()?;
+            if !c.to_lowercase().eq(n.to_lowercase()) {
+                return None;
+            }
+            end += c.len_utf8();
+        }
+        Some(start..end)
+    })
+}
+
+/// Open the file at `path` in the editor from the config, at line `row` if the editor is known to
+/// support that.
+pub fn edit_file(path: &str, row: usize) -> Result<()> {
+    let editor = &crate::config!().options.editor;
+    // If your editor isn't supported yet for opening at a specific line, please add the
+    // implementation below!
+    let args = match (editor.as_str(), row) {
+        (_, 1) => vec![path.to_string()],
+        ("nvim" | "vim" | "vi" | "nano", _) => vec![format!("+{row}"), path.to_string()],
+        ("hx", _) => vec![format!("{}:{row}", path)],
+        _ => vec![path.to_string()],
+    };
+
+    crossterm::execute!(
+        stdout(),
+        event::DisableMouseCapture,
+        terminal::LeaveAlternateScreen
+    )
+    .context("failed to leave alternate screen")?;
+    // The TUI has to come back even if the editor couldn't be opened, to show the error.
+    let output = Command::new(editor)
+        .args(&args)
+        .stdout(Stdio::inherit())
+        .stdin(Stdio::inherit())
+        .output()
+        .with_context(|| format!("failed to open editor `{editor}`"));
+    crossterm::execute!(stdout(), terminal::EnterAlternateScreen, cursor::Hide)
+        .context("failed to enter alternate screen")?;
+    enable_mouse_capture()?;
+    render::REDRAW_FLAG.store(true, Ordering::Release);
+    output.map(drop)
+}
+
+/// Describe the rebase that is in progress the same way as `git status`, if there is one.
+pub fn rebase_in_progress() -> Result<Option<String>> {
+    let output = GexError::run(
+        "rev-parse",
+        &[
+            "rev-parse",
+            "--git-path",
+            "rebase-merge",
+            "--git-path",
+            "rebase-apply",
+        ],
+    )?;
+    let paths = std::str::from_utf8(&output.stdout).context("invalid utf8 from `git rev-parse`")?;
+    // `rebase-merge` is used by interactive rebases and `rebase-apply` by the ones using `git am`.
+    let Some((dir, interactive)) = paths
+        .lines()
+        .map(Path::new)
+        .zip([true, false])
+        .find(|(dir, _)| dir.is_dir())
+    else {
+        return Ok(None);
+    };
+    let interactive = interactive && dir.join("interactive").exists();
+    let onto = fs::read_to_string(dir.join("onto")).unwrap_or_default();
+    let onto = GexError::run("rev-parse", &["rev-parse", "--short", onto.trim()])?;
+    Ok(Some(format!(
+        "{}rebase in progress; onto {}",
+        if interactive { "interactive " } else { "" },
+        String::from_utf8_lossy(&onto.stdout).trim()
+    )))
+}
+
+/// Summarise the changes in `patches` like `git diff --shortstat`, e.g. `3 files changed, 47
+/// insertions(+), 12 deletions(-)`, or nothing if there aren't any. Unmerged files aren't counted,
+/// since they aren't parsed.
+fn diff_stat(patches: &[parse::FilePatch]) -> String {
+    if patches.is_empty() {
+        return String::new();
+    }
+    let lines = patches.iter().flat_map(|p| &p.hunks).flat_map(|h| &h.lines);
+    let insertions = lines
+        .clone()
+        .filter(|l| matches!(l, DiffLine::Added(_)))
+        .count();
+    let deletions = lines.filter(|l| matches!(l, DiffLine::Deleted(_))).count();
+    let plural =
+        |n: usize, one: &str, many: &str| format!("{n} {}", if n == 1 { one } else { many });
+    let mut stat = vec![plural(patches.len(), "file changed", "files changed")];
+    // Like git, a count of zero is only given when both are zero.
+    if insertions > 0 || deletions == 0 {
+        stat.push(plural(insertions, "insertion(+)", "insertions(+)"));
+    }
+    if deletions > 0 || insertions == 0 {
+        stat.push(plural(deletions, "deletion(-)", "deletions(-)"));
+    }
+    stat.join(", ")
+}
+
+/// Format a size in bytes to be human readable, e.g. `4.2 MiB`.
+#[allow(clippy::cast_precision_loss)]
+fn human_size(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
 fn format_trailing_whitespace<'s>(s: &'s str, config: &'_ Config) -> Cow<'s, str> {
     let count_trailing_whitespace = s
         .bytes()
@@ -134,9 +334,88 @@ fn format_trailing_whitespace<'s>(s: &'s str, config: &'_ Config) -> Cow<'s, str
     }
 }
 
+/// Highlight the syntax of `line` if it's enabled and the language is known, and its trailing
+/// whitespace if `ws_error_highlight` is set.
+fn format_line(
+    line: &str,
+    language: Option<Language>,
+    ws_error_highlight: bool,
+    config: &Config,
+) -> String {
+    let line = match language {
+        Some(language) if config.options.syntax_highlighting => {
+            Cow::Owned(highlight::highlight(line, language))
+        }
+        _ => Cow::Borrowed(line),
+    };
+    if ws_error_highlight {
+        format_trailing_whitespace(&line, config).into_owned()
+    } else {
+        line.into_owned()
+    }
+}
+
 impl Hunk {
-    pub const fn new(diff: String, expanded: bool) -> Self {
-        Self { diff, expanded }
+    pub fn new(diff: String, expanded: bool, language: Option<Language>) -> Self {
+        let config = CONFIG.get().expect("config wasn't initialised");
+        let ws_error_highlight = config.options.ws_error_highlight;
+        let mut lines = diff.lines();
+        let header = lines
+            .next()
+            .unwrap_or_default()
+            .replace(" @@", &format!(" @@{ResetAttributes}"));
+        let lines = lines.collect::<Vec<_>>();
+        // Parsing the lines is only needed for word diffs.
+        let words = if diff::WORD_DIFF.load(Ordering::Acquire) {
+            lines
+                .iter()
+                .map(|line| DiffLine::parse(line))
+                .collect::<Result<Vec<_>>>()
+                .map(|lines| diff::highlight_words(&lines))
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        let lines = lines
+            .into_iter()
+            .enumerate()
+            .map(|(i, line)| {
+                match (line.chars().next(), words.get(i).and_then(Option::as_ref)) {
+                    (Some(c @ ('+' | '-')), Some(words)) => format!(
+                        "{}{c}{words}",
+                        style::SetForegroundColor(if c == '+' {
+                            config.colors.addition
+                        } else {
+                            config.colors.deletion
+                        }),
+                    ),
+                    (Some('+'), _) => format!(
+                        "{}+{}",
+                        style::SetForegroundColor(config.colors.addition),
+                        format_line(&line[1..], language, ws_error_highlight.new, config)
+                    ),
+                    (Some('-'), _) => format!(
+                        "{}-{}",
+                        style::SetForegroundColor(config.colors.deletion),
+                        format_line(&line[1..], language, ws_error_highlight.old, config)
+                    ),
+                    (Some(c), _) => format!(
+                        "{}{c}{}",
+                        style::SetForegroundColor(config.colors.foreground),
+                        format_line(&line[1..], language, ws_error_highlight.context, config)
+                    ),
+                    // I think this case never happens, but if it does, it just means the line was
+                    // empty.
+                    (None, _) => String::new(),
+                }
+            })
+            .collect();
+        Self {
+            diff,
+            expanded,
+            header,
+            lines,
+        }
     }
 }
 
@@ -144,15 +423,13 @@ impl Expand for Hunk {
     fn toggle_expand(&mut self) {
         self.expanded = !self.expanded;
     }
-
-    fn expanded(&self) -> bool {
-        self.expanded
-    }
 }
 
 #[derive(Debug)]
 pub struct FileDiff {
     path: String,
+    /// The path the file was renamed or copied from.
+    orig_path: Option<String>,
     expanded: bool,
     hunks: Vec<Hunk>,
     cursor: usize,
@@ -160,47 +437,115 @@ pub struct FileDiff {
     // The implementation here involving this `selected` field is awful and hacky and I can't wait
     // to refactor it out.
     selected: bool,
+    /// Whether the file is marked to be staged or unstaged along with the other marked files.
+    marked: bool,
+    /// The commit recorded in HEAD, for a submodule.
+    head_oid: Option<String>,
+    /// The bytes of `path` that match the search, if it does.
+    search_match: Option<Range<usize>>,
+    /// The contents of an untracked or conflicted file as they're displayed, which are read when
+    /// it's first expanded.
+    content: OnceCell<String>,
 }
 
-impl render::Render for FileDiff {
-    fn render(&self, f: &mut Renderer) -> fmt::Result {
+impl FileDiff {
+    /// Render the file and its hunks. `background` is the colour behind the file's line, which the
+    /// part of the path that matches the search stands out from.
+    fn render(&self, f: &mut Renderer, background: Color) -> fmt::Result {
         use fmt::Write;
         let config = CONFIG.get().expect("config wasn't initialised");
+        let path = self.search_match.as_ref().map_or_else(
+            || self.path.clone(),
+            |range| {
+                // Without colours the match is underlined instead.
+                let (start, end) = if config.colors.search == Color::Reset {
+                    (
+                        Attribute::Underlined.to_string(),
+                        Attribute::NoUnderline.to_string(),
+                    )
+                } else {
+                    (
+                        style::SetBackgroundColor(config.colors.search).to_string(),
+                        style::SetBackgroundColor(background).to_string(),
+                    )
+                };
+                format!(
+                    "{}{start}{}{end}{}",
+                    &self.path[..range.start],
+                    &self.path[range.clone()],
+                    &self.path[range.end..]
+                )
+            },
+        );
+        write!(f, "\r{}", if self.expanded { "⌄" } else { "›" })?;
+        match self.kind {
+            DiffType::Conflicted(conflict) => write!(
+                f,
+                "{}[{conflict}]{} ",
+                style::SetForegroundColor(config.colors.error),
+                style::SetForegroundColor(config.colors.foreground)
+            )?,
+            DiffType::Deleted => write!(
+                f,
+                "{}[DELETE]{} ",
+                style::SetForegroundColor(config.colors.deletion),
+                style::SetForegroundColor(config.colors.foreground)
+            )?,
+            _ => {}
+        }
         write!(
             f,
-            "\r{}{}{}{ResetAttributes}",
-            if self.expanded { "⌄" } else { "›" },
+            "{}{}{}{ResetAttributes}",
             match self.kind {
                 DiffType::Renamed => "[RENAME] ",
-                DiffType::Deleted => "[DELETE] ",
+                DiffType::Copied => "[COPY] ",
                 _ => "",
             },
-            self.path,
+            self.orig_path
+                .as_ref()
+                .map_or_else(String::new, |orig_path| format!("{orig_path} → ")),
+            path,
         )?;
-        if self.expanded {
-            if self.hunks.is_empty() {
-                if let Ok(file_content) = fs::read_to_string(&self.path) {
-                    let ws_error_highlight = config.options.ws_error_highlight;
-
-                    write!(f, "{ResetAttributes}")?;
-                    for l in file_content.lines() {
-                        write!(
-                            f,
-                            "\r\n{}+{l}",
-                            style::SetForegroundColor(config.colors.addition),
-                            l = if ws_error_highlight.new {
-                                format_trailing_whitespace(l, config)
-                            } else {
-                                Cow::Borrowed(l)
-                            }
-                        )?;
-                    }
-                    if self.selected {
-                        f.insert_item_end();
-                    }
-                }
+        if let DiffType::Submodule(state) = self.kind {
+            // The changes are in the submodule itself, so there's nothing to expand.
+            if let Some(head_oid) = &self.head_oid {
+                write!(f, " {}{head_oid:.7}{ResetAttributes}", Attribute::Dim)?;
+            }
+            let changes = [
+                (SubmoduleState::NEW_COMMITS, "new commits"),
+                (SubmoduleState::MODIFIED, "modified content"),
+                (SubmoduleState::UNTRACKED, "untracked content"),
+                (SubmoduleState::STAGED, "staged"),
+                (SubmoduleState::CONFLICTED, "conflict"),
+            ]
+            .into_iter()
+            .filter(|&(flag, _)| state.contains(flag))
+            .map(|(_, change)| change)
+            .collect::<Vec<_>>();
+            if !changes.is_empty() {
+                write!(
+                    f,
+                    " {}({}){}",
+                    style::SetForegroundColor(config.colors.heading),
+                    changes.join(", "),
+                    style::SetForegroundColor(config.colors.foreground)
+                )?;
+            }
+        } else if self.expanded {
+            // Untracked files have no diff to show, so we display the whole file as new. Conflicted
+            // files are displayed as they are so that the conflict markers can be seen. Tracked
+            // files without any hunks (e.g. mode changes) don't have any content to show.
+            if matches!(self.kind, DiffType::Untracked | DiffType::Conflicted(_)) {
+                self.render_file_content(f)?;
+            } else if self.hunks.is_empty() {
+                write!(
+                    f,
+                    "{ResetAttributes}\r\n{}(no textual changes){ResetAttributes}",
+                    Attribute::Dim
+                )?;
             } else {
                 for (i, hunk) in self.hunks.iter().enumerate() {
+                    f.insert_sub_item(i + 1);
                     if self.selected && i + 1 == self.cursor {
                         f.insert_cursor();
                         write!(f, "{ResetAttributes}\r\n{}{hunk}", Attribute::Reverse)?;
@@ -213,46 +558,116 @@ impl render::Render for FileDiff {
         }
         Ok(())
     }
-}
 
-impl FileDiff {
+    /// Render the contents of the file in the worktree, for when there's no diff to show.
+    fn render_file_content(&self, f: &mut Renderer) -> fmt::Result {
+        use fmt::Write;
+        let content = self
+            .content
+            .get_or_init(|| self.file_content().unwrap_or_default());
+        write!(f, "{ResetAttributes}{content}")?;
+        if self.selected {
+            f.insert_item_end();
+        }
+        Ok(())
+    }
+
+    /// The contents of the file in the worktree as they're displayed. Only as much of the file is
+    /// read as can be shown.
+    fn file_content(&self) -> Result<String> {
+        use fmt::Write;
+        let config = CONFIG.get().expect("config wasn't initialised");
+        let file = fs::File::open(&self.path)?;
+        let size = file.metadata()?.len();
+        let mut file_content = Vec::new();
+        file.take(MAX_UNTRACKED_BYTES)
+            .read_to_end(&mut file_content)?;
+        let ws_error_highlight = config.options.ws_error_highlight;
+        let language = Language::from_path(&self.path);
+
+        let mut content = String::new();
+        // Same heuristic as git: a NUL byte near the start means it's binary.
+        if file_content
+            .iter()
+            .take(BINARY_CHECK_BYTES)
+            .any(|&b| b == 0)
+        {
+            write!(
+                content,
+                "\r\n{}<binary file, {}>{ResetAttributes}",
+                Attribute::Dim,
+                human_size(usize::try_from(size).unwrap_or(usize::MAX))
+            )?;
+            return Ok(content);
+        }
+        // The rest of the file isn't known, so the line it was cut off in is left out.
+        let truncated = size > file_content.len() as u64;
+        if truncated {
+            let end = file_content.iter().rposition(|&b| b == b'\n').unwrap_or(0);
+            file_content.truncate(end);
+        }
+        let file_content = String::from_utf8_lossy(&file_content);
+        let mut lines = file_content.lines();
+        for l in lines.by_ref().take(MAX_UNTRACKED_LINES) {
+            // Control characters could mess up the terminal.
+            let l = l.replace(|c: char| c.is_control() && c != '\t', "\u{fffd}");
+            if matches!(self.kind, DiffType::Conflicted(_)) {
+                let is_marker = ["<<<<<<<", "|||||||", "=======", ">>>>>>>"]
+                    .iter()
+                    .any(|marker| l.starts_with(marker));
+                if is_marker {
+                    write!(
+                        content,
+                        "\r\n{}{l}{}",
+                        style::SetForegroundColor(config.colors.hunk_head),
+                        style::SetForegroundColor(config.colors.foreground)
+                    )?;
+                } else {
+                    write!(content, "\r\n{l}")?;
+                }
+                continue;
+            }
+            write!(
+                content,
+                "\r\n{}+{}",
+                style::SetForegroundColor(config.colors.addition),
+                format_line(&l, language, ws_error_highlight.new, config)
+            )?;
+        }
+        let remaining = lines.count();
+        if truncated {
+            write!(
+                content,
+                "\r\n{}... more lines{ResetAttributes}",
+                Attribute::Dim
+            )?;
+        } else if remaining > 0 {
+            write!(
+                content,
+                "\r\n{}... {remaining} more lines{ResetAttributes}",
+                Attribute::Dim
+            )?;
+        }
+        Ok(content)
+    }
+
     fn new(path: &str, kind: DiffType, expanded: bool, cursor: usize) -> Self {
         Self {
             path: path.to_string(),
+            orig_path: None,
             hunks: Vec::new(),
             selected: false,
+            marked: false,
+            head_oid: None,
+            search_match: None,
+            content: OnceCell::new(),
             kind,
             expanded,
             cursor,
         }
     }
 
-    /// Fails on the case that we are already on the first hunk
-    fn up(&mut self) -> Result<(), ()> {
-        self.cursor = self.cursor.checked_sub(1).ok_or(())?;
-        Ok(())
-    }
-
-    /// Fails on the case that we are already on the final hunk
-    fn down(&mut self) -> Result<(), ()> {
-        if self.cursor + 1 >= self.len() {
-            return Err(());
-        }
-        self.cursor += 1;
-        Ok(())
-    }
-
-    /// Move the cursor to the topmost element of this `FileDiff`.
-    fn cursor_first(&mut self) {
-        self.cursor = 0;
-    }
-
-    /// Move the cursor to the last element of this `FileDiff`, if it is expanded.
-    fn cursor_last(&mut self) {
-        self.cursor = self.len() - 1;
-    }
-
-    fn len(&self) -> usize {
+    const fn len(&self) -> usize {
         if self.expanded {
             self.hunks.len() + 1
         } else {
@@ -265,10 +680,36 @@ impl Expand for FileDiff {
     fn toggle_expand(&mut self) {
         self.expanded = !self.expanded;
     }
+}
 
-    fn expanded(&self) -> bool {
-        self.expanded
-    }
+/// The sections of the status view that a [`FileDiff`] can belong to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    Conflicted,
+    Untracked,
+    Unstaged,
+    Staged,
+    Submodules,
+    Ignored,
+}
+
+/// The sections in the order they are displayed.
+const SECTIONS: [Section; 6] = [
+    Section::Conflicted,
+    Section::Untracked,
+    Section::Unstaged,
+    Section::Staged,
+    Section::Submodules,
+    Section::Ignored,
+];
+
+/// A place in the status view that the cursor can be at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Position {
+    Heading(Section),
+    File(usize),
+    /// A hunk of a file, numbered from 1.
+    Hunk(usize, usize),
 }
 
 // Enum for `Status.stage_or_unstage`
@@ -276,34 +717,381 @@ impl Expand for FileDiff {
 enum Stage {
     Add,
     Reset,
+    Discard,
+}
+
+/// Run `command` on the file at `path`. If `hunk` is 0 then the whole file is affected, otherwise
+/// only the `hunk`th hunk of the file's diff. `orig_path` is the path a renamed file was moved
+/// from, which has to be unstaged along with it.
+fn patch(
+    command: Stage,
+    path: &str,
+    orig_path: Option<&str>,
+    kind: DiffType,
+    hunk: usize,
+) -> Result<()> {
+    if hunk == 0 {
+        let args = match command {
+            Stage::Add => vec!["add", "--", path],
+            Stage::Reset => match (kind, orig_path) {
+                (DiffType::Deleted, _) => vec!["reset", "-q", "HEAD", "--", path],
+                (DiffType::Renamed, Some(orig_path)) => vec!["reset", "-q", "--", orig_path, path],
+                _ => vec!["reset", "-q", "--", path],
+            },
+            Stage::Discard => vec!["restore", "--", path],
+        };
+        MiniBuffer::push_command_output(&git_process(&args)?);
+        return Ok(());
+    }
+
+    let mut child = Command::new("git")
+        .args(match command {
+            Stage::Add => ["add", "-p", "--", path],
+            Stage::Reset => ["reset", "-p", "--", path],
+            Stage::Discard => ["checkout", "-p", "--", path],
+        })
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("failed to spawn interactive git process")?;
+
+    let mut stdin = child.stdin.take().context("failed to open child stdin")?;
+
+    let mut bufs = vec![b"n\n"; hunk - 1];
+    bufs.push(b"y\n");
+
+    std::thread::spawn(move || {
+        for buf in bufs {
+            stdin.write_all(buf).context("failed to patch hunk")?;
+        }
+        Ok::<_, Error>(())
+    })
+    .join()
+    .unwrap()
+    .context("failed to patch hunk")?;
+
+    let mut stderr_buf = String::new();
+    child
+        .stderr
+        // If I understand correctly, reading to EOF should have the added effect
+        // waiting on the child process to finish.
+        .map(|mut stderr| stderr.read_to_string(&mut stderr_buf))
+        .context("failed to read stderr of child process")??;
+    MiniBuffer::push(&stderr_buf, MessageType::Error);
+    Ok(())
+}
+
+/// Check out the commit recorded for the submodule at `path` with `git submodule update --init`,
+/// cloning it first if it hasn't been yet.
+fn update_submodule(path: &str) -> Result<()> {
+    let output = remote_git(&["submodule", "update", "--init", "--", path])
+        .output()
+        .context("failed to run `git submodule update`")?;
+    MiniBuffer::push_command_output(&output);
+    Ok(())
+}
+
+/// The rule of an ignore file, e.g. `.gitignore`, that matches a path.
+#[derive(Debug, PartialEq, Eq)]
+pub struct IgnoreRule {
+    /// The file that the rule is in.
+    pub source: String,
+    pub line: usize,
+    /// The pattern of the rule, which starts with `!` if it stops paths being ignored.
+    pub pattern: String,
+}
+
+impl fmt::Display for IgnoreRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.source, self.line, self.pattern)
+    }
+}
+
+/// Parse the output of `git check-ignore -v -z`, which has the source, line number, pattern and
+/// path of each match, all NUL-terminated.
+fn parse_check_ignore(output: &str) -> Result<HashMap<String, IgnoreRule>> {
+    let mut rules = HashMap::new();
+    let mut fields = output.split_terminator('\0');
+    while let Some(source) = fields.next() {
+        let (Some(line), Some(pattern), Some(path)) = (fields.next(), fields.next(), fields.next())
+        else {
+            anyhow::bail!("unexpected end of `git check-ignore` output after `{source}`");
+        };
+        let line = line.parse().with_context(|| {
+            format!("invalid line number in `git check-ignore` output: `{line}`")
+        })?;
+        rules.insert(
+            path.to_string(),
+            IgnoreRule {
+                source: source.to_string(),
+                line,
+                pattern: pattern.to_string(),
+            },
+        );
+    }
+    Ok(rules)
 }
 
+/// Find the rules of the ignore files that match `paths`. Untracked paths can still match a rule
+/// that stops them being ignored, e.g. `!keep.log`.
+fn ignore_rules(paths: &[&str]) -> Result<HashMap<String, IgnoreRule>> {
+    if paths.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let mut child = Command::new("git")
+        .args(["check-ignore", "-v", "-z", "--stdin"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("failed to run `git check-ignore`")?;
+    let mut stdin = child.stdin.take().context("failed to open child stdin")?;
+    let input = paths
+        .iter()
+        .fold(String::new(), |acc, path| acc + path + "\0");
+    // The output is read while the paths are written, so that neither pipe can fill up and block.
+    let writer = thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child.wait_with_output();
+    // It exits with 1 when none of the paths are ignored.
+    let output = match output {
+        Ok(output) if output.status.code() == Some(1) => output,
+        output => GexError::check("check-ignore", output)?,
+    };
+    writer
+        .join()
+        .map_err(|_| anyhow!("failed to write to `git check-ignore`"))?
+        .context("failed to write to `git check-ignore`")?;
+    parse_check_ignore(
+        std::str::from_utf8(&output.stdout).context("invalid utf8 from `git check-ignore`")?,
+    )
+}
+
+/// The output of the git commands that the status is made from. They can take a while in a large
+/// repository, so they're run on another thread.
+#[derive(Debug)]
+struct StatusOutput {
+    status: Output,
+    unstaged_diff: Output,
+    staged_diff: Output,
+    rebase: Option<String>,
+    merge: bool,
+    head: String,
+    /// The rules that match the untracked and ignored paths.
+    ignore_rules: HashMap<String, IgnoreRule>,
+}
+
+impl StatusOutput {
+    fn run() -> Result<Self> {
+        // Ignored directories are listed as a whole rather than every file inside them.
+        let status = GexError::run(
+            "status",
+            &[
+                "status",
+                "--porcelain=v2",
+                "--branch",
+                "--ignored=matching",
+                "-z",
+            ],
+        )?;
+        let untracked_or_ignored = parse::parse_porcelain_status(
+            std::str::from_utf8(&status.stdout).map_err(|_| GexError::Utf8Error("status"))?,
+        )
+        .map_err(|e| GexError::ParseError(format!("{e:#}")))?
+        .entries
+        .into_iter()
+        .filter_map(|entry| match entry {
+            StatusEntry::Untracked(path) | StatusEntry::Ignored(path) => Some(path),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+        let ignore_rules = ignore_rules(&untracked_or_ignored)?;
+        Ok(Self {
+            status,
+            unstaged_diff: GexError::run(
+                "diff",
+                &["-c", "core.quotePath=false", "diff", "--no-ext-diff"],
+            )?,
+            staged_diff: GexError::run(
+                "diff",
+                &[
+                    "-c",
+                    "core.quotePath=false",
+                    "diff",
+                    "--cached",
+                    "--no-ext-diff",
+                ],
+            )?,
+            rebase: rebase_in_progress()?,
+            merge: merge_in_progress()?,
+            // This fails when there aren't any commits yet, which leaves the head empty.
+            head: std::str::from_utf8(
+                &git_process(&["log", "HEAD", "--pretty=format:%h %s", "-n", "1"])?.stdout,
+            )
+            .map_err(|_| GexError::Utf8Error("log"))?
+            .to_string(),
+            ignore_rules,
+        })
+    }
+}
+
+/// Unstage a single hunk of a staged rename. `git reset -p` doesn't detect renames, so it would
+/// offer to unstage the whole new file instead. Reversing the hunk in the index leaves the rename
+/// in place.
+fn unstage_renamed_hunk(path: &str, hunk: &str) -> Result<()> {
+    hunk::apply_hunk(path, hunk, &["--cached", "--reverse"])
+}
+
+#[allow(clippy::struct_excessive_bools)]
 #[derive(Debug, Default)]
 pub struct Status {
-    pub branch: String,
+    /// The root of the working tree, with the home directory abbreviated to `~`.
+    pub root: String,
+    /// The subdirectory of the working tree that gex was started in, if any.
+    pub subdir: Option<String>,
+    /// Whether the working tree is a linked one from `git worktree add`.
+    pub worktree: bool,
+    /// The current branch, or `None` if HEAD is detached.
+    pub branch: Option<String>,
     pub head: String,
+    /// The upstream of the current branch, if it has one.
+    pub upstream: Option<String>,
+    /// The number of commits the branch is ahead of and behind its upstream, or `None` if there
+    /// isn't one or it no longer exists.
+    pub ahead_behind: Option<(usize, usize)>,
+    /// When the remotes started being fetched in the background, if they are.
+    pub fetching: Option<Instant>,
+    /// When the push running in the background was started, if there is one.
+    pub pushing: Option<Instant>,
+    /// When `git pull --rebase` started running in the background, if it is.
+    pub pulling: Option<Instant>,
+    /// A description of the rebase in progress, if any, e.g. `interactive rebase in progress; onto
+    /// abc1234`.
+    pub rebase: Option<String>,
+    /// Whether a merge is in progress.
+    pub merging: bool,
+    /// The rules of the ignore files that match the untracked and ignored paths, by path.
+    ignore_rules: HashMap<String, IgnoreRule>,
+    /// Whether the status is being fetched again in the background.
+    pub loading: bool,
+    /// When the status started being fetched in the background, and where the result will be sent.
+    refresh: Option<(Instant, Receiver<Result<StatusOutput>>)>,
     pub file_diffs: Vec<FileDiff>,
+    pub count_conflicted: usize,
     pub count_untracked: usize,
     pub count_unstaged: usize,
     pub count_staged: usize,
+    pub count_submodules: usize,
+    pub count_ignored: usize,
+    /// The summary of the unstaged changes, like `git diff --shortstat`, e.g. `3 files changed, 47
+    /// insertions(+), 12 deletions(-)`, which is shown under the heading.
+    unstaged_stat: String,
+    /// The same summary for the staged changes.
+    staged_stat: String,
     pub cursor: usize,
+    /// Whether the cursor is on the heading of the section containing the item at `cursor`, which
+    /// is then the first item of that section.
+    heading_selected: bool,
+    /// Which sections are collapsed, indexed by [`Section`].
+    collapsed: [bool; SECTIONS.len()],
+    /// Where the range of items being marked starts, if one is. Every item between it and the
+    /// cursor is marked.
+    mark_anchor: Option<usize>,
+    /// What's being searched for in the paths of the items. It's kept once the search bar is
+    /// closed, so that the matches can still be jumped between.
+    search: String,
+    /// Whether the keys typed are being added to [`Status::search`].
+    searching: bool,
 }
 
 impl render::Render for Status {
     fn render(&self, f: &mut Renderer) -> Result<(), fmt::Error> {
         use fmt::Write;
         let config = CONFIG.get().expect("config wasn't initialised");
+        write!(f, "\r{}{}", Attribute::Dim, self.root)?;
+        if let Some(subdir) = &self.subdir {
+            write!(f, "  [{subdir}]")?;
+        }
+        if self.worktree {
+            write!(f, "  (worktree)")?;
+        }
+        writeln!(f, "{ResetAttributes}")?;
         // Display the current branch
-        writeln!(
-            f,
-            "\rOn branch {}{}{}",
-            Attribute::Bold,
-            self.branch,
-            ResetAttributes,
-        )?;
+        match &self.branch {
+            // HEAD is detached during a rebase, but what's more useful to know is that it's going
+            // on.
+            None if self.rebase.is_some() => write!(
+                f,
+                "\r{}{}{}",
+                Attribute::Bold,
+                self.rebase.as_deref().unwrap_or_default(),
+                ResetAttributes,
+            )?,
+            Some(branch) => write!(
+                f,
+                "\rOn branch {}{branch}{}",
+                Attribute::Bold,
+                ResetAttributes,
+            )?,
+            None => write!(
+                f,
+                "\r{}HEAD detached at {}{}",
+                Attribute::Bold,
+                self.head.split_whitespace().next().unwrap_or_default(),
+                ResetAttributes,
+            )?,
+        }
+        match (self.ahead_behind, &self.upstream) {
+            (Some((0, 0)), _) => {}
+            (Some((ahead, 0)), _) => write!(f, " ↑{ahead}")?,
+            (Some((0, behind)), _) => write!(f, " ↓{behind}")?,
+            (Some((ahead, behind)), _) => write!(f, " ↑{ahead} ↓{behind}")?,
+            (None, Some(_)) => write!(f, " {}(upstream gone){ResetAttributes}", Attribute::Dim)?,
+            // A detached HEAD can't have an upstream, so there's no need to point it out.
+            (None, None) if self.branch.is_some() => {
+                write!(f, " {}(no upstream){ResetAttributes}", Attribute::Dim)?;
+            }
+            (None, None) => {}
+        }
+        let loading = self
+            .refresh
+            .as_ref()
+            .filter(|_| self.loading)
+            .map(|&(started, _)| started);
+        for (started, activity) in [
+            (self.fetching, "fetching"),
+            (self.pushing, "pushing"),
+            (self.pulling, "pulling"),
+            (loading, "refreshing"),
+        ] {
+            if let Some(started) = started {
+                let frame = started.elapsed().as_millis() / SPINNER_FRAME_TIME.as_millis();
+                write!(
+                    f,
+                    " {}{activity} {}{ResetAttributes}",
+                    Attribute::Dim,
+                    SPINNER[frame as usize % SPINNER.len()]
+                )?;
+            }
+        }
+        writeln!(f)?;
+        if self.merging {
+            write!(
+                f,
+                "\r{}Merge in progress{ResetAttributes} ",
+                Attribute::Bold
+            )?;
+            writeln!(
+                f,
+                "{}(m m to continue, m a to abort){ResetAttributes}",
+                Attribute::Dim
+            )?;
+        }
 
         // Display most recent commit
-        if !self.head.is_empty() {
+        if self.head.is_empty() {
+            writeln!(f, "\r\n{}No commits yet{ResetAttributes}", Attribute::Dim)?;
+        } else {
             let mut head = self.head.split_whitespace();
             writeln!(
                 f,
@@ -328,46 +1116,74 @@ impl render::Render for Status {
             drop(stdout().flush());
         }
 
-        for (index, file) in self.file_diffs.iter().enumerate() {
-            if index == 0 && self.count_untracked != 0 {
-                writeln!(
-                    f,
-                    "\r\n{}Untracked files{} {}({}){}",
-                    style::SetForegroundColor(config.colors.heading),
-                    ResetColor,
-                    style::Attribute::Dim,
-                    self.count_untracked,
-                    ResetAttributes
-                )?;
-            } else if index == self.count_untracked && self.count_unstaged != 0 {
-                writeln!(
-                    f,
-                    "\r\n{}Unstaged changes{} {}({}){}",
-                    style::SetForegroundColor(config.colors.heading),
-                    ResetColor,
-                    style::Attribute::Dim,
-                    self.count_unstaged,
-                    ResetAttributes
-                )?;
-            } else if index == self.count_untracked + self.count_unstaged {
-                writeln!(
-                    f,
-                    "\r\n{}Staged changes{} {}({}){}",
-                    style::SetForegroundColor(config.colors.heading),
-                    ResetColor,
-                    style::Attribute::Dim,
-                    self.count_staged,
-                    ResetAttributes
-                )?;
+        let marked = self.marked();
+        for section in SECTIONS {
+            let (start, len) = self.section_range(section);
+            if len == 0 {
+                continue;
             }
-
-            if file.cursor == 0 && self.cursor == index {
+            let (heading, color) = match section {
+                Section::Conflicted => ("Unmerged paths", config.colors.error),
+                Section::Untracked => ("Untracked files", config.colors.heading),
+                Section::Unstaged => ("Unstaged changes", config.colors.heading),
+                Section::Staged => ("Staged changes", config.colors.heading),
+                Section::Submodules => ("Submodules", config.colors.heading),
+                Section::Ignored => ("Ignored files", config.colors.heading),
+            };
+            let collapsed = self.collapsed[section as usize];
+
+            writeln!(f, "\r")?;
+            f.insert_heading();
+            if self.heading_selected && self.section_of(self.cursor) == section {
                 f.insert_cursor();
                 write!(f, "{}", Attribute::Reverse)?;
             }
-            write!(f, "\r    ")?;
-            file.render(f)?;
-            writeln!(f, "{ResetAttributes}")?;
+            writeln!(
+                f,
+                "\r{}{}{heading}{} {}({len}){}",
+                if collapsed { "›" } else { "⌄" },
+                style::SetForegroundColor(color),
+                ResetColor,
+                style::Attribute::Dim,
+                ResetAttributes
+            )?;
+            let summary = match section {
+                Section::Unstaged => self.unstaged_stat.as_str(),
+                Section::Staged => self.staged_stat.as_str(),
+                _ => "",
+            };
+            if !summary.is_empty() {
+                writeln!(f, "\r  {}{summary}{ResetAttributes}", Attribute::Dim)?;
+            }
+            if collapsed {
+                continue;
+            }
+
+            for (index, file) in self.file_diffs.iter().enumerate().skip(start).take(len) {
+                f.insert_item(index);
+                if !self.heading_selected && file.cursor == 0 && self.cursor == index {
+                    f.insert_cursor();
+                    write!(f, "{}", Attribute::Reverse)?;
+                }
+                if marked.contains(&index) && render::plain() {
+                    // There's no background to show the mark, so it's shown next to the file.
+                    write!(f, "\r  * ")?;
+                } else {
+                    write!(f, "\r    ")?;
+                }
+                let background = if marked.contains(&index) {
+                    write!(f, "{}", style::SetBackgroundColor(config.colors.selection))?;
+                    // Without colours the background wouldn't stand out.
+                    if config.colors.selection == style::Color::Reset {
+                        write!(f, "{}", Attribute::Underlined)?;
+                    }
+                    config.colors.selection
+                } else {
+                    config.colors.background
+                };
+                file.render(f, background)?;
+                writeln!(f, "{ResetAttributes}")?;
+            }
         }
 
         Ok(())
@@ -375,310 +1191,585 @@ impl render::Render for Status {
 }
 
 impl Status {
-    pub fn new(repo: &Repository, options: &Options) -> Result<Self> {
-        let mut status = Self::default();
-        status.fetch(repo, options)?;
+    pub fn new(options: &Options, subdir: Option<String>, worktree: bool) -> Result<Self> {
+        // The working directory is set to the root of the working tree on startup.
+        let root = std::env::current_dir().context("failed to get the working directory")?;
+        let root = dirs::home_dir()
+            .and_then(|home| Some(Path::new("~").join(root.strip_prefix(home).ok()?)))
+            .unwrap_or(root);
+        let mut status = Self {
+            root: root.display().to_string(),
+            subdir,
+            worktree,
+            ..Self::default()
+        };
+        // There can be a lot of ignored files, e.g. build output, so they're out of the way at
+        // first.
+        status.collapsed[Section::Ignored as usize] = true;
+        status.fetch(options)?;
         Ok(status)
     }
 
-    pub fn fetch(&mut self, repo: &Repository, options: &Options) -> Result<()> {
-        // Leaving ourselves a lot of room to optimise and tidy up in here :D
-        let output = git_process(&["status"])?;
-
-        let input =
-            std::str::from_utf8(&output.stdout).context("malformed stdout from `git status`")?;
-
-        // TODO: When head().is_branch() is false, we should do something different. For example,
-        // use `branch: Option<String>` in `Status` and display something different when head
-        // detached or on tag, etc.
-        let branch = match repo.head() {
-            Ok(head) => head
-                .shorthand()
-                .context("no name found for current HEAD")?
-                .to_string(),
-            Err(e) => {
-                // git2 doesn't provide any API to get the name of an unborn branch, so we have to
-                // read it directly :(
-                if e.code() == UnbornBranch {
-                    let mut head_path = repo.path().to_path_buf();
-                    head_path.push("HEAD");
-                    fs::read_to_string(&head_path)
-                        .with_context(|| format!("couldn't read file: {head_path:?}"))?
-                        .lines()
-                        .next()
-                        .with_context(|| format!("no ref found in {head_path:?}"))?
-                        .trim()
-                        .strip_prefix("ref: refs/heads/")
-                        .with_context(|| format!("unexpected ref path found in {head_path:?}"))?
-                        .to_string()
-                } else {
-                    return Err(Error::new(e)).context("failed to get name of current branch");
-                }
-            }
+    pub fn fetch(&mut self, options: &Options) -> Result<()> {
+        self.update(StatusOutput::run()?, options)
+    }
+
+    /// Start fetching the status on another thread, so that the UI doesn't freeze in the meantime.
+    /// [`Status::finish_refresh`] takes the result when it's done.
+    pub fn refresh_in_background(&mut self) {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || drop(sender.send(StatusOutput::run())));
+        self.refresh = Some((Instant::now(), receiver));
+        self.loading = true;
+    }
+
+    /// Wait up to `timeout` for the status being fetched in the background, and update it if it's
+    /// done. Returns whether it was updated.
+    pub fn finish_refresh(&mut self, timeout: Duration, options: &Options) -> Result<bool> {
+        let Some((_, receiver)) = &self.refresh else {
+            return Ok(false);
         };
+        let output = match receiver.recv_timeout(timeout) {
+            Ok(output) => output,
+            Err(RecvTimeoutError::Timeout) => return Ok(false),
+            Err(RecvTimeoutError::Disconnected) => Err(anyhow!("the status couldn't be fetched")),
+        };
+        self.refresh = None;
+        self.loading = false;
+        self.update(output?, options)?;
+        Ok(true)
+    }
 
+    /// Update the status from the output of git, keeping the cursor, marks and expanded items
+    /// where they were as far as possible.
+    fn update(&mut self, output: StatusOutput, options: &Options) -> Result<()> {
+        watch::record_fetch(&output.status.stdout);
+        let input = std::str::from_utf8(&output.status.stdout)
+            .context("malformed stdout from `git status`")?;
+        let status = parse::parse_porcelain_status(input)?;
+
+        // The indices of the range being marked won't be valid anymore, so mark its items
+        // individually and start the range again from wherever the cursor ends up.
+        let marks = self
+            .marked()
+            .into_iter()
+            .map(|i| (self.file_diffs[i].path.clone(), self.section_of(i)))
+            .collect::<Vec<_>>();
+        let marks = marks
+            .iter()
+            .map(|(path, section)| (path.as_str(), *section))
+            .collect::<Vec<_>>();
+
+        // Carry over the state of items that were already in the same section before.
+        let new_file_diff = |path: &str, kind: DiffType, section: Section| {
+            let previous_entry = self
+                .file_diffs
+                .iter()
+                .enumerate()
+                .find(|(i, f)| f.path == path && self.section_of(*i) == section)
+                .map(|(_, f)| f);
+            let mut file_diff = FileDiff::new(
+                path,
+                kind,
+                previous_entry.map_or(options.auto_expand_files, |f| f.expanded),
+                previous_entry.map_or(0, |f| f.cursor),
+            );
+            file_diff.marked = marks.contains(&(path, section));
+            file_diff
+        };
+
+        let mut conflicted = Vec::new();
         let mut untracked = Vec::new();
         let mut staged = Vec::new();
         let mut unstaged = Vec::new();
-
-        let mut lines = input.lines();
-        while let Some(line) = lines.next() {
-            if line == "Untracked files:" {
-                // (use "git add <file>..." to include in what will be committed)
-                lines.next().context("strange `git status` output")?;
-                for line in lines.by_ref() {
-                    if line.is_empty() {
-                        break;
-                    }
-                    let path = line.trim_start();
-                    let previous_entry = self
-                        .file_diffs
-                        .iter()
-                        .take(self.count_untracked)
-                        .find(|f| f.path == path);
-                    untracked.push(FileDiff::new(
-                        path,
-                        DiffType::Untracked,
-                        previous_entry.map_or(options.auto_expand_files, |f| f.expanded),
-                        previous_entry.map_or(0, |f| f.cursor),
-                    ));
+        let mut submodules = Vec::new();
+        let mut ignored = Vec::new();
+
+        for entry in status.entries {
+            let (index, worktree, path, orig_path) = match entry {
+                StatusEntry::Untracked(path) => {
+                    untracked.push(new_file_diff(path, DiffType::Untracked, Section::Untracked));
+                    continue;
                 }
-            } else if line == "Changes not staged for commit:" {
-                // (use "git add <file>..." to update what will be committed)
-                // (use "git restore <file>..." to discard changes in working directory)
-                lines.next().context("strange `git status` output")?;
-                lines.next().context("strange `git status` output")?;
-                for line in lines.by_ref() {
-                    if line.is_empty() {
-                        break;
-                    }
-
-                    let parse_result: IResult<&str, &str> = take_until("  ")(line.trim_start());
-                    let (line, prefix) = parse_result
-                        .map_err(|e| e.to_owned())
-                        .context("strange diff output")?;
-
-                    let path = line.trim_start();
-                    let previous_entry = self
-                        .file_diffs
-                        .iter()
-                        .skip(self.count_untracked)
-                        .take(self.count_unstaged)
-                        .find(|f| f.path == path);
-                    unstaged.push(FileDiff::new(
-                        path,
-                        match prefix {
-                            "" => DiffType::Untracked,        // untracked files
-                            "new file:" => DiffType::Created, // staged new files
-                            "modified:" => DiffType::Modified,
-                            "renamed:" => DiffType::Renamed,
-                            "deleted:" => DiffType::Deleted,
-                            _ => {
-                                return Err(anyhow!(
-                                    "unknown file prefix in `git status` output: `{prefix}`"
-                                ))
-                            }
-                        },
-                        previous_entry.map_or(options.auto_expand_files, |f| f.expanded),
-                        previous_entry.map_or(0, |f| f.cursor),
-                    ));
+                // There's no diff for an ignored file either, so it's shown like an untracked one.
+                StatusEntry::Ignored(path) => {
+                    ignored.push(new_file_diff(path, DiffType::Untracked, Section::Ignored));
+                    continue;
                 }
-            } else if line == "Changes to be committed:" {
-                // (use "git restore --staged <file>..." to unstage)
-                lines.next().context("strange `git status` output")?;
-                for line in lines.by_ref() {
-                    if line.is_empty() {
-                        break;
-                    }
-
-                    let parse_result: IResult<&str, &str> = take_until("  ")(line.trim_start());
-                    let (line, prefix) = parse_result
-                        .map_err(|e| e.to_owned())
-                        .context("strange `git status` output")?;
-
-                    let path = line.trim_start();
-                    let previous_entry = self
-                        .file_diffs
-                        .iter()
-                        .skip(self.count_untracked + self.count_unstaged)
-                        .find(|f| f.path == path);
-                    staged.push(FileDiff::new(
-                        path,
-                        match prefix {
-                            "" => DiffType::Untracked,        // untracked files
-                            "new file:" => DiffType::Created, // staged new files
-                            "modified:" => DiffType::Modified,
-                            "renamed:" => DiffType::Renamed,
-                            "deleted:" => DiffType::Deleted,
-                            _ => {
-                                return Err(anyhow!(
-                                    "unknown file prefix in `git status` output: `{prefix}`"
-                                ))
-                            }
-                        },
-                        previous_entry.map_or(options.auto_expand_files, |f| f.expanded),
-                        previous_entry.map_or(0, |f| f.cursor),
-                    ));
+                StatusEntry::Unmerged {
+                    index,
+                    worktree,
+                    path,
+                } => {
+                    let kind = DiffType::Conflicted(Conflict::from_status_codes(index, worktree)?);
+                    conflicted.push(new_file_diff(path, kind, Section::Conflicted));
+                    continue;
+                }
+                // Whether a new commit is staged for a submodule is shown in its own section.
+                StatusEntry::Submodule { submodule, .. } => {
+                    let kind = DiffType::Submodule(submodule.state);
+                    let mut file_diff = new_file_diff(submodule.path, kind, Section::Submodules);
+                    file_diff.head_oid = Some(submodule.head_oid.to_string());
+                    submodules.push(file_diff);
+                    continue;
                 }
+                StatusEntry::Changed {
+                    index,
+                    worktree,
+                    path,
+                } => (index, worktree, path, None),
+                StatusEntry::Renamed {
+                    index,
+                    worktree,
+                    path,
+                    orig_path,
+                } => (index, worktree, path, Some(orig_path)),
+            };
+            if let Some(kind) = DiffType::from_status_code(index)? {
+                let mut file_diff = new_file_diff(path, kind, Section::Staged);
+                file_diff.orig_path = orig_path.map(str::to_string);
+                staged.push(file_diff);
+            }
+            // The rename has already happened in the index, so in the worktree it's just the new
+            // path that is modified.
+            if let Some(kind) = DiffType::from_status_code(worktree)? {
+                unstaged.push(new_file_diff(path, kind, Section::Unstaged));
             }
         }
 
+        let branch = status.head.map(str::to_string);
+
         // Get the diff information for unstaged changes
-        let diff = git_process(&["diff", "--no-ext-diff"])?;
-        Self::populate_diffs(&mut unstaged, &self.file_diffs, &diff, options)
-            .context("failed to populate unstaged file diffs")?;
+        self.unstaged_stat = Self::populate_diffs(
+            &mut unstaged,
+            &self.file_diffs,
+            &output.unstaged_diff,
+            options,
+        )
+        .context("failed to populate unstaged file diffs")?;
 
         // Get the diff information for staged changes
-        let diff = git_process(&["diff", "--cached", "--no-ext-diff"])?;
-        Self::populate_diffs(&mut staged, &self.file_diffs, &diff, options)
-            .context("failed to populate unstaged file diffs")?;
+        self.staged_stat =
+            Self::populate_diffs(&mut staged, &self.file_diffs, &output.staged_diff, options)
+                .context("failed to populate staged file diffs")?;
 
         self.branch = branch;
-        self.head = std::str::from_utf8(
-            &git_process(&["log", "HEAD", "--pretty=format:%h %s", "-n", "1"])?.stdout,
-        )
-        .context("invalid utf8 from `git log`")?
-        .to_string();
+        self.rebase = output.rebase;
+        self.merging = output.merge;
+        self.ignore_rules = output.ignore_rules;
+        self.upstream = status.upstream.map(str::to_string);
+        self.ahead_behind = status.ahead_behind;
+        self.head = output.head;
+
+        // Remember what was under the cursor so that we can try to keep it there.
+        let previous_selection = self
+            .file_diffs
+            .get(self.cursor)
+            .map(|f| (f.path.clone(), self.section_of(self.cursor)));
+        let previous_heading = self.heading_selected.then(|| self.section_of(self.cursor));
+
+        self.count_conflicted = conflicted.len();
         self.count_untracked = untracked.len();
         self.count_staged = staged.len();
         self.count_unstaged = unstaged.len();
+        self.count_submodules = submodules.len();
+        self.count_ignored = ignored.len();
 
-        self.file_diffs = untracked;
+        self.file_diffs = conflicted;
+        self.file_diffs.append(&mut untracked);
         self.file_diffs.append(&mut unstaged);
         self.file_diffs.append(&mut staged);
+        self.file_diffs.append(&mut submodules);
+        self.file_diffs.append(&mut ignored);
 
         for file_diff in self.file_diffs.iter_mut().filter(|f| f.cursor >= f.len()) {
             file_diff.cursor = file_diff.len() - 1;
         }
 
+        // Prefer the same path in the same section, then the same path anywhere (e.g. it was just
+        // staged), otherwise stay at the same index.
+        if let Some((path, section)) = previous_selection {
+            let by_path = |same_section: bool| {
+                self.file_diffs.iter().enumerate().position(|(i, f)| {
+                    f.path == path && (!same_section || self.section_of(i) == section)
+                })
+            };
+            if let Some(index) = by_path(true).or_else(|| by_path(false)) {
+                self.cursor = index;
+            }
+        }
+
         if !self.file_diffs.is_empty() && self.cursor >= self.file_diffs.len() {
             self.cursor = self.file_diffs.len() - 1;
         }
 
-        if let Some(file_diff) = self.file_diffs.get_mut(self.cursor) {
-            file_diff.selected = true;
+        // Stay on the same heading if the section still exists. The items of a collapsed section
+        // can't be selected, so select its heading instead.
+        self.heading_selected = false;
+        match previous_heading {
+            Some(section) if self.section_range(section).1 != 0 => {
+                self.set_position(Position::Heading(section));
+            }
+            _ if !self.file_diffs.is_empty() => {
+                let section = self.section_of(self.cursor);
+                if self.collapsed[section as usize] {
+                    self.set_position(Position::Heading(section));
+                } else if let Some(file_diff) = self.file_diffs.get_mut(self.cursor) {
+                    file_diff.selected = true;
+                }
+            }
+            _ => {}
+        }
+        if self.mark_anchor.is_some() {
+            self.mark_anchor = Some(self.cursor);
         }
+        self.highlight_matches();
 
         Ok(())
     }
 
     /// Takes a vec `file_diffs` containing `FileDiff` elements that have only the name populated,
-    /// and populates their hunks based on the parsing of `diff`, and the `prev_file_diffs`.
+    /// and populates their hunks based on the parsing of `diff`, and the `prev_file_diffs`. Returns
+    /// the summary of the changes in `diff`.
     fn populate_diffs(
         file_diffs: &mut Vec<FileDiff>,
         prev_file_diffs: &[FileDiff],
         diff: &Output,
         options: &Options,
-    ) -> Result<()> {
+    ) -> Result<String> {
         let diff = std::str::from_utf8(&diff.stdout).context("malformed stdout from `git diff`")?;
-        let hunks = parse::parse_diff(diff)?;
+        let patches = parse::parse_diff(diff)?;
+        let stat = diff_stat(&patches);
+        let patches = patches
+            .into_iter()
+            .map(|patch| (patch.path().to_string(), patch))
+            .collect::<HashMap<_, _>>();
         for file in file_diffs {
-            if let Some(hunks) = hunks.get(file.path.as_str()) {
+            if let Some(patch) = patches.get(&file.path) {
                 // Get all the diffs entries of this file from the previous iteration.
-                let previous_file_entries = prev_file_diffs.iter().filter(|f| f.path == file.path);
-                file.hunks = hunks
+                let previous_hunks = prev_file_diffs
+                    .iter()
+                    .filter(|f| f.path == file.path)
+                    .flat_map(|f| &f.hunks)
+                    .collect::<Vec<_>>();
+                file.hunks = patch
+                    .hunks
                     .iter()
                     .map(|hunk| {
-                        let expanded = previous_file_entries
-                            .clone()
-                            .find_map(|f| {
-                                f.hunks.iter().find(|h| {
-                                    let h_header =
-                                        h.diff.lines().next().expect("hunk should never be empty");
-                                    let hunk_header =
-                                        hunk.lines().next().expect("hunk should never be empty");
-                                    (parse_hunk_new(h_header).unwrap_or_else(|e| panic!("{e:?}"))
-                                        == parse_hunk_new(hunk_header)
-                                            .unwrap_or_else(|e| panic!("{e:?}")))
-                                        || (parse_hunk_old(h_header)
-                                            .unwrap_or_else(|e| panic!("{e:?}"))
-                                            == parse_hunk_old(hunk_header)
-                                                .unwrap_or_else(|e| panic!("{e:?}")))
-                                })
-                            })
-                            .map_or(options.auto_expand_hunks, |h| h.expanded);
-
-                        Hunk::new(hunk.clone(), expanded)
+                        let hunk = hunk.to_string();
+                        let mut expanded = options.auto_expand_hunks;
+                        for h in &previous_hunks {
+                            if same_hunk(&h.diff, &hunk)? {
+                                expanded = h.expanded;
+                                break;
+                            }
+                        }
+                        Ok(Hunk::new(hunk, expanded, Language::from_path(&file.path)))
                     })
-                    .collect();
+                    .collect::<Result<_>>()?;
             }
         }
-        Ok(())
+        Ok(stat)
+    }
+
+    /// The section that the item at `index` belongs to.
+    const fn section_of(&self, index: usize) -> Section {
+        if index < self.count_conflicted {
+            Section::Conflicted
+        } else if index < self.count_conflicted + self.count_untracked {
+            Section::Untracked
+        } else if index < self.count_conflicted + self.count_untracked + self.count_unstaged {
+            Section::Unstaged
+        } else if index
+            < self.count_conflicted + self.count_untracked + self.count_unstaged + self.count_staged
+        {
+            Section::Staged
+        } else if index
+            < self.count_conflicted
+                + self.count_untracked
+                + self.count_unstaged
+                + self.count_staged
+                + self.count_submodules
+        {
+            Section::Submodules
+        } else {
+            Section::Ignored
+        }
     }
 
-    fn stage_or_unstage(&mut self, command: Stage) -> Result<()> {
+    /// The index of the first item in `section`, and how many items it has.
+    const fn section_range(&self, section: Section) -> (usize, usize) {
+        let untracked_start = self.count_conflicted;
+        let unstaged_start = untracked_start + self.count_untracked;
+        let staged_start = unstaged_start + self.count_unstaged;
+        let submodules_start = staged_start + self.count_staged;
+        let ignored_start = submodules_start + self.count_submodules;
+        match section {
+            Section::Conflicted => (0, self.count_conflicted),
+            Section::Untracked => (untracked_start, self.count_untracked),
+            Section::Unstaged => (unstaged_start, self.count_unstaged),
+            Section::Staged => (staged_start, self.count_staged),
+            Section::Submodules => (submodules_start, self.count_submodules),
+            Section::Ignored => (ignored_start, self.count_ignored),
+        }
+    }
+
+    /// The section of the item currently under the cursor, or `None` if there are no items or the
+    /// cursor is on a heading.
+    pub fn section(&self) -> Option<Section> {
+        (!self.file_diffs.is_empty() && !self.heading_selected)
+            .then(|| self.section_of(self.cursor))
+    }
+
+    fn stage_or_unstage(&self, command: Stage) -> Result<()> {
         if self.file_diffs.is_empty() {
             return Ok(());
         }
 
         let file = self
             .file_diffs
-            .get_mut(self.cursor)
+            .get(self.cursor)
             .context("cursor is at invalid position")?;
-        file.selected = false;
-
-        match file.cursor {
-            0 => {
-                let args = match command {
-                    Stage::Add => vec!["add", &file.path],
-                    Stage::Reset => match file.kind {
-                        DiffType::Deleted => vec!["reset", "HEAD", &file.path],
-                        _ => vec!["reset", &file.path],
-                    },
-                };
-                git_process(&args)?;
-            }
-            i => {
-                let mut patch = Command::new("git")
-                    .args(match command {
-                        Stage::Add => ["add", "-p", &file.path],
-                        Stage::Reset => ["reset", "-p", &file.path],
+        if let (Stage::Reset, DiffType::Renamed, Some(hunk)) = (
+            command,
+            file.kind,
+            file.cursor.checked_sub(1).and_then(|i| file.hunks.get(i)),
+        ) {
+            return unstage_renamed_hunk(&file.path, &hunk.diff);
+        }
+        patch(
+            command,
+            &file.path,
+            file.orig_path.as_deref(),
+            file.kind,
+            file.cursor,
+        )
+    }
+
+    /// The indices of the marked items.
+    fn marked(&self) -> Vec<usize> {
+        let range = self
+            .mark_anchor
+            .map(|anchor| self.cursor.min(anchor)..=self.cursor.max(anchor));
+        (0..self.file_diffs.len())
+            .filter(|&i| {
+                self.file_diffs[i].marked
+                    || range.as_ref().is_some_and(|range| {
+                        range.contains(&i) && !self.collapsed[self.section_of(i) as usize]
                     })
-                    .stdin(Stdio::piped())
-                    .stdout(Stdio::null())
-                    .stderr(Stdio::piped())
-                    .spawn()
-                    .context("failed to spawn interactive git process")?;
+            })
+            .collect()
+    }
 
-                let mut stdin = patch.stdin.take().context("failed to open child stdin")?;
+    /// The paths of the marked items in `sections`. Renamed files come with the path they were
+    /// moved from.
+    fn marked_paths(&self, sections: &[Section]) -> Vec<&str> {
+        let mut paths = Vec::new();
+        for i in self.marked() {
+            if !sections.contains(&self.section_of(i)) {
+                continue;
+            }
+            let file = &self.file_diffs[i];
+            paths.extend(file.orig_path.as_deref());
+            paths.push(file.path.as_str());
+        }
+        paths
+    }
 
-                let mut bufs = vec![b"n\n"; i - 1];
-                bufs.push(b"y\n");
+    /// Mark or unmark the item under the cursor. On a heading, every item of the section is marked,
+    /// or unmarked if they already all are.
+    pub fn toggle_mark(&mut self) {
+        let Some(position) = self.position() else {
+            return;
+        };
+        let (start, len) = match position {
+            Position::Heading(section) => self.section_range(section),
+            Position::File(index) | Position::Hunk(index, _) => (index, 1),
+        };
+        let files = &mut self.file_diffs[start..start + len];
+        let mark = !files.iter().all(|f| f.marked);
+        for file in files {
+            file.marked = mark;
+        }
+    }
 
-                std::thread::spawn(move || {
-                    for buf in bufs {
-                        stdin.write_all(buf).context("failed to patch hunk")?;
-                    }
-                    Ok::<_, Error>(())
-                })
-                .join()
-                .unwrap()
-                .context("failed to patch hunk")?;
-
-                let mut stderr_buf = String::new();
-                patch
-                    .stderr
-                    // If I understand correctly, reading to EOF should have the added effect
-                    // waiting on the child process to finish.
-                    .map(|mut stderr| stderr.read_to_string(&mut stderr_buf))
-                    .context("failed to read stderr of child process")??;
-                MiniBuffer::push(&stderr_buf, MessageType::Error);
+    /// Start marking every item that the cursor moves over, or stop if that's already happening.
+    pub fn toggle_mark_range(&mut self) {
+        if self.mark_anchor.is_some() {
+            for i in self.marked() {
+                self.file_diffs[i].marked = true;
             }
+            self.mark_anchor = None;
+        } else if !self.file_diffs.is_empty() {
+            self.mark_anchor = Some(self.cursor);
         }
+    }
 
-        self.file_diffs
-            .get_mut(self.cursor)
-            .context("cursor is at invalid position")?
-            .selected = true;
-        Ok(())
+    /// Unmark all items.
+    pub fn clear_marks(&mut self) {
+        self.mark_anchor = None;
+        for file in &mut self.file_diffs {
+            file.marked = false;
+        }
+    }
+
+    /// Stop showing the cursor, e.g. because the status is only printed.
+    pub fn deselect(&mut self) {
+        self.heading_selected = false;
+        self.cursor = self.file_diffs.len();
+        for file in &mut self.file_diffs {
+            file.selected = false;
+        }
+    }
+
+    /// The path of the file under the cursor, if any.
+    pub fn selected_path(&self) -> Option<&str> {
+        (!self.heading_selected)
+            .then(|| self.file_diffs.get(self.cursor))
+            .flatten()
+            .map(|file| file.path.as_str())
     }
 
-    pub fn stage(&mut self) -> Result<()> {
-        self.stage_or_unstage(Stage::Add)
+    /// The path of the file under the cursor, if it's in the unstaged section.
+    pub fn unstaged_path(&self) -> Option<&str> {
+        (self.section() == Some(Section::Unstaged))
+            .then(|| self.file_diffs[self.cursor].path.as_str())
     }
 
-    pub fn unstage(&mut self) -> Result<()> {
-        self.stage_or_unstage(Stage::Reset)
+    /// The rule of an ignore file that matches the untracked or ignored item under the cursor, if
+    /// there is one.
+    pub fn ignore_rule(&self) -> Option<&IgnoreRule> {
+        matches!(self.section(), Some(Section::Untracked | Section::Ignored))
+            .then(|| self.ignore_rules.get(&self.file_diffs[self.cursor].path))
+            .flatten()
+    }
+
+    /// The path of the submodule under the cursor, if it's in the submodules section.
+    pub fn submodule_path(&self) -> Option<&str> {
+        (self.section() == Some(Section::Submodules))
+            .then(|| self.file_diffs[self.cursor].path.as_str())
+    }
+
+    /// Stage the marked items, or the item under the cursor if there aren't any. Staging marks a
+    /// conflicted file as resolved, and stages the commit that is checked out in a submodule.
+    /// Returns `false` without doing anything if there's nothing to stage.
+    pub fn stage(&mut self) -> Result<bool> {
+        if !self.marked().is_empty() {
+            let paths = self.marked_paths(&[
+                Section::Conflicted,
+                Section::Untracked,
+                Section::Unstaged,
+                Section::Submodules,
+            ]);
+            if paths.is_empty() {
+                return Ok(false);
+            }
+            let mut args = vec!["add", "--"];
+            args.extend(paths);
+            MiniBuffer::push_command_output(&git_process(&args)?);
+            self.clear_marks();
+            return Ok(true);
+        }
+        if !matches!(
+            self.section(),
+            Some(
+                Section::Conflicted | Section::Untracked | Section::Unstaged | Section::Submodules
+            )
+        ) {
+            return Ok(false);
+        }
+        self.stage_or_unstage(Stage::Add)?;
+        Ok(true)
+    }
+
+    /// Unstage the marked items, or the item under the cursor if there aren't any. A submodule
+    /// under the cursor without a staged commit is updated to the commit recorded for it instead.
+    /// Returns `false` without doing anything if there's nothing to unstage.
+    pub fn unstage(&mut self) -> Result<bool> {
+        if !self.marked().is_empty() {
+            let paths = self.marked_paths(&[Section::Staged, Section::Submodules]);
+            if paths.is_empty() {
+                return Ok(false);
+            }
+            // `git restore` needs HEAD to restore the index from, but without any commits
+            // everything in the index is new so it can just be removed.
+            let mut args = if command::has_commits()? {
+                vec!["restore", "--staged", "--"]
+            } else {
+                vec!["rm", "--cached", "-q", "--"]
+            };
+            args.extend(paths);
+            MiniBuffer::push_command_output(&git_process(&args)?);
+            self.clear_marks();
+            return Ok(true);
+        }
+        if let Some(path) = self.submodule_path() {
+            let staged = matches!(
+                self.file_diffs[self.cursor].kind,
+                DiffType::Submodule(state) if state.contains(SubmoduleState::STAGED)
+            );
+            if staged {
+                self.stage_or_unstage(Stage::Reset)?;
+            } else {
+                update_submodule(path)?;
+            }
+            return Ok(true);
+        }
+        if self.section() != Some(Section::Staged) {
+            return Ok(false);
+        }
+        self.stage_or_unstage(Stage::Reset)?;
+        Ok(true)
+    }
+
+    /// Discard the unstaged changes of the item under the cursor, after asking the user for
+    /// confirmation. Does nothing if the item isn't in the unstaged section.
+    pub fn discard(&self, minibuffer: &mut MiniBuffer, view: &mut View) {
+        if self.section() != Some(Section::Unstaged) {
+            return;
+        }
+        let file = &self.file_diffs[self.cursor];
+        let (path, kind, hunk) = (file.path.clone(), file.kind, file.cursor);
+        let prompt = if hunk == 0 {
+            format!("Discard changes to {path}?")
+        } else {
+            format!("Discard hunk {hunk} of {path}?")
+        };
+        minibuffer.confirm(
+            Rc::new(move || {
+                patch(Stage::Discard, &path, None, kind, hunk)?;
+                REFRESH_FLAG.store(true, Ordering::Release);
+                Ok(())
+            }),
+            &prompt,
+            view,
+            View::Status,
+        );
+    }
+
+    /// Add the ignored item under the cursor with `git add --force` after confirmation.
+    pub fn force_add(&self, minibuffer: &mut MiniBuffer, view: &mut View) {
+        if self.section() != Some(Section::Ignored) {
+            return;
+        }
+        let path = self.file_diffs[self.cursor].path.clone();
+        let prompt = self
+            .ignore_rule()
+            .filter(|rule| !rule.pattern.starts_with('!'))
+            .map_or_else(
+                || format!("Force add {path}?"),
+                |rule| format!("Add {path} despite {rule}?"),
+            );
+        minibuffer.confirm(
+            Rc::new(move || {
+                MiniBuffer::push_command_output(&git_process(&["add", "--force", "--", &path])?);
+                REFRESH_FLAG.store(true, Ordering::Release);
+                Ok(())
+            }),
+            &prompt,
+            view,
+            View::Status,
+        );
     }
 
     /// Toggles expand on the selected diff item.
@@ -687,15 +1778,21 @@ impl Status {
             return Ok(());
         }
 
+        if self.heading_selected {
+            let section = self.section_of(self.cursor);
+            self.collapsed[section as usize] = !self.collapsed[section as usize];
+            return Ok(());
+        }
+
         let file = self
             .file_diffs
             .get_mut(self.cursor)
             .context("cursor is at invalid position")?;
 
         if file.cursor == 0 {
-            file.expanded = !file.expanded;
+            file.toggle_expand();
         } else {
-            file.hunks[file.cursor - 1].expanded = !file.hunks[file.cursor - 1].expanded;
+            file.hunks[file.cursor - 1].toggle_expand();
         }
 
         Ok(())
@@ -703,11 +1800,13 @@ impl Status {
 
     /// Open the current file in the configured editor. Return when the edit finishes.
     pub fn open_editor(&self) -> Result<()> {
-        let editor = &crate::config!().options.editor;
+        if self.heading_selected {
+            return Ok(());
+        }
         let file = &self
             .file_diffs
             .get(self.cursor)
-            .expect("cursor is at invalid position");
+            .context("cursor is at invalid position")?;
 
         let row = match file.cursor {
             0 => 1,
@@ -718,171 +1817,313 @@ impl Status {
                 .parse()
                 .context("couldn't get starting line of hunk")?,
         };
-        // If your editor isn't supported yet for opening at a specific line, please add the
-        // implementation below!
-        let args = match (editor.as_str(), row) {
-            (_, 1) => vec![file.path.clone()],
-            ("nvim" | "vim" | "vi" | "nano", _) => vec![format!("+{row}"), file.path.clone()],
-            ("hx", _) => vec![format!("{}:{row}", &file.path)],
-            _ => vec![file.path.clone()],
-        };
-
-        crossterm::execute!(stdout(), terminal::LeaveAlternateScreen)
-            .expect("failed to leave alternate screen");
-        Command::new(editor)
-            .args(&args)
-            .stdout(Stdio::inherit())
-            .stdin(Stdio::inherit())
-            .output()
-            .context("failed to open editor")?;
-        crossterm::execute!(stdout(), terminal::EnterAlternateScreen, cursor::Hide)
-            .expect("failed to enter alternate screen");
-        Ok(())
+        edit_file(&file.path, row)
     }
 
-    /// Jump to previous file.
-    pub fn file_up(&mut self) -> Result<()> {
-        if self.file_diffs.is_empty() {
-            return Ok(());
+    /// The positions the cursor can be at, in the order they are displayed.
+    fn positions(&self) -> Vec<Position> {
+        let mut positions = Vec::new();
+        for section in SECTIONS {
+            let (start, len) = self.section_range(section);
+            if len == 0 {
+                continue;
+            }
+            positions.push(Position::Heading(section));
+            if self.collapsed[section as usize] {
+                continue;
+            }
+            for (index, file) in self.file_diffs.iter().enumerate().skip(start).take(len) {
+                positions.push(Position::File(index));
+                positions.extend((1..file.len()).map(|hunk| Position::Hunk(index, hunk)));
+            }
         }
-        let file = self
-            .file_diffs
-            .get_mut(self.cursor)
-            .context("cursor is at invalid position")?;
-        if file.cursor == 0 {
-            file.selected = false;
-            self.cursor = self.cursor.saturating_sub(1);
-            let new_file = self
-                .file_diffs
-                .get_mut(self.cursor)
-                .expect("self.cursor >= 0, !self.file_diffs.is_empty");
-            new_file.selected = true;
-            new_file.cursor = 0;
+        positions
+    }
+
+    /// The position of the cursor, or `None` if there is nothing to select.
+    fn position(&self) -> Option<Position> {
+        let file = self.file_diffs.get(self.cursor)?;
+        Some(if self.heading_selected {
+            Position::Heading(self.section_of(self.cursor))
+        } else if file.cursor == 0 {
+            Position::File(self.cursor)
         } else {
-            file.cursor = 0;
-        }
-        Ok(())
+            Position::Hunk(self.cursor, file.cursor)
+        })
     }
 
-    /// Jump to next file.
-    pub fn file_down(&mut self) -> Result<()> {
-        if self.cursor < self.file_diffs.len() - 1 {
-            self.file_diffs
-                .get_mut(self.cursor)
-                .context("cursor is at invalid position")?
-                .selected = false;
-            self.cursor += 1;
-            let new_file = self
-                .file_diffs
-                .get_mut(self.cursor)
-                .expect("self.cursor < self.file_diffs.len");
-            new_file.selected = true;
-            new_file.cursor = 0;
+    fn set_position(&mut self, position: Position) {
+        if let Some(file) = self.file_diffs.get_mut(self.cursor) {
+            file.selected = false;
         }
-        Ok(())
+        let (index, hunk) = match position {
+            Position::Heading(section) => {
+                self.heading_selected = true;
+                self.cursor = self.section_range(section).0;
+                return;
+            }
+            Position::File(index) => (index, 0),
+            Position::Hunk(index, hunk) => (index, hunk),
+        };
+        self.heading_selected = false;
+        self.cursor = index;
+        let file = &mut self.file_diffs[index];
+        file.selected = true;
+        file.cursor = hunk;
     }
 
-    /// Move the cursor up one
-    pub fn up(&mut self) -> Result<()> {
-        if self.file_diffs.is_empty() {
-            return Ok(());
+    /// Move the cursor to the first position in the given direction that `filter` accepts.
+    fn move_cursor(&mut self, forwards: bool, filter: impl Fn(&Position) -> bool) {
+        let positions = self.positions();
+        let Some(current) = self
+            .position()
+            .and_then(|position| positions.iter().position(|p| *p == position))
+        else {
+            return;
+        };
+        let next = if forwards {
+            positions[current + 1..].iter().find(|p| filter(p))
+        } else {
+            positions[..current].iter().rev().find(|p| filter(p))
+        };
+        if let Some(&next) = next {
+            self.set_position(next);
         }
+    }
 
-        let file = self
+    /// Whether the keys typed are being used to search the paths of the items.
+    pub const fn is_searching(&self) -> bool {
+        self.searching
+    }
+
+    /// What to show in the search bar, if it's open.
+    pub fn search_prompt(&self) -> Option<String> {
+        let no_matches =
+            !self.search.is_empty() && self.file_diffs.iter().all(|f| f.search_match.is_none());
+        self.searching.then(|| {
+            format!(
+                "/{}{}",
+                self.search,
+                if no_matches { "  (no matches)" } else { "" }
+            )
+        })
+    }
+
+    /// Open the search bar to search the paths of the items.
+    pub fn start_search(&mut self) {
+        self.search.clear();
+        self.searching = true;
+        self.highlight_matches();
+    }
+
+    /// Stop highlighting the matches of the last search.
+    pub fn clear_search(&mut self) {
+        self.search.clear();
+        self.highlight_matches();
+    }
+
+    /// Handle a key press while the search bar is open. The cursor jumps to the first item that
+    /// matches as the search is typed.
+    pub fn search_input(&mut self, key_event: KeyEvent) {
+        match key_event.code {
+            KeyCode::Char(c) => self.search.push(c),
+            KeyCode::Backspace => {
+                self.search.pop();
+            }
+            KeyCode::Esc | KeyCode::Enter => {
+                self.searching = false;
+                return;
+            }
+            _ => return,
+        }
+        self.highlight_matches();
+        if let Some(index) = self
             .file_diffs
-            .get_mut(self.cursor)
-            .context("cursor is at invalid position")?;
+            .iter()
+            .position(|f| f.search_match.is_some())
+        {
+            self.jump_to(index);
+        }
+    }
 
-        if file.up().is_err() {
-            match self.cursor.checked_sub(1) {
-                Some(v) => {
-                    self.cursor = v;
-                    file.selected = false;
-                    let new_file = self
-                        .file_diffs
-                        .get_mut(self.cursor)
-                        .context("cursor at invalid position")?;
-                    new_file.selected = true;
-                    if new_file.expanded() {
-                        new_file.cursor_last();
-                    }
+    /// Jump to the next or previous item that matches the search, wrapping around at the ends.
+    pub fn search_next(&mut self, forwards: bool) {
+        let len = self.file_diffs.len();
+        // On a heading, the first item of its section counts as the next one.
+        let skip = usize::from(!(forwards && self.heading_selected));
+        let next = (0..len)
+            .map(|i| {
+                if forwards {
+                    (self.cursor + skip + i) % len
+                } else {
+                    (self.cursor + len - 1 - i) % len
                 }
-                None => self.cursor = 0,
-            }
+            })
+            .find(|&i| self.file_diffs[i].search_match.is_some());
+        if let Some(index) = next {
+            self.jump_to(index);
         }
+    }
 
-        Ok(())
+    /// Move the cursor to the item at `index`, expanding its section if it's collapsed.
+    fn jump_to(&mut self, index: usize) {
+        self.collapsed[self.section_of(index) as usize] = false;
+        self.set_position(Position::File(index));
     }
 
-    /// Move the cursor down one
-    pub fn down(&mut self) -> Result<()> {
-        if self.file_diffs.is_empty() {
-            return Ok(());
+    fn highlight_matches(&mut self) {
+        for file in &mut self.file_diffs {
+            file.search_match = find_ignoring_case(&file.path, &self.search);
         }
+    }
 
-        let count_file_diffs = self.file_diffs.len();
-        let file = self
-            .file_diffs
-            .get_mut(self.cursor)
-            .context("cursor is at invalid position")?;
+    /// Jump to previous file.
+    pub fn file_up(&mut self) {
+        self.move_cursor(false, |p| !matches!(p, Position::Hunk(..)));
+    }
 
-        if file.down().is_err() {
-            if self.cursor + 1 >= count_file_diffs {
-                return Ok(());
-            }
+    /// Jump to next file.
+    pub fn file_down(&mut self) {
+        self.move_cursor(true, |p| !matches!(p, Position::Hunk(..)));
+    }
 
-            self.cursor += 1;
-            file.selected = false;
-            let new_file = self
-                .file_diffs
-                .get_mut(self.cursor)
-                .context("cursor at invalid position")?;
-            new_file.selected = true;
-            if new_file.expanded() {
-                new_file.cursor_first();
-            }
+    /// Move the cursor up one
+    pub fn up(&mut self) {
+        self.move_cursor(false, |_| true);
+    }
+
+    /// Move the cursor to the item at `index`, or to one of its hunks if `hunk` isn't 0.
+    pub fn select(&mut self, index: usize, hunk: usize) {
+        if self.file_diffs.get(index).is_none_or(|f| hunk >= f.len()) {
+            return;
         }
+        self.set_position(if hunk == 0 {
+            Position::File(index)
+        } else {
+            Position::Hunk(index, hunk)
+        });
+    }
 
-        Ok(())
+    /// Move the cursor down one
+    pub fn down(&mut self) {
+        self.move_cursor(true, |_| true);
     }
 
     /// Move the cursor to the first element.
-    pub fn cursor_first(&mut self) -> Result<()> {
-        if self.file_diffs.is_empty() {
-            return Ok(());
+    pub fn cursor_first(&mut self) {
+        if let Some(&first) = self.positions().first() {
+            self.set_position(first);
         }
-
-        self.file_diffs
-            .get_mut(self.cursor)
-            .context("cursor is at invalid position")?
-            .selected = false;
-        self.cursor = 0;
-        let new_file = self
-            .file_diffs
-            .get_mut(self.cursor)
-            .expect("0th element must exist, !self.file_diffs.is_empty()");
-        new_file.cursor_first();
-        new_file.selected = true;
-        Ok(())
     }
 
     /// Move the cursor to the last element.
-    pub fn cursor_last(&mut self) -> Result<()> {
-        if self.file_diffs.is_empty() {
-            return Ok(());
+    pub fn cursor_last(&mut self) {
+        if let Some(&last) = self.positions().last() {
+            self.set_position(last);
         }
+    }
 
-        self.file_diffs
-            .get_mut(self.cursor)
-            .context("cursor is at invalid position")?
-            .selected = false;
-        self.cursor = self.file_diffs.len() - 1;
-        let new_file = self
-            .file_diffs
-            .get_mut(self.cursor)
-            .expect("cursor at `len() - 1`th pos of non-empty diffs");
-        new_file.cursor_last();
-        new_file.selected = true;
-        Ok(())
+    /// Move the cursor `count` positions in the given direction, stopping at the first or last one.
+    pub fn page(&mut self, forwards: bool, count: usize) {
+        let positions = self.positions();
+        let Some(current) = self
+            .position()
+            .and_then(|position| positions.iter().position(|p| *p == position))
+        else {
+            return;
+        };
+        let next = if forwards {
+            (current + count).min(positions.len() - 1)
+        } else {
+            current.saturating_sub(count)
+        };
+        self.set_position(positions[next]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::IgnoreRule;
+
+    #[test_case("src/main.rs", "main", Some((4, 8)) ; "lowercase")]
+    #[test_case("README.md", "readme", Some((0, 6)) ; "ignoring case")]
+    #[test_case("docs/Über.md", "über", Some((5, 10)) ; "multibyte")]
+    #[test_case("src/main.rs", "lib", None ; "no match")]
+    #[test_case("src/main.rs", "", None ; "empty")]
+    fn find_ignoring_case(haystack: &str, needle: &str, expected: Option<(usize, usize)>) {
+        assert_eq!(
+            super::find_ignoring_case(haystack, needle),
+            expected.map(|(start, end)| start..end)
+        );
+    }
+
+    #[test_case("", "" ; "no changes")]
+    #[test_case(ONE_LINE, "1 file changed, 1 insertion(+)" ; "insertion only")]
+    #[test_case(SEVERAL_FILES, "3 files changed, 2 insertions(+), 3 deletions(-)" ; "both")]
+    #[test_case(MODE_ONLY, "1 file changed, 0 insertions(+), 0 deletions(-)" ; "mode only")]
+    fn diff_stat(diff: &str, expected: &str) {
+        let patches = crate::parse::parse_diff(diff).unwrap();
+        assert_eq!(super::diff_stat(&patches), expected);
+    }
+
+    const ONE_LINE: &str = "diff --git a/a.txt b/a.txt
+index 1111111..2222222 100644
+--- a/a.txt
++++ b/a.txt
+@@ -1 +1,2 @@
+ a
++b
+";
+
+    const SEVERAL_FILES: &str = "diff --git a/a.txt b/a.txt
+index 1111111..2222222 100644
+--- a/a.txt
++++ b/a.txt
+@@ -1,3 +1,2 @@
+-a
+-b
++c
+ d
+diff --git a/b.txt b/b.txt
+deleted file mode 100644
+index 3333333..0000000
+--- a/b.txt
++++ /dev/null
+@@ -1 +0,0 @@
+-e
+diff --git a/c.txt b/c.txt
+new file mode 100644
+index 0000000..4444444
+--- /dev/null
++++ b/c.txt
+@@ -0,0 +1 @@
++f
+";
+
+    const MODE_ONLY: &str = "diff --git a/run.sh b/run.sh
+old mode 100644
+new mode 100755
+";
+
+    #[test]
+    fn parse_check_ignore() {
+        const INPUT: &str = ".gitignore\x004\x00!keep.log\x00keep.log\x00\
+                             docs/.gitignore\x001\x00!build/\x00docs/build/\x00";
+        let rules = super::parse_check_ignore(INPUT).unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(
+            rules["keep.log"],
+            IgnoreRule {
+                source: ".gitignore".to_string(),
+                line: 4,
+                pattern: "!keep.log".to_string(),
+            }
+        );
+        assert_eq!(
+            rules["docs/build/"].to_string(),
+            "docs/.gitignore:1:!build/"
+        );
     }
 }
@@ -0,0 +1,177 @@
+//! Module relating to the status bar, which is displayed on the last row of the status view. It
+//! shows the keys for what can be done with the item under the cursor, and short messages that
+//! don't need to be dismissed.
+
+use std::{
+    fmt::Write as _,
+    io::{stdout, Write},
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    cursor::MoveTo,
+    style::{Attribute, SetForegroundColor},
+    terminal::ClearType,
+};
+
+use crate::{
+    command::GexCommand,
+    config::{Action, GexAction, CONFIG},
+    help,
+    minibuffer::{MessageType, MESSAGES},
+    render::{self, Clear, ResetAttributes},
+    status::{IgnoreRule, Section},
+};
+
+/// How long a message is shown for.
+const MESSAGE_TIME: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Default)]
+pub struct StatusBar {
+    /// The message being shown instead of the keys, and when it was first shown.
+    message: Option<(String, Instant)>,
+}
+
+/// The keys for what can be done in `section`, or on a heading if it's `None`, as pairs of the key
+/// and what it does. Keys that aren't bound to anything are left out.
+fn hints(section: Option<Section>) -> Vec<(String, &'static str)> {
+    let config = CONFIG.get().expect("config wasn't initialised");
+    let action = |action: GexAction| {
+        config
+            .keymap
+            .actions
+            .iter()
+            .filter(|(_, a)| **a == action)
+            .map(|(&key, _)| help::key_name(key))
+            .min()
+    };
+    let commit = GexCommand::commands()
+        .iter()
+        .find(|(_, cmd)| matches!(cmd, GexCommand::Commit))
+        .map(|(key, _)| key.to_string());
+    let toggle = config
+        .keymap
+        .navigation
+        .iter()
+        .filter(|(_, a)| **a == Action::ToggleExpand)
+        .map(|(&key, _)| help::key_name(key))
+        .min();
+
+    let hints = match section {
+        Some(Section::Conflicted) => vec![
+            (action(GexAction::Stage), "mark resolved"),
+            (action(GexAction::Edit), "edit"),
+        ],
+        Some(Section::Untracked) => vec![
+            (action(GexAction::Stage), "stage"),
+            (action(GexAction::Edit), "edit"),
+            (action(GexAction::Quit), "quit"),
+        ],
+        Some(Section::Unstaged) => vec![
+            (action(GexAction::Stage), "stage"),
+            (action(GexAction::Discard), "discard"),
+            (action(GexAction::Open), "hunks"),
+            (action(GexAction::Edit), "edit"),
+        ],
+        Some(Section::Staged) => vec![
+            (action(GexAction::Unstage), "unstage"),
+            (commit, "commit"),
+            (action(GexAction::Edit), "edit"),
+        ],
+        Some(Section::Submodules) => vec![
+            (action(GexAction::Stage), "stage"),
+            (action(GexAction::Unstage), "unstage or update"),
+            (action(GexAction::Open), "open"),
+        ],
+        Some(Section::Ignored) => vec![
+            (action(GexAction::ForceAdd), "force add"),
+            (action(GexAction::Edit), "edit"),
+        ],
+        None => vec![(toggle, "collapse"), (action(GexAction::Quit), "quit")],
+    };
+    hints
+        .into_iter()
+        .chain([(action(GexAction::Help), "help")])
+        .filter_map(|(key, description)| Some((key?, description)))
+        .collect()
+}
+
+impl StatusBar {
+    /// Take the message to be shown next from the minibuffer if it's the only one, fits on a line
+    /// and isn't an error, so that it's shown in the status bar instead of having to be dismissed.
+    /// Errors stay in the minibuffer so that they can't be missed.
+    pub fn take_message(&mut self) {
+        let mut messages = MESSAGES.try_lock().expect("couldn't get mutex lock");
+        if let [(msg, MessageType::Note)] = messages.as_slice() {
+            if !msg.contains('\n') {
+                let (msg, _) = messages.remove(0);
+                drop(messages);
+                self.message = Some((msg, Instant::now()));
+            }
+        }
+    }
+
+    /// How long until the message being shown should be cleared, if there is one.
+    pub fn message_time_left(&self) -> Option<Duration> {
+        self.message
+            .as_ref()
+            .map(|(_, shown)| MESSAGE_TIME.saturating_sub(shown.elapsed()))
+    }
+
+    /// Clear the message if it has been shown for long enough. Returns whether it was cleared.
+    pub fn clear_expired_message(&mut self) -> bool {
+        if self.message_time_left().is_some_and(|left| left.is_zero()) {
+            self.message = None;
+            return true;
+        }
+        false
+    }
+
+    /// Draw the status bar on the last row of the terminal, for the item under the cursor being in
+    /// `section`. The search bar is shown instead if `search` is given. The rule of an ignore file
+    /// that matches the item is shown on the right of the keys.
+    pub fn draw(
+        &self,
+        section: Option<Section>,
+        search: Option<&str>,
+        ignore_rule: Option<&IgnoreRule>,
+        term_width: u16,
+        term_height: u16,
+    ) {
+        let config = CONFIG.get().expect("config wasn't initialised");
+        let mut line = String::new();
+        if let Some(search) = search {
+            line.push_str(search);
+        } else if let Some((msg, _)) = &self.message {
+            line.extend(msg.chars().take(term_width.into()));
+        } else {
+            let mut width = 0;
+            for (key, description) in hints(section) {
+                let _ = write!(
+                    line,
+                    "{}{}{key}{ResetAttributes} {description}  ",
+                    SetForegroundColor(config.colors.key),
+                    Attribute::Bold,
+                );
+                width += key.chars().count() + description.chars().count() + 3;
+            }
+            if let Some(rule) = ignore_rule.map(ToString::to_string) {
+                // It's left out rather than cut off if there isn't room for it.
+                let padding = usize::from(term_width).saturating_sub(width + rule.chars().count());
+                if padding > 0 {
+                    let _ = write!(line, "{:padding$}{}{rule}", "", Attribute::Dim);
+                }
+            }
+        }
+        let _ = write!(line, "{ResetAttributes}");
+        if render::plain() {
+            line = render::strip_styles(&line);
+        }
+        print!(
+            "{}{}{line}",
+            MoveTo(0, term_height.saturating_sub(1)),
+            Clear(ClearType::CurrentLine),
+        );
+        drop(stdout().flush());
+    }
+}
@@ -0,0 +1,209 @@
+//! Module relating to the list of tags.
+
+use std::{collections::HashMap, fmt, rc::Rc, sync::atomic::Ordering};
+
+use anyhow::{Context, Result};
+use crossterm::{
+    event::{KeyCode, KeyEvent},
+    style::{Attribute, SetForegroundColor},
+};
+
+use crate::{
+    command,
+    config::{Action, CONFIG},
+    git_process, log,
+    minibuffer::MiniBuffer,
+    pager::Pager,
+    render::{self, Renderer, ResetAttributes},
+    status, View,
+};
+
+/// A tag from `git tag -l`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct TagEntry {
+    pub name: String,
+    /// Whether it's an annotated tag, which is an object with a message of its own, rather than a
+    /// lightweight one, which is only a name for a commit.
+    pub annotated: bool,
+}
+
+/// Parse the output of `git tag -l`, which has a tag name on each line, along with the output of
+/// `git for-each-ref --format=%(refname:strip=2)%00%(objecttype) refs/tags/` to tell which of them
+/// are annotated. The order of `git tag -l` is kept.
+fn parse_tags(names: &str, object_types: &str) -> Vec<TagEntry> {
+    let object_types = object_types
+        .lines()
+        .filter_map(|line| line.split_once('\0'))
+        .collect::<HashMap<_, _>>();
+    names
+        .lines()
+        .filter(|name| !name.is_empty())
+        .map(|name| TagEntry {
+            name: name.to_string(),
+            annotated: object_types.get(name) == Some(&"tag"),
+        })
+        .collect()
+}
+
+/// The tags of the repository, newest version first.
+#[derive(Debug, Default)]
+pub struct TagList {
+    pub tags: Vec<TagEntry>,
+    /// The index of the selected tag.
+    pub cursor: usize,
+    /// The output of `git show` for the selected tag, if it's being shown.
+    show: Pager,
+}
+
+impl render::Render for TagList {
+    fn render(&self, f: &mut Renderer) -> fmt::Result {
+        use fmt::Write;
+        let config = CONFIG.get().expect("config wasn't initialised");
+
+        if self.show.is_open() {
+            return self.show.render(f);
+        }
+
+        if self.tags.is_empty() {
+            return write!(
+                f,
+                "{}No tags.{}\r\n\nPress c to create one at HEAD.",
+                SetForegroundColor(config.colors.heading),
+                SetForegroundColor(config.colors.foreground),
+            );
+        }
+
+        for (i, tag) in self.tags.iter().enumerate() {
+            if i == self.cursor {
+                f.insert_cursor();
+                write!(f, "{}", Attribute::Reverse)?;
+            }
+            let color = if tag.annotated {
+                config.colors.heading
+            } else {
+                config.colors.hunk_head
+            };
+            write!(
+                f,
+                "\r{}{}{ResetAttributes}",
+                SetForegroundColor(color),
+                tag.name
+            )?;
+            if tag.annotated {
+                write!(f, " {}(annotated){ResetAttributes}", Attribute::Dim)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl TagList {
+    pub fn fetch(&mut self) -> Result<()> {
+        self.show.close();
+
+        let names = git_process(&["tag", "-l", "--sort=-version:refname"])?;
+        let object_types = git_process(&[
+            "for-each-ref",
+            "--format=%(refname:strip=2)%00%(objecttype)",
+            "refs/tags/",
+        ])?;
+        self.tags = parse_tags(
+            std::str::from_utf8(&names.stdout).context("broken stdout from `git tag`")?,
+            std::str::from_utf8(&object_types.stdout)
+                .context("broken stdout from `git for-each-ref`")?,
+        );
+        self.cursor = self.cursor.min(self.tags.len().saturating_sub(1));
+        Ok(())
+    }
+
+    /// Handle a key press. Returns `true` when the user wants to leave the tag list.
+    pub fn handle_input(
+        &mut self,
+        key_event: KeyEvent,
+        action: Option<&Action>,
+        minibuffer: &mut MiniBuffer,
+        view: &mut View,
+    ) -> Result<bool> {
+        if self.show.handle_input(key_event, action) {
+            return Ok(false);
+        }
+
+        let last_tag = self.tags.len().saturating_sub(1);
+        match action {
+            Some(Action::MoveDown) => self.cursor = (self.cursor + 1).min(last_tag),
+            Some(Action::MoveUp) => self.cursor = self.cursor.saturating_sub(1),
+            Some(Action::GotoTop) => self.cursor = 0,
+            Some(Action::GotoBottom) => self.cursor = last_tag,
+            _ => {}
+        }
+        match key_event.code {
+            KeyCode::Esc | KeyCode::Char('q') => return Ok(true),
+            KeyCode::Char('c') => {
+                log::new_tag("HEAD", View::TagList, minibuffer, view);
+                return Ok(false);
+            }
+            _ => {}
+        }
+
+        let Some(tag) = self.tags.get(self.cursor) else {
+            return Ok(false);
+        };
+        match key_event.code {
+            KeyCode::Enter => {
+                let output = git_process(&[
+                    "-c",
+                    "core.quotePath=false",
+                    "show",
+                    "--no-ext-diff",
+                    "--no-color",
+                    &format!("refs/tags/{}", tag.name),
+                ])?;
+                if !output.status.success() {
+                    MiniBuffer::push_command_output(&output);
+                    return Ok(false);
+                }
+                self.show.open(&output.stdout, false);
+            }
+            KeyCode::Char('d') => {
+                let name = tag.name.clone();
+                minibuffer.confirm(
+                    Rc::new(move || {
+                        MiniBuffer::push_command_output(&git_process(&["tag", "-d", &name])?);
+                        status::REFRESH_FLAG.store(true, Ordering::Release);
+                        Ok(())
+                    }),
+                    &format!("Delete tag {}?", tag.name),
+                    view,
+                    View::TagList,
+                );
+            }
+            KeyCode::Char('p') => command::push_tag(&tag.name)?,
+            _ => {}
+        }
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TagEntry;
+
+    #[test]
+    fn parse_tags() {
+        const NAMES: &str = "v0.10.0\nv0.6.4\nnightly\n";
+        const OBJECT_TYPES: &str = "nightly\0commit\nv0.10.0\0tag\nv0.6.4\0tag\n";
+        let tag = |name: &str, annotated| TagEntry {
+            name: name.to_string(),
+            annotated,
+        };
+        assert_eq!(
+            super::parse_tags(NAMES, OBJECT_TYPES),
+            [
+                tag("v0.10.0", true),
+                tag("v0.6.4", true),
+                tag("nightly", false)
+            ]
+        );
+    }
+}
@@ -0,0 +1,147 @@
+//! Module relating to refreshing the status automatically when the repository changes outside of
+//! gex, e.g. when a file is saved in an editor.
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc,
+    },
+    thread,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Context, Result};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+
+use crate::parse::{self, StatusEntry};
+
+/// How long the repository has to go without changing before the status is checked.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// The fingerprint of the repository when the status was last fetched.
+static LAST_FETCHED: AtomicU64 = AtomicU64::new(0);
+/// Set when the repository has changed since the status was last fetched.
+static CHANGED: AtomicBool = AtomicBool::new(false);
+
+/// A fingerprint of the state of the repository, from the output of `git status --porcelain=v2
+/// --branch --ignored=matching -z`. The modification times of the changed files are included too,
+/// since editing a file that's already modified doesn't change its status.
+fn fingerprint(status: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    status.hash(&mut hasher);
+    let entries = std::str::from_utf8(status)
+        .ok()
+        .and_then(|status| parse::parse_porcelain_status(status).ok())
+        .map(|status| status.entries)
+        .unwrap_or_default();
+    for entry in entries {
+        let path = match entry {
+            StatusEntry::Changed { path, .. }
+            | StatusEntry::Renamed { path, .. }
+            | StatusEntry::Unmerged { path, .. }
+            | StatusEntry::Untracked(path) => path,
+            // The state of a submodule's own working tree is in the status already, and ignored
+            // paths can be whole directories that aren't worth looking into.
+            StatusEntry::Submodule { .. } | StatusEntry::Ignored(_) => continue,
+        };
+        if let Ok(metadata) = fs::metadata(path) {
+            metadata.len().hash(&mut hasher);
+            metadata.modified().ok().hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+/// Remember the state of the repository that the status was just fetched from, so that changes
+/// made by gex itself don't cause another refresh.
+pub fn record_fetch(status: &[u8]) {
+    LAST_FETCHED.store(fingerprint(status), Ordering::Release);
+}
+
+/// Whether the repository has changed since the status was last fetched. The change is only
+/// reported once.
+pub fn take_change() -> bool {
+    CHANGED.swap(false, Ordering::Acquire)
+}
+
+/// Start watching the working tree and the index and HEAD in the background.
+///
+/// Events are debounced, so that several writes in quick succession only cause one refresh. After
+/// that `git status` is checked, so that changes that don't affect it, like building into an
+/// ignored directory, and changes made by gex itself, which fetches the status anyway, don't cause
+/// a refresh.
+pub fn start() -> Result<()> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--show-toplevel", "--absolute-git-dir"])
+        .output()
+        .context("failed to run `git rev-parse`")?;
+    let stdout =
+        String::from_utf8(output.stdout).context("malformed stdout from `git rev-parse`")?;
+    let mut dirs = stdout.lines().map(PathBuf::from);
+    let (Some(work_tree), Some(git_dir)) = (dirs.next(), dirs.next()) else {
+        return Err(anyhow!("couldn't find the repository to watch"));
+    };
+
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender).context("failed to start watching")?;
+    watcher
+        .watch(&work_tree, RecursiveMode::Recursive)
+        .context("failed to watch the working tree")?;
+    // The git directory of a worktree or of a repository with `--separate-git-dir` is elsewhere.
+    if !git_dir.starts_with(&work_tree) {
+        watcher
+            .watch(&git_dir, RecursiveMode::NonRecursive)
+            .context("failed to watch the git directory")?;
+    }
+
+    thread::spawn(move || {
+        // The watcher stops when it's dropped.
+        let _watcher = watcher;
+        while let Ok(event) = receiver.recv() {
+            if !event.is_ok_and(|event| relevant(&event, &git_dir)) {
+                continue;
+            }
+            while receiver.recv_timeout(DEBOUNCE).is_ok() {}
+            // Don't update the index, which could get in the way of git commands run meanwhile.
+            let Ok(output) = Command::new("git")
+                .args([
+                    "--no-optional-locks",
+                    "status",
+                    "--porcelain=v2",
+                    "--branch",
+                    "--ignored=matching",
+                    "-z",
+                ])
+                .output()
+            else {
+                continue;
+            };
+            if !output.status.success() {
+                continue;
+            }
+            let current = fingerprint(&output.stdout);
+            if current != LAST_FETCHED.load(Ordering::Acquire) {
+                CHANGED.store(true, Ordering::Release);
+                // Until the status is fetched, there's no need to report it again.
+                LAST_FETCHED.store(current, Ordering::Release);
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Whether `event` could have changed the status. Inside the git directory, only the index, HEAD
+/// and the refs matter, which leaves out the churn in `.git/objects` during a fetch or gc.
+fn relevant(event: &Event, git_dir: &Path) -> bool {
+    !matches!(event.kind, EventKind::Access(_))
+        && event.paths.iter().any(|path| {
+            path.strip_prefix(git_dir).map_or(true, |path| {
+                path == Path::new("index") || path == Path::new("HEAD") || path.starts_with("refs")
+            })
+        })
+}
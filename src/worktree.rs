@@ -0,0 +1,275 @@
+//! Module relating to the list of worktrees.
+
+use std::{fmt, path::Path, rc::Rc, sync::atomic::Ordering};
+
+use anyhow::{Context, Result};
+use crossterm::{
+    cursor,
+    event::{KeyCode, KeyEvent},
+    style::{Attribute, SetForegroundColor},
+};
+
+use crate::{
+    config::{Action, CONFIG},
+    git_process,
+    minibuffer::{MessageType, MiniBuffer},
+    render::{self, Renderer, ResetAttributes},
+    status, View,
+};
+
+/// A worktree from `git worktree list --porcelain`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct WorktreeEntry {
+    pub path: String,
+    /// The hash of the commit that's checked out. This is empty for a bare repository.
+    pub head: String,
+    /// The name of the branch that's checked out, or `"detached"` if there isn't one.
+    pub branch: String,
+    /// Whether this is the main worktree, which the others were added to.
+    pub is_main: bool,
+}
+
+/// Parse the output of `git worktree list --porcelain`, which has a paragraph of `key value` lines
+/// for each worktree. The main worktree always comes first.
+fn parse_worktrees(output: &str) -> Result<Vec<WorktreeEntry>> {
+    let mut worktrees = Vec::new();
+    for paragraph in output.split("\n\n").filter(|p| !p.trim().is_empty()) {
+        let mut lines = paragraph.lines();
+        let path = lines
+            .next()
+            .and_then(|line| line.strip_prefix("worktree "))
+            .with_context(|| {
+                format!("unexpected paragraph in `git worktree list` output: `{paragraph}`")
+            })?;
+        let mut worktree = WorktreeEntry {
+            path: path.to_string(),
+            head: String::new(),
+            branch: "detached".to_string(),
+            is_main: worktrees.is_empty(),
+        };
+        for line in lines {
+            if let Some(head) = line.strip_prefix("HEAD ") {
+                head.clone_into(&mut worktree.head);
+            } else if let Some(branch) = line.strip_prefix("branch ") {
+                branch
+                    .strip_prefix("refs/heads/")
+                    .unwrap_or(branch)
+                    .clone_into(&mut worktree.branch);
+            }
+        }
+        worktrees.push(worktree);
+    }
+    Ok(worktrees)
+}
+
+/// The worktrees of the repository.
+#[derive(Debug, Default)]
+pub struct WorktreeList {
+    pub worktrees: Vec<WorktreeEntry>,
+    /// The index of the selected worktree.
+    pub cursor: usize,
+    /// The index of the worktree that gex is running in.
+    current: Option<usize>,
+}
+
+impl render::Render for WorktreeList {
+    fn render(&self, f: &mut Renderer) -> fmt::Result {
+        use fmt::Write;
+        let config = CONFIG.get().expect("config wasn't initialised");
+
+        let path_width = self
+            .worktrees
+            .iter()
+            .map(|worktree| worktree.path.chars().count())
+            .max()
+            .unwrap_or_default();
+        for (i, worktree) in self.worktrees.iter().enumerate() {
+            let current = self.current == Some(i);
+            if i == self.cursor {
+                f.insert_cursor();
+            }
+            write!(f, "\r{}", if current { "* " } else { "  " })?;
+            if current {
+                write!(f, "{}", SetForegroundColor(config.colors.heading))?;
+            }
+            if i == self.cursor {
+                write!(f, "{}", Attribute::Reverse)?;
+            }
+            write!(
+                f,
+                "{:path_width$}{ResetAttributes} {}{:.8}{ResetAttributes} ",
+                worktree.path,
+                Attribute::Dim,
+                worktree.head,
+            )?;
+            if worktree.branch == "detached" {
+                write!(f, "{}(detached){ResetAttributes}", Attribute::Dim)?;
+            } else {
+                write!(f, "{}", worktree.branch)?;
+            }
+            if worktree.is_main {
+                write!(f, " {}(main){ResetAttributes}", Attribute::Dim)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl WorktreeList {
+    pub fn fetch(&mut self) -> Result<()> {
+        let output = git_process(&["worktree", "list", "--porcelain"])?;
+        self.worktrees = parse_worktrees(
+            std::str::from_utf8(&output.stdout)
+                .context("broken stdout from `git worktree list`")?,
+        )?;
+        // The working directory is the root of the worktree that gex was started in.
+        let current_dir = std::env::current_dir()
+            .and_then(|dir| dir.canonicalize())
+            .ok();
+        self.current = self.worktrees.iter().position(|worktree| {
+            current_dir.is_some() && Path::new(&worktree.path).canonicalize().ok() == current_dir
+        });
+        self.cursor = self.cursor.min(self.worktrees.len().saturating_sub(1));
+        Ok(())
+    }
+
+    /// The path of the selected worktree.
+    pub fn selected_path(&self) -> Option<&str> {
+        self.worktrees
+            .get(self.cursor)
+            .map(|worktree| worktree.path.as_str())
+    }
+
+    /// Handle a key press. Returns `true` when the user wants to leave the worktree list.
+    pub fn handle_input(
+        &mut self,
+        key_event: KeyEvent,
+        action: Option<&Action>,
+        minibuffer: &mut MiniBuffer,
+        view: &mut View,
+    ) -> bool {
+        let last_worktree = self.worktrees.len().saturating_sub(1);
+        match action {
+            Some(Action::MoveDown) => self.cursor = (self.cursor + 1).min(last_worktree),
+            Some(Action::MoveUp) => self.cursor = self.cursor.saturating_sub(1),
+            Some(Action::GotoTop) => self.cursor = 0,
+            Some(Action::GotoBottom) => self.cursor = last_worktree,
+            _ => {}
+        }
+        match key_event.code {
+            KeyCode::Esc | KeyCode::Char('q') => return true,
+            KeyCode::Char('a') => {
+                minibuffer.get_input(
+                    Rc::new(|input| {
+                        print!("{}", cursor::Hide);
+                        let Some(input) = input else {
+                            return Ok(());
+                        };
+                        let mut words = input.split_whitespace();
+                        let (Some(path), Some(branch), None) =
+                            (words.next(), words.next(), words.next())
+                        else {
+                            MiniBuffer::push(
+                                "Enter the path of the worktree followed by the branch to check \
+                                 out.",
+                                MessageType::Error,
+                            );
+                            return Ok(());
+                        };
+                        MiniBuffer::push_command_output(&git_process(&[
+                            "worktree", "add", path, branch,
+                        ])?);
+                        status::REFRESH_FLAG.store(true, Ordering::Release);
+                        Ok(())
+                    }),
+                    Some("Path and branch of the new worktree: "),
+                    view,
+                    View::WorktreeList,
+                );
+            }
+            KeyCode::Char('d') => {
+                let Some(worktree) = self.worktrees.get(self.cursor) else {
+                    return false;
+                };
+                if self.current == Some(self.cursor) {
+                    MiniBuffer::push("Can't remove the current worktree.", MessageType::Error);
+                    return false;
+                }
+                if worktree.is_main {
+                    MiniBuffer::push("Can't remove the main worktree.", MessageType::Error);
+                    return false;
+                }
+                let path = worktree.path.clone();
+                minibuffer.confirm(
+                    Rc::new(move || {
+                        MiniBuffer::push_command_output(&git_process(&[
+                            "worktree", "remove", &path,
+                        ])?);
+                        status::REFRESH_FLAG.store(true, Ordering::Release);
+                        Ok(())
+                    }),
+                    &format!("Remove worktree {}?", worktree.path),
+                    view,
+                    View::WorktreeList,
+                );
+            }
+            _ => {}
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WorktreeEntry;
+
+    #[test]
+    fn parse_worktrees() {
+        const INPUT: &str = "\
+worktree /home/user/gex
+HEAD 2bc8724a1f0e6d7c3b9a8e5f4d2c1b0a9e8f7d6c
+branch refs/heads/main
+
+worktree /home/user/gex feature
+HEAD 9e8f7d6c2bc8724a1f0e6d7c3b9a8e5f4d2c1b0a
+branch refs/heads/feature
+locked
+
+worktree /tmp/review
+HEAD 2bc8724a1f0e6d7c3b9a8e5f4d2c1b0a9e8f7d6c
+detached
+prunable gitdir file points to non-existent location
+
+";
+        let worktree = |path: &str, head: &str, branch: &str, is_main| WorktreeEntry {
+            path: path.to_string(),
+            head: head.to_string(),
+            branch: branch.to_string(),
+            is_main,
+        };
+        assert_eq!(
+            super::parse_worktrees(INPUT).unwrap(),
+            [
+                worktree(
+                    "/home/user/gex",
+                    "2bc8724a1f0e6d7c3b9a8e5f4d2c1b0a9e8f7d6c",
+                    "main",
+                    true
+                ),
+                worktree(
+                    "/home/user/gex feature",
+                    "9e8f7d6c2bc8724a1f0e6d7c3b9a8e5f4d2c1b0a",
+                    "feature",
+                    false
+                ),
+                worktree(
+                    "/tmp/review",
+                    "2bc8724a1f0e6d7c3b9a8e5f4d2c1b0a9e8f7d6c",
+                    "detached",
+                    false
+                ),
+            ]
+        );
+    }
+}